@@ -0,0 +1,634 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use navsharp::{idx, Idx, IdxVec};
+
+use crate::{
+    definitions::{
+        builtins::{NativeFunction, NativeRegistry},
+        bytecode::Bytecode,
+        eval::{ASTEval, Strings, Value},
+        debugger::Debugger,
+        limits::{InterruptHandle, Limits},
+        profiler::{Profile, Profiler},
+        runtime_error::RuntimeError,
+        lexer::{Lexer, Token},
+        parser::Parser,
+        visitor::ASTVisitor,
+        vm::VirtualMachine,
+        AssignExpr, Ast, BlockExpr, Body, BoolExpr, CallExpr, ConstDeclaration, Expr,
+        FuncDeclaration, Item, ItemId, ItemKind, LetStmt, NullExpr, NumberExpr, Stmt, UnaryExpr,
+        VarExpr,
+    },
+    diagnostics::{DiagnosticsBag, DiagnosticsBagCell},
+    lint::{config::LintConfig, LintDriver},
+    text::{
+        similarity::closest_matches,
+        source_map::{FileId, SourceMap},
+        span::TextSpan,
+    },
+    typecheck::{checker::TypeChecker, flow::FlowAnalyzer, Type},
+};
+
+idx!(FunctionIdx);
+idx!(VariableIdx);
+idx!(TypeIdx);
+
+#[derive(Debug, Clone)]
+pub struct VariableSymbol {
+    pub name: String,
+    pub ty: Type,
+    pub is_global: bool,
+    pub is_const: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum FunctionBody {
+    Declared(Body),
+    Native(NativeFunction),
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionSymbol {
+    pub name: String,
+    pub parameters: Vec<VariableIdx>,
+    pub body: FunctionBody,
+    pub return_type: Type,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldSymbol {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeSymbolKind {
+    Builtin(Type),
+    /// The aliased type, `Type::Unresolved` until the type checker gets to it.
+    Alias(Type),
+    Struct(Vec<FieldSymbol>),
+    Enum(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeSymbol {
+    pub name: String,
+    pub kind: TypeSymbolKind,
+}
+
+/// Every symbol of a program. Locals live here too; scoping is only a
+/// concern of the resolver, which links each use to a unique index.
+#[derive(Debug, Clone)]
+pub struct GlobalScope {
+    pub variables: IdxVec<VariableIdx, VariableSymbol>,
+    pub functions: IdxVec<FunctionIdx, FunctionSymbol>,
+    pub types: IdxVec<TypeIdx, TypeSymbol>,
+    pub constants: HashMap<VariableIdx, Value>,
+    pub global_variables: Vec<VariableIdx>,
+}
+
+impl GlobalScope {
+    /// A scope declaring the builtin types and functions.
+    pub fn new() -> Self {
+        Self::with_natives(&NativeRegistry::with_builtins())
+    }
+
+    /// A scope declaring the builtin types and the functions of `natives`.
+    pub fn with_natives(natives: &NativeRegistry) -> Self {
+        let mut global_scope = Self {
+            variables: IdxVec::new(),
+            functions: IdxVec::new(),
+            types: IdxVec::new(),
+            constants: HashMap::new(),
+            global_variables: Vec::new(),
+        };
+        for (name, ty) in Type::builtins() {
+            global_scope.types.push(TypeSymbol {
+                name: name.to_string(),
+                kind: TypeSymbolKind::Builtin(ty),
+            });
+        }
+        for native in natives.functions() {
+            let parameters = native
+                .parameters
+                .iter()
+                .map(|(name, ty)| global_scope.declare_variable(name, ty.clone(), false, false))
+                .collect();
+            let function_idx = global_scope.declare_function(
+                &native.name,
+                parameters,
+                FunctionBody::Native(native.clone()),
+            );
+            global_scope.functions[function_idx].return_type = native.return_type.clone();
+        }
+        global_scope
+    }
+
+    pub fn declare_variable(
+        &mut self,
+        name: &str,
+        ty: Type,
+        is_global: bool,
+        is_const: bool,
+    ) -> VariableIdx {
+        let variable_idx = self.variables.push(VariableSymbol {
+            name: name.to_string(),
+            ty,
+            is_global,
+            is_const,
+        });
+        if is_global {
+            self.global_variables.push(variable_idx);
+        }
+        variable_idx
+    }
+
+    /// The most recent global of that name, since a global `let` may
+    /// shadow an earlier one.
+    pub fn lookup_global_variable(&self, name: &str) -> Option<VariableIdx> {
+        self.global_variables
+            .iter()
+            .rev()
+            .find(|variable_idx| self.variables[**variable_idx].name == name)
+            .copied()
+    }
+
+    /// Declares a function even if the name is taken, so that the body of a
+    /// duplicate is still checked. Calls resolve to the first declaration.
+    pub fn declare_function(
+        &mut self,
+        name: &str,
+        parameters: Vec<VariableIdx>,
+        body: FunctionBody,
+    ) -> FunctionIdx {
+        self.functions.push(FunctionSymbol {
+            name: name.to_string(),
+            parameters,
+            body,
+            return_type: Type::Void,
+        })
+    }
+
+    pub fn lookup_function(&self, name: &str) -> Option<FunctionIdx> {
+        self.functions
+            .indexed_iter()
+            .find(|(_, function)| function.name == name)
+            .map(|(function_idx, _)| function_idx)
+    }
+
+    /// Returns the existing type as the error if the name is taken.
+    pub fn declare_type(&mut self, name: String, kind: TypeSymbolKind) -> Result<TypeIdx, TypeIdx> {
+        if let Some(existing) = self.lookup_type(&name) {
+            return Err(existing);
+        }
+        Ok(self.types.push(TypeSymbol { name, kind }))
+    }
+
+    pub fn lookup_type(&self, name: &str) -> Option<TypeIdx> {
+        self.types
+            .indexed_iter()
+            .find(|(_, symbol)| symbol.name == name)
+            .map(|(type_idx, _)| type_idx)
+    }
+}
+
+impl Default for GlobalScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The type a name refers to once the type checker has declared every type,
+/// or `None` if there is no such type.
+pub fn resolve_type_from_string(global_scope: &GlobalScope, type_name: &str) -> Option<Type> {
+    let type_idx = global_scope.lookup_type(type_name)?;
+    let symbol = &global_scope.types[type_idx];
+    match &symbol.kind {
+        TypeSymbolKind::Builtin(ty) => Some(ty.clone()),
+        TypeSymbolKind::Struct(_) => Some(Type::Struct(type_idx, symbol.name.clone())),
+        TypeSymbolKind::Enum(_) => Some(Type::Enum(type_idx, symbol.name.clone())),
+        TypeSymbolKind::Alias(Type::Unresolved) => None,
+        TypeSymbolKind::Alias(ty) => Some(ty.clone()),
+    }
+}
+
+/// Links every variable use, assignment and call to the symbol it refers
+/// to.
+///
+/// Functions and consts are declared before anything else, so they can be
+/// used above their definition. Variables are lexically scoped: a `let` is
+/// visible from the next statement to the end of the enclosing body or
+/// block, and may shadow any earlier variable. Parameters are locals of
+/// the function's outermost scope. A `let` outside of any body declares a
+/// global, visible to the functions defined after it.
+pub struct Resolver<'a> {
+    global_scope: &'a mut GlobalScope,
+    diagnostics: DiagnosticsBagCell,
+    source_map: &'a SourceMap,
+    scopes: Vec<HashMap<String, VariableIdx>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(
+        global_scope: &'a mut GlobalScope,
+        diagnostics: DiagnosticsBagCell,
+        source_map: &'a SourceMap,
+    ) -> Self {
+        Self {
+            global_scope,
+            diagnostics,
+            source_map,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, ast: &mut Ast) {
+        let items: Vec<Item> = ast.items.iter().cloned().collect();
+        for item in items {
+            match &item.kind {
+                ItemKind::Function(func_decl) => {
+                    let function_idx = self.declare_function(func_decl);
+                    ast.set_item_function(item.id, function_idx);
+                }
+                ItemKind::Const(const_decl) => {
+                    let variable_idx = self.declare_const(const_decl);
+                    ast.set_item_variable(item.id, variable_idx);
+                }
+                _ => {}
+            }
+        }
+        ast.visit(self);
+    }
+
+    fn declare_function(&mut self, func_decl: &FuncDeclaration) -> FunctionIdx {
+        let name = func_decl.identifier.span.literal(self.source_map);
+        if self.global_scope.lookup_function(name).is_some() {
+            self.diagnostics
+                .borrow_mut()
+                .report_function_already_declared(name, &func_decl.identifier.span);
+        }
+        let mut parameters: Vec<VariableIdx> = Vec::new();
+        for parameter in &func_decl.parameters {
+            let name = parameter.identifier.span.literal(self.source_map);
+            if parameters
+                .iter()
+                .any(|existing| self.global_scope.variables[*existing].name == name)
+            {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_duplicate_parameter(name, &parameter.identifier.span);
+            }
+            parameters.push(
+                self.global_scope
+                    .declare_variable(name, Type::Unresolved, false, false),
+            );
+        }
+        self.global_scope.declare_function(
+            name,
+            parameters,
+            FunctionBody::Declared(func_decl.body.clone()),
+        )
+    }
+
+    fn declare_const(&mut self, const_decl: &ConstDeclaration) -> VariableIdx {
+        let name = const_decl.identifier.span.literal(self.source_map);
+        if let Some(existing) = self.global_scope.lookup_global_variable(name) {
+            let existing_is_const = self.global_scope.variables[existing].is_const;
+            self.diagnostics.borrow_mut().report_variable_already_declared(
+                name,
+                existing_is_const,
+                &const_decl.identifier.span,
+            );
+        }
+        self.global_scope
+            .declare_variable(name, Type::Unresolved, true, true)
+    }
+
+    fn declare_variable(&mut self, identifier: &Token) -> VariableIdx {
+        let name = identifier.span.literal(self.source_map);
+        let is_global = self.scopes.is_empty();
+        if is_global {
+            // Consts can't be shadowed, the `let` would silently hide them
+            // from every function declared after it.
+            if let Some(existing) = self.global_scope.lookup_global_variable(name) {
+                if self.global_scope.variables[existing].is_const {
+                    self.diagnostics
+                        .borrow_mut()
+                        .report_variable_already_declared(name, true, &identifier.span);
+                }
+            }
+        }
+        let variable_idx = self
+            .global_scope
+            .declare_variable(name, Type::Unresolved, is_global, false);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), variable_idx);
+        }
+        variable_idx
+    }
+
+    fn lookup_variable(&self, name: &str) -> Option<VariableIdx> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .or_else(|| self.global_scope.lookup_global_variable(name))
+    }
+
+    /// The visible variables named closest to `name`, for suggestions.
+    fn similar_variables(&self, name: &str) -> Vec<&str> {
+        let locals = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.keys().map(String::as_str));
+        let globals = self
+            .global_scope
+            .global_variables
+            .iter()
+            .map(|variable_idx| self.global_scope.variables[*variable_idx].name.as_str());
+        closest_matches(name, locals.chain(globals), 3)
+    }
+
+    /// The functions named closest to `name`, for suggestions.
+    fn similar_functions(&self, name: &str) -> Vec<&str> {
+        let functions = self
+            .global_scope
+            .functions
+            .iter()
+            .map(|function| function.name.as_str());
+        closest_matches(name, functions, 3)
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+}
+
+impl ASTVisitor for Resolver<'_> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        let function = &self.global_scope.functions[func_decl.idx];
+        let parameters: HashMap<String, VariableIdx> = function
+            .parameters
+            .iter()
+            .map(|parameter| (self.global_scope.variables[*parameter].name.clone(), *parameter))
+            .collect();
+        self.scopes.push(parameters);
+        self.visit_body(ast, &func_decl.body);
+        self.pop_scope();
+    }
+
+    fn visit_body(&mut self, ast: &mut Ast, body: &Body) {
+        self.push_scope();
+        self.visit_body_default(ast, body);
+        self.pop_scope();
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, _expr: &Expr) {
+        self.push_scope();
+        for stmt in &block_expr.stmts {
+            self.visit_statement(ast, *stmt);
+        }
+        self.pop_scope();
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, stmt: &Stmt) {
+        // The initializer still sees the variable being shadowed, so
+        // `let x = x + 1` refers to the outer `x`.
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+        let variable_idx = self.declare_variable(&let_stmt.identifier);
+        ast.set_let_variable(stmt.id, variable_idx);
+    }
+
+    fn visit_const_decl(&mut self, ast: &mut Ast, const_decl: &ConstDeclaration, _item_id: ItemId) {
+        self.visit_expression(ast, const_decl.initializer);
+    }
+
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expr: &CallExpr, expr: &Expr) {
+        for argument in &call_expr.arguments {
+            self.visit_expression(ast, *argument);
+        }
+        let name = call_expr.function_name(self.source_map);
+        match self.global_scope.lookup_function(name) {
+            Some(function_idx) => ast.set_function(expr.id, function_idx),
+            None => self
+                .diagnostics
+                .borrow_mut()
+                .report_undeclared_function(
+                    name,
+                    &call_expr.callee.span,
+                    &self.similar_functions(name),
+                ),
+        }
+    }
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, expr: &Expr) {
+        self.visit_expression(ast, assign_expr.expression);
+        let name = assign_expr.identifier.span.literal(self.source_map);
+        let Some(variable_idx) = self.lookup_variable(name) else {
+            self.diagnostics.borrow_mut().report_undeclared_variable(
+                name,
+                &assign_expr.identifier.span,
+                &self.similar_variables(name),
+            );
+            return;
+        };
+        if self.global_scope.variables[variable_idx].is_const {
+            self.diagnostics
+                .borrow_mut()
+                .report_cannot_assign_to_const(name, &assign_expr.identifier.span);
+        }
+        ast.set_variable(expr.id, variable_idx);
+    }
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, var_expr: &VarExpr, expr: &Expr) {
+        let name = var_expr.identifier(self.source_map);
+        match self.lookup_variable(name) {
+            Some(variable_idx) => ast.set_variable(expr.id, variable_idx),
+            None => self
+                .diagnostics
+                .borrow_mut()
+                .report_undeclared_variable(
+                    name,
+                    &var_expr.identifier.span,
+                    &self.similar_variables(name),
+                ),
+        }
+    }
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
+
+/// A program that made it through every compiler pass and is ready to run.
+pub struct CompilationUnit {
+    pub ast: Ast,
+    pub diagnostics_bag: DiagnosticsBagCell,
+    pub global_scope: GlobalScope,
+    strings: Strings,
+}
+
+impl CompilationUnit {
+    pub fn compile(
+        source_map: &SourceMap,
+        file_id: FileId,
+    ) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        Self::compile_with_lints(source_map, file_id, &LintConfig::new())
+    }
+
+    /// Compiles like `compile`, reporting lints at the levels of
+    /// `lint_config`. Lints only run on programs without errors.
+    pub fn compile_with_lints(
+        source_map: &SourceMap,
+        file_id: FileId,
+        lint_config: &LintConfig,
+    ) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        Self::compile_files(source_map, &[file_id], lint_config)
+    }
+
+    /// Compiles the files as one program, their items in the order of
+    /// `file_ids`. Each file is parsed on its own, so a construct can't span
+    /// two of them.
+    pub fn compile_files(
+        source_map: &SourceMap,
+        file_ids: &[FileId],
+        lint_config: &LintConfig,
+    ) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        Self::compile_files_with_natives(
+            source_map,
+            file_ids,
+            lint_config,
+            &NativeRegistry::with_builtins(),
+        )
+    }
+
+    /// Compiles like `compile_files`, with the native functions of
+    /// `natives` in place of the builtins.
+    pub fn compile_files_with_natives(
+        source_map: &SourceMap,
+        file_ids: &[FileId],
+        lint_config: &LintConfig,
+        natives: &NativeRegistry,
+    ) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        let diagnostics_bag: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        for file_id in file_ids {
+            let tokens = Self::lex(source_map, *file_id);
+            let mut parser = Parser::new(tokens, diagnostics_bag.clone(), &mut ast, source_map);
+            parser.parse();
+        }
+        Self::check_diagnostics(&diagnostics_bag)?;
+
+        let mut global_scope = GlobalScope::with_natives(natives);
+        Resolver::new(&mut global_scope, diagnostics_bag.clone(), source_map).resolve(&mut ast);
+        Self::check_diagnostics(&diagnostics_bag)?;
+        TypeChecker::new(&mut global_scope, diagnostics_bag.clone(), source_map).check(&mut ast);
+        let unreachable = FlowAnalyzer::new(&global_scope, diagnostics_bag.clone()).analyze(&mut ast);
+        Self::check_diagnostics(&diagnostics_bag)?;
+
+        let (constants, strings) = {
+            let mut const_eval = ASTEval::new_const(&global_scope);
+            let constants = const_eval.evaluate_consts(&mut ast, &diagnostics_bag);
+            (constants, const_eval.strings)
+        };
+        global_scope.constants = constants;
+        Self::check_diagnostics(&diagnostics_bag)?;
+
+        LintDriver::with_builtin_lints(lint_config, &global_scope, source_map)
+            .with_diagnostics(unreachable)
+            .run(&mut ast, &diagnostics_bag);
+        Self::check_diagnostics(&diagnostics_bag)?;
+
+        Ok(CompilationUnit {
+            ast,
+            diagnostics_bag,
+            global_scope,
+            strings,
+        })
+    }
+
+    /// Every token of the file, whitespace and comments included, ending
+    /// with `Eof`.
+    pub fn lex(source_map: &SourceMap, file_id: FileId) -> Vec<Token> {
+        let mut lexer = Lexer::new(source_map.get(file_id).text.text(), file_id);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    /// Parses the file on its own, without resolving or checking it.
+    pub fn parse(source_map: &SourceMap, file_id: FileId) -> Result<Ast, DiagnosticsBagCell> {
+        let diagnostics_bag: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        let tokens = Self::lex(source_map, file_id);
+        Parser::new(tokens, diagnostics_bag.clone(), &mut ast, source_map).parse();
+        Self::check_diagnostics(&diagnostics_bag)?;
+        Ok(ast)
+    }
+
+    /// The strings interned while compiling, which consts may refer to. An
+    /// evaluator running the program must start with them.
+    pub fn strings(&self) -> &Strings {
+        &self.strings
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        self.run_with_limits(Limits::new(), InterruptHandle::new())
+    }
+
+    /// Runs the program within `limits`, stopping early if `interrupt_handle`
+    /// is interrupted from another thread. The program is compiled to
+    /// bytecode first, which runs much faster than walking the tree.
+    pub fn run_with_limits(
+        &mut self,
+        limits: Limits,
+        interrupt_handle: InterruptHandle,
+    ) -> Result<(), RuntimeError> {
+        let bytecode = Bytecode::compile(&self.ast, &self.global_scope, limits);
+        let mut vm =
+            VirtualMachine::new(&bytecode, &self.global_scope).with_interrupt_handle(interrupt_handle);
+        vm.strings = self.strings.clone();
+        vm.run()
+    }
+
+    /// Runs the program while `profiler` records it, returning what it
+    /// recorded even if the program failed.
+    pub fn run_with_profiler(&mut self, profiler: Profiler) -> (Result<(), RuntimeError>, Profile) {
+        let mut eval = ASTEval::new(&self.global_scope).with_profiler(profiler);
+        eval.strings = self.strings.clone();
+        let result = eval.run(&mut self.ast);
+        let profile = eval.take_profile(&self.ast).unwrap();
+        (result, profile)
+    }
+
+    /// Runs the program under `debugger`, which pauses it at breakpoints
+    /// and steps.
+    pub fn run_with_debugger(&mut self, debugger: Debugger) -> Result<(), RuntimeError> {
+        let mut eval = ASTEval::new(&self.global_scope).with_debugger(debugger);
+        eval.strings = self.strings.clone();
+        eval.run(&mut self.ast)
+    }
+
+    fn check_diagnostics(diagnostics_bag: &DiagnosticsBagCell) -> Result<(), DiagnosticsBagCell> {
+        if diagnostics_bag.borrow().has_errors() {
+            return Err(diagnostics_bag.clone());
+        }
+        Ok(())
+    }
+}
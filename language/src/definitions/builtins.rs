@@ -0,0 +1,243 @@
+use std::{
+    fmt::{Debug, Formatter},
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+use crate::{
+    definitions::{
+        eval::{StringIdx, Strings, Value},
+        runtime_error::RuntimeErrorKind,
+    },
+    typecheck::Type,
+};
+
+/// What a native function can use of the evaluator calling it.
+pub struct NativeContext<'e> {
+    pub strings: &'e mut Strings,
+    output: &'e mut dyn Write,
+    /// `None` reads from stdin, only locking it while reading so that a
+    /// front end can read from it too.
+    input: Option<&'e mut dyn BufRead>,
+}
+
+impl<'e> NativeContext<'e> {
+    pub(crate) fn new(
+        strings: &'e mut Strings,
+        output: &'e mut dyn Write,
+        input: Option<&'e mut dyn BufRead>,
+    ) -> Self {
+        Self {
+            strings,
+            output,
+            input,
+        }
+    }
+
+    pub fn string(&self, idx: StringIdx) -> &str {
+        self.strings.get(idx)
+    }
+
+    pub fn intern(&mut self, value: &str) -> Value {
+        Value::String(self.strings.intern(value))
+    }
+
+    /// Writes to the program's output.
+    pub fn write(&mut self, text: &str) -> Result<(), RuntimeErrorKind> {
+        self.output
+            .write_all(text.as_bytes())
+            .and_then(|()| self.output.flush())
+            .map_err(|error| RuntimeErrorKind::Native(format!("could not write output: {}", error)))
+    }
+
+    /// Reads a line of the program's input without its line ending, or
+    /// `None` at the end of the input.
+    pub fn read_line(&mut self) -> Result<Option<String>, RuntimeErrorKind> {
+        let mut line = String::new();
+        let read = match &mut self.input {
+            Some(input) => input.read_line(&mut line),
+            None => io::stdin().lock().read_line(&mut line),
+        };
+        match read {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(line.trim_end_matches(['\n', '\r']).to_string())),
+            Err(error) => Err(RuntimeErrorKind::Native(format!(
+                "could not read input: {}",
+                error
+            ))),
+        }
+    }
+}
+
+/// The Rust implementation of a native function. The arguments match the
+/// declared parameters, since calls are type-checked, and the value it
+/// returns is checked against the declared return type.
+pub type NativeFn = dyn Fn(&mut NativeContext, &[Value]) -> Result<Value, RuntimeErrorKind>;
+
+/// A function implemented in Rust, declared in the global scope next to
+/// user functions so that calls to it are resolved and type-checked the
+/// same way.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub parameters: Vec<(String, Type)>,
+    pub return_type: Type,
+    function: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn call(
+        &self,
+        context: &mut NativeContext,
+        arguments: &[Value],
+    ) -> Result<Value, RuntimeErrorKind> {
+        let value = (self.function)(context, arguments)?;
+        if value.has_type(&self.return_type) {
+            Ok(value)
+        } else {
+            Err(RuntimeErrorKind::NativeReturnType {
+                name: self.name.clone(),
+                expected: self.return_type.to_string(),
+                found: value.type_name(),
+            })
+        }
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("parameters", &self.parameters)
+            .field("return_type", &self.return_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The native functions a program can call.
+#[derive(Debug, Clone)]
+pub struct NativeRegistry {
+    functions: Vec<NativeFunction>,
+}
+
+impl NativeRegistry {
+    /// A registry without any function, not even the builtins.
+    pub fn new() -> Self {
+        Self {
+            functions: Vec::new(),
+        }
+    }
+
+    /// The functions provided by the language itself:
+    ///
+    /// - `len(s: string) -> int`, the number of characters in a string.
+    /// - `print(text: string)` and `println(text: string)`, which write to
+    ///   the output, the latter followed by a newline.
+    /// - `assert(condition: bool)`, which fails if the condition is false.
+    /// - `read_line() -> string?`, the next line of the input, or `null` at
+    ///   its end.
+    /// - `exit(code: int)`, which ends the program with an exit code.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            "len",
+            &[("s", Type::String)],
+            Type::Int,
+            |context, arguments| {
+                let value = context.string(arguments[0].expect_string()?);
+                Ok(Value::Number(value.chars().count() as i64))
+            },
+        );
+        registry.register(
+            "print",
+            &[("text", Type::String)],
+            Type::Void,
+            |context, arguments| {
+                let text = context.string(arguments[0].expect_string()?).to_string();
+                context.write(&text)?;
+                Ok(Value::Void)
+            },
+        );
+        registry.register(
+            "println",
+            &[("text", Type::String)],
+            Type::Void,
+            |context, arguments| {
+                let text = context.string(arguments[0].expect_string()?).to_string();
+                context.write(&text)?;
+                context.write("\n")?;
+                Ok(Value::Void)
+            },
+        );
+        registry.register(
+            "assert",
+            &[("condition", Type::Bool)],
+            Type::Void,
+            |_, arguments| {
+                if arguments[0].expect_boolean()? {
+                    Ok(Value::Void)
+                } else {
+                    Err(RuntimeErrorKind::AssertionFailed)
+                }
+            },
+        );
+        registry.register(
+            "read_line",
+            &[],
+            Type::Nullable(Box::new(Type::String)),
+            |context, _| match context.read_line()? {
+                Some(line) => Ok(context.intern(&line)),
+                None => Ok(Value::Null),
+            },
+        );
+        registry.register(
+            "exit",
+            &[("code", Type::Int)],
+            Type::Void,
+            |_, arguments| {
+                Err(RuntimeErrorKind::Exit {
+                    code: arguments[0].expect_number()?,
+                })
+            },
+        );
+        registry
+    }
+
+    /// Adds a function. A function registered under a name that is already
+    /// taken replaces the earlier one, so builtins can be overridden.
+    pub fn register(
+        &mut self,
+        name: &str,
+        parameters: &[(&str, Type)],
+        return_type: Type,
+        function: impl Fn(&mut NativeContext, &[Value]) -> Result<Value, RuntimeErrorKind> + 'static,
+    ) {
+        let native = NativeFunction {
+            name: name.to_string(),
+            parameters: parameters
+                .iter()
+                .map(|(name, ty)| (name.to_string(), ty.clone()))
+                .collect(),
+            return_type,
+            function: Rc::new(function),
+        };
+        match self
+            .functions
+            .iter_mut()
+            .find(|existing| existing.name == name)
+        {
+            Some(existing) => *existing = native,
+            None => self.functions.push(native),
+        }
+    }
+
+    pub fn functions(&self) -> &[NativeFunction] {
+        &self.functions
+    }
+}
+
+impl Default for NativeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
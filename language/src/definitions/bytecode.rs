@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+
+use navsharp::{Idx, IdxVec};
+
+use crate::{
+    compilation_unit::{FunctionBody, FunctionIdx, GlobalScope, VariableIdx},
+    definitions::{
+        eval::Value, limits::Limits, Ast, BinOpKind, Body, ExprId, ExprKind, ItemKind, StmtId,
+        StmtKind, StringPart, UnOpKind,
+    },
+    text::{source_map::FileId, span::TextSpan},
+    typecheck::Type,
+};
+
+/// An instruction of the virtual machine. Instructions work on a stack of
+/// values: every expression pushes exactly one value, which the
+/// instructions consuming it pop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Accounts for evaluating `count` expressions, in the order the tree
+    /// walker would visit them. Their spans are the `count` spans of
+    /// [`Chunk::step_spans`] starting at `first`. Only emitted when steps
+    /// are limited.
+    Step {
+        count: u32,
+        first: u32,
+    },
+    /// Fails if the program was interrupted. Emitted at the head of every
+    /// loop and function when steps aren't counted, since nothing else can
+    /// run for long.
+    CheckInterrupt,
+    /// Frees the strings the program can't reach anymore, if a collection
+    /// is due. Emitted at the head of every loop and function, which is
+    /// where the tree walker collects too.
+    Collect,
+    /// Fails if strings and variables take more than the heap limit. Only
+    /// emitted when the heap is limited.
+    CheckHeap,
+    /// Starts a block that may declare variables, for heap accounting. Only
+    /// emitted when the heap is limited.
+    EnterScope,
+    /// Forgets the variables declared since the matching `EnterScope`,
+    /// which are in the slots from `first` up to `end`.
+    ExitScope {
+        first: u32,
+        end: u32,
+    },
+    Push(Value),
+    /// Pushes the string at this index of [`Bytecode::strings`].
+    PushString(u32),
+    /// Pops the values of the interpolations of the template at this index
+    /// of [`Bytecode::templates`], and pushes the string they make up.
+    Interpolate(u32),
+    Pop,
+    Duplicate,
+    LoadLocal(u32),
+    /// Pops into a local.
+    StoreLocal(u32),
+    /// Pops into a local that a `let` declares.
+    DeclareLocal(u32),
+    /// Fails if the global hasn't been declared yet.
+    LoadGlobal(VariableIdx),
+    /// Pops into a global, failing if it hasn't been declared yet.
+    StoreGlobal(VariableIdx),
+    DeclareGlobal(VariableIdx),
+    Unary(UnOpKind),
+    Binary(BinOpKind),
+    Jump(u32),
+    /// Pops a condition and jumps if it is false.
+    JumpIfFalse(u32),
+    /// Pops two values and jumps unless the comparison between them holds,
+    /// a `Binary` and a `JumpIfFalse` in one.
+    JumpUnless {
+        comparison: BinOpKind,
+        target: u32,
+    },
+    /// Calls a declared function with the `arguments` values on top of the
+    /// stack, which become its first locals.
+    Call {
+        function: FunctionIdx,
+        arguments: u32,
+    },
+    CallNative {
+        function: FunctionIdx,
+        arguments: u32,
+    },
+    /// Pops the value to return and leaves the function.
+    Return,
+    /// An expression that failed to parse.
+    Invalid,
+}
+
+/// The code of a function, or of the top level of a program.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    /// The span each instruction reports errors at, by instruction.
+    pub spans: Vec<TextSpan>,
+    /// The spans of the expressions `Step` instructions account for.
+    pub step_spans: Vec<TextSpan>,
+    /// How many slots the locals take, parameters first.
+    pub locals: usize,
+}
+
+/// A part of an interpolated string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplatePart {
+    Literal(String),
+    /// The next interpolated value, as `Value::display` shows it.
+    Value,
+}
+
+/// A program compiled for the virtual machine.
+///
+/// Locals are resolved to slots of their call's frame, so that they are
+/// read and written without lookups. Globals stay indexed by their
+/// variable, since a function may run before the `let` of a global it uses
+/// and has to fail then.
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    pub top_level: Chunk,
+    /// The chunk of every declared function, `None` for natives.
+    pub functions: IdxVec<FunctionIdx, Option<Chunk>>,
+    /// The string literals, interned when first evaluated.
+    pub strings: Vec<String>,
+    pub templates: Vec<Vec<TemplatePart>>,
+    /// The limits the code accounts for, which the virtual machine running
+    /// it enforces.
+    pub limits: Limits,
+}
+
+impl Bytecode {
+    /// Compiles a program that went through every compiler pass. What the
+    /// code accounts for depends on `limits`: counting steps and tracking
+    /// the heap cost time, so code only does it when it will be checked.
+    pub fn compile(ast: &Ast, global_scope: &GlobalScope, limits: Limits) -> Self {
+        let mut compiler = Compiler {
+            ast,
+            global_scope,
+            count_steps: limits.max_steps.is_some(),
+            track_heap: limits.max_heap_size.is_some(),
+            chunk: Chunk::default(),
+            locals: HashMap::new(),
+            jump_target: 0,
+            strings: Vec::new(),
+            string_lookup: HashMap::new(),
+            templates: Vec::new(),
+        };
+        let mut functions = IdxVec::new();
+        for (function_idx, function) in global_scope.functions.indexed_iter() {
+            let chunk = match &function.body {
+                FunctionBody::Declared(body) => Some(compiler.compile_function(function_idx, body)),
+                FunctionBody::Native(_) => None,
+            };
+            functions.push(chunk);
+        }
+        let top_level = compiler.compile_top_level();
+        Self {
+            top_level,
+            functions,
+            strings: compiler.strings,
+            templates: compiler.templates,
+            limits,
+        }
+    }
+}
+
+fn is_comparison(kind: BinOpKind) -> bool {
+    matches!(
+        kind,
+        BinOpKind::Equalsto
+            | BinOpKind::NotEqualsto
+            | BinOpKind::LessThan
+            | BinOpKind::LessThanEqualTo
+            | BinOpKind::GreaterThan
+            | BinOpKind::GreaterThanEqualTo
+    )
+}
+
+struct Compiler<'a> {
+    ast: &'a Ast,
+    global_scope: &'a GlobalScope,
+    count_steps: bool,
+    track_heap: bool,
+    /// The chunk being compiled.
+    chunk: Chunk,
+    /// The slots of the locals of the chunk being compiled.
+    locals: HashMap<VariableIdx, u32>,
+    /// The last position a jump may land on. `Step`s are only merged when
+    /// no jump lands between them.
+    jump_target: usize,
+    strings: Vec<String>,
+    string_lookup: HashMap<String, u32>,
+    templates: Vec<Vec<TemplatePart>>,
+}
+
+impl Compiler<'_> {
+    fn compile_function(&mut self, function_idx: FunctionIdx, body: &Body) -> Chunk {
+        let function = &self.global_scope.functions[function_idx];
+        for parameter in &function.parameters {
+            self.local_slot(*parameter);
+        }
+        self.emit(Instruction::Collect, body.span());
+        if !self.count_steps {
+            self.emit(Instruction::CheckInterrupt, body.span());
+        }
+        for stmt_id in body.iter() {
+            self.statement(*stmt_id);
+        }
+        self.emit(Instruction::Push(Value::Void), body.span());
+        self.emit(Instruction::Return, body.span());
+        self.finish_chunk()
+    }
+
+    fn compile_top_level(&mut self) -> Chunk {
+        let ast = self.ast;
+        // Where the program ends, which nothing reports errors at.
+        let mut end = TextSpan::new(FileId::first(), 0, 0);
+        for item in ast.items.iter() {
+            if let ItemKind::Stmt(stmt_id) = &item.kind {
+                self.statement(*stmt_id);
+                end = ast.query_stmt(*stmt_id).span(ast);
+            }
+        }
+        self.emit(Instruction::Push(Value::Void), end);
+        self.emit(Instruction::Return, end);
+        self.finish_chunk()
+    }
+
+    fn finish_chunk(&mut self) -> Chunk {
+        self.locals.clear();
+        self.jump_target = 0;
+        std::mem::take(&mut self.chunk)
+    }
+
+    fn local_slot(&mut self, variable_idx: VariableIdx) -> u32 {
+        let next = self.locals.len() as u32;
+        let slot = *self.locals.entry(variable_idx).or_insert(next);
+        self.chunk.locals = self.chunk.locals.max(self.locals.len());
+        slot
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: TextSpan) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.spans.push(span);
+        self.chunk.code.len() - 1
+    }
+
+    /// Emits a jump to be patched that is taken if the condition just
+    /// compiled is false.
+    fn emit_jump_if_false(&mut self, span: TextSpan) -> usize {
+        let last = self.chunk.code.len().wrapping_sub(1);
+        match self.chunk.code.last() {
+            Some(&Instruction::Binary(comparison))
+                if last >= self.jump_target && is_comparison(comparison) =>
+            {
+                self.chunk.code[last] = Instruction::JumpUnless {
+                    comparison,
+                    target: 0,
+                };
+                last
+            }
+            _ => self.emit(Instruction::JumpIfFalse(0), span),
+        }
+    }
+
+    /// The position of the next instruction, as a jump target.
+    fn label(&mut self) -> u32 {
+        self.jump_target = self.chunk.code.len();
+        self.chunk.code.len() as u32
+    }
+
+    fn patch_jump(&mut self, jump: usize) {
+        let target = self.label();
+        match &mut self.chunk.code[jump] {
+            Instruction::Jump(to)
+            | Instruction::JumpIfFalse(to)
+            | Instruction::JumpUnless { target: to, .. } => *to = target,
+            instruction => unreachable!("patching {:?}", instruction),
+        }
+    }
+
+    fn step(&mut self, span: TextSpan) {
+        if !self.count_steps {
+            return;
+        }
+        let mergeable = self.chunk.code.len() > self.jump_target;
+        self.chunk.step_spans.push(span);
+        match self.chunk.code.last_mut() {
+            Some(Instruction::Step { count, .. }) if mergeable => *count += 1,
+            _ => {
+                let first = self.chunk.step_spans.len() as u32 - 1;
+                self.emit(Instruction::Step { count: 1, first }, span);
+            }
+        }
+    }
+
+    /// Returns the first slot the scope's variables may take.
+    fn enter_scope(&mut self, span: TextSpan) -> u32 {
+        if self.track_heap {
+            self.emit(Instruction::EnterScope, span);
+        }
+        self.locals.len() as u32
+    }
+
+    /// Slots are never shared, so every slot taken since the scope was
+    /// entered belongs to it.
+    fn exit_scope(&mut self, first: u32, span: TextSpan) {
+        if self.track_heap {
+            let end = self.locals.len() as u32;
+            self.emit(Instruction::ExitScope { first, end }, span);
+        }
+    }
+
+    fn string_constant(&mut self, value: String) -> u32 {
+        if let Some(idx) = self.string_lookup.get(&value) {
+            return *idx;
+        }
+        let idx = self.strings.len() as u32;
+        self.strings.push(value.clone());
+        self.string_lookup.insert(value, idx);
+        idx
+    }
+
+    fn statement(&mut self, stmt_id: StmtId) {
+        let ast = self.ast;
+        let stmt = ast.query_stmt(stmt_id);
+        match &stmt.kind {
+            StmtKind::Expr(expr_id) => {
+                // An assignment's value is only needed when it is used.
+                if let ExprKind::Assignment(_) = ast.query_expr(*expr_id).kind {
+                    self.expression_with(*expr_id, false);
+                } else {
+                    self.expression(*expr_id);
+                    self.emit(Instruction::Pop, stmt.span(ast));
+                }
+            }
+            StmtKind::Let(let_stmt) => {
+                let span = stmt.span(ast);
+                match let_stmt.initializer {
+                    Some(initializer) => self.expression(initializer),
+                    None => {
+                        self.emit(Instruction::Push(Value::Null), span);
+                    }
+                }
+                let variable_idx = let_stmt.variable_idx;
+                if self.global_scope.variables[variable_idx].is_global {
+                    self.emit(Instruction::DeclareGlobal(variable_idx), span);
+                } else {
+                    let slot = self.local_slot(variable_idx);
+                    self.emit(Instruction::DeclareLocal(slot), span);
+                }
+            }
+            StmtKind::While(while_stmt) => {
+                let condition_span = ast.query_expr(while_stmt.condition).span(ast);
+                let start = self.label();
+                self.emit(Instruction::Collect, condition_span);
+                if !self.count_steps {
+                    self.emit(Instruction::CheckInterrupt, condition_span);
+                }
+                self.expression(while_stmt.condition);
+                let exit = self.emit_jump_if_false(condition_span);
+                self.body(&while_stmt.body, false);
+                // Or every iteration would leave the body's value behind.
+                self.emit(Instruction::Pop, while_stmt.body.span());
+                self.emit(Instruction::Jump(start), while_stmt.while_keyword.span);
+                self.patch_jump(exit);
+            }
+            StmtKind::Return(return_stmt) => {
+                match return_stmt.return_value {
+                    Some(return_value) => self.expression(return_value),
+                    None => {
+                        self.emit(
+                            Instruction::Push(Value::Void),
+                            return_stmt.return_keyword.span,
+                        );
+                    }
+                }
+                self.emit(Instruction::Return, return_stmt.return_keyword.span);
+            }
+        }
+    }
+
+    /// Compiles the statements of a body in a scope of their own, pushing
+    /// the value of its last expression statement if `has_value`.
+    fn body(&mut self, body: &Body, has_value: bool) {
+        self.block(&body.stmts, body.span(), has_value);
+    }
+
+    fn block(&mut self, stmts: &[StmtId], span: TextSpan, has_value: bool) {
+        let first = self.enter_scope(span);
+        let (last, rest) = match stmts.split_last() {
+            Some((last, rest)) => (Some(last), rest),
+            None => (None, stmts),
+        };
+        for stmt_id in rest {
+            self.statement(*stmt_id);
+        }
+        let mut pushed = false;
+        if let Some(last) = last {
+            match &self.ast.query_stmt(*last).kind {
+                StmtKind::Expr(expr_id) if has_value => {
+                    self.expression(*expr_id);
+                    pushed = true;
+                }
+                _ => self.statement(*last),
+            }
+        }
+        self.exit_scope(first, span);
+        // Values of type `void` are never used, so any value will do.
+        if !pushed {
+            self.emit(Instruction::Push(Value::Void), span);
+        }
+    }
+
+    fn expression(&mut self, expr_id: ExprId) {
+        self.expression_with(expr_id, true);
+    }
+
+    /// Compiles an expression, leaving its value on the stack if
+    /// `keep_value`. Only assignments can leave it out.
+    fn expression_with(&mut self, expr_id: ExprId, keep_value: bool) {
+        let ast = self.ast;
+        let expr = ast.query_expr(expr_id);
+        let span = expr.span(ast);
+        self.step(span);
+        match &expr.kind {
+            ExprKind::Number(number_expr) => {
+                self.emit(Instruction::Push(Value::Number(number_expr.number)), span);
+            }
+            ExprKind::Boolean(bool_expr) => {
+                self.emit(Instruction::Push(Value::Boolean(bool_expr.value)), span);
+            }
+            ExprKind::Null(_) => {
+                self.emit(Instruction::Push(Value::Null), span);
+            }
+            ExprKind::String(string_expr) => {
+                let mut template = Vec::new();
+                for part in &string_expr.parts {
+                    match part {
+                        StringPart::Literal(text) => match template.last_mut() {
+                            Some(TemplatePart::Literal(previous)) => previous.push_str(text),
+                            _ => template.push(TemplatePart::Literal(text.clone())),
+                        },
+                        StringPart::Interpolation(expr_id) => {
+                            self.expression(*expr_id);
+                            template.push(TemplatePart::Value);
+                        }
+                    }
+                }
+                if template.contains(&TemplatePart::Value) {
+                    let idx = self.templates.len() as u32;
+                    self.templates.push(template);
+                    self.emit(Instruction::Interpolate(idx), span);
+                } else {
+                    let text = match template.pop() {
+                        Some(TemplatePart::Literal(text)) => text,
+                        _ => String::new(),
+                    };
+                    let idx = self.string_constant(text);
+                    self.emit(Instruction::PushString(idx), span);
+                }
+            }
+            ExprKind::Variable(var_expr) => {
+                let variable_idx = var_expr.variable_idx;
+                let span = var_expr.identifier.span;
+                if let Some(value) = self.global_scope.constants.get(&variable_idx) {
+                    self.emit(Instruction::Push(*value), span);
+                } else if self.global_scope.variables[variable_idx].is_global {
+                    self.emit(Instruction::LoadGlobal(variable_idx), span);
+                } else {
+                    let slot = self.local_slot(variable_idx);
+                    self.emit(Instruction::LoadLocal(slot), span);
+                }
+            }
+            ExprKind::Assignment(assign_expr) => {
+                self.expression(assign_expr.expression);
+                if keep_value {
+                    self.emit(Instruction::Duplicate, span);
+                }
+                let variable_idx = assign_expr.variable_idx;
+                if self.global_scope.variables[variable_idx].is_global {
+                    self.emit(Instruction::StoreGlobal(variable_idx), span);
+                } else {
+                    let slot = self.local_slot(variable_idx);
+                    self.emit(Instruction::StoreLocal(slot), span);
+                }
+            }
+            ExprKind::Unary(unary_expr) => {
+                self.expression(unary_expr.operand);
+                self.emit(Instruction::Unary(unary_expr.operator.kind), span);
+            }
+            ExprKind::Binary(binary_expr) => {
+                self.expression(binary_expr.left);
+                self.expression(binary_expr.right);
+                self.emit(Instruction::Binary(binary_expr.operator.kind), span);
+            }
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                self.expression(parenthesized_expr.inner);
+            }
+            ExprKind::Call(call_expr) => {
+                for argument in &call_expr.arguments {
+                    self.expression(*argument);
+                }
+                let function = call_expr.function_idx;
+                let arguments = call_expr.arguments.len() as u32;
+                let instruction = match self.global_scope.functions[function].body {
+                    FunctionBody::Declared(_) => Instruction::Call {
+                        function,
+                        arguments,
+                    },
+                    FunctionBody::Native(_) => Instruction::CallNative {
+                        function,
+                        arguments,
+                    },
+                };
+                self.emit(instruction, span);
+            }
+            ExprKind::If(if_expr) => {
+                let has_value = expr.ty != Type::Void;
+                let condition_span = ast.query_expr(if_expr.condition).span(ast);
+                self.expression(if_expr.condition);
+                let to_else = self.emit_jump_if_false(condition_span);
+                self.body(&if_expr.then_branch, has_value);
+                let to_end = self.emit(Instruction::Jump(0), span);
+                self.patch_jump(to_else);
+                match &if_expr.else_branch {
+                    Some(else_branch) => self.body(&else_branch.body, has_value),
+                    None => {
+                        self.emit(Instruction::Push(Value::Void), span);
+                    }
+                }
+                self.patch_jump(to_end);
+            }
+            ExprKind::Block(block_expr) => {
+                self.block(&block_expr.stmts, span, expr.ty != Type::Void);
+            }
+            ExprKind::Error(span) => {
+                self.emit(Instruction::Invalid, *span);
+            }
+        }
+        if self.track_heap {
+            self.emit(Instruction::CheckHeap, span);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use crate::{
+    definitions::runtime_error::StackFrame,
+    text::{source_map::SourceMap, span::TextSpan},
+    typecheck::Type,
+};
+
+/// Where execution pauses when it gets there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// The statements starting on a line. `file` matches a source file
+    /// named the same or whose path ends with it, and lines count from 1.
+    Line { file: String, line: usize },
+    /// The first statement of every call to a function.
+    Function(String),
+}
+
+impl Breakpoint {
+    /// Parses `file:line`, or a function name.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some((file, line)) = text.rsplit_once(':') {
+            let line = line.parse().ok().filter(|line| *line > 0)?;
+            return Some(Breakpoint::Line {
+                file: file.to_string(),
+                line,
+            });
+        }
+        if text.is_empty() || text.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Breakpoint::Function(text.to_string()))
+    }
+
+    fn matches_file(file: &str, name: &str) -> bool {
+        name == file || Path::new(name).ends_with(file)
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Line { file, line } => write!(f, "{}:{}", file, line),
+            Breakpoint::Function(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// How a front end resumes a paused program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    /// Runs until the next breakpoint.
+    Continue,
+    /// Pauses at the next statement, inside a call if it makes one.
+    StepIn,
+    /// Pauses at the next statement of the current function or its callers.
+    StepOver,
+    /// Pauses at the next statement after the current function returns.
+    StepOut,
+    /// Stops the program with [`RuntimeErrorKind::Interrupted`].
+    ///
+    /// [`RuntimeErrorKind::Interrupted`]: crate::definitions::runtime_error::RuntimeErrorKind::Interrupted
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// The breakpoint at this index of the debugger's breakpoints.
+    Breakpoint(usize),
+    /// A step finished, or the program started with
+    /// [`Debugger::stop_at_entry`].
+    Step,
+}
+
+/// A variable in scope in a paused call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    pub ty: Type,
+    /// The value as the REPL shows it, with strings quoted.
+    pub value: String,
+}
+
+/// A call being executed when the program paused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallFrame {
+    /// The function, and where execution is in it: the statement about to
+    /// run in the innermost frame, the call to the next frame in the
+    /// others.
+    pub stack_frame: StackFrame,
+    /// The variables the call can see, outer blocks first. The top-level
+    /// frame holds the globals.
+    pub variables: Vec<Variable>,
+}
+
+/// The state of a program paused before a statement.
+#[derive(Debug, Clone)]
+pub struct Pause {
+    pub reason: PauseReason,
+    /// The statement about to run.
+    pub span: TextSpan,
+    /// The calls being executed, innermost first, ending with the top
+    /// level.
+    pub frames: Vec<CallFrame>,
+}
+
+impl Pause {
+    /// The variable a name refers to in a frame, the innermost declaration
+    /// when blocks shadow it.
+    pub fn variable(&self, frame: usize, name: &str) -> Option<&Variable> {
+        self.frames
+            .get(frame)?
+            .variables
+            .iter()
+            .rev()
+            .find(|variable| variable.name == name)
+    }
+}
+
+/// Decides what happens when a program pauses, e.g. by asking the user.
+pub trait DebugFrontend {
+    /// Called whenever the program pauses. Breakpoints may be added or
+    /// removed before resuming.
+    fn paused(&mut self, pause: &Pause, breakpoints: &mut Vec<Breakpoint>) -> DebugCommand;
+}
+
+/// How far the program runs before pausing again.
+#[derive(Debug, Clone, Copy)]
+enum Resume {
+    Continue,
+    StepIn,
+    /// Until a statement at this call depth or shallower.
+    StepOver(usize),
+    /// Until a statement shallower than this call depth.
+    StepOut(usize),
+}
+
+/// Pauses an evaluator at breakpoints and steps, handing control to a
+/// front end each time.
+pub struct Debugger<'a> {
+    source_map: &'a SourceMap,
+    frontend: Box<dyn DebugFrontend + 'a>,
+    pub breakpoints: Vec<Breakpoint>,
+    resume: Resume,
+    /// The function breakpoint of a call just entered, which pauses at its
+    /// first statement.
+    entered: Option<usize>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(source_map: &'a SourceMap, frontend: impl DebugFrontend + 'a) -> Self {
+        Self {
+            source_map,
+            frontend: Box::new(frontend),
+            breakpoints: Vec::new(),
+            resume: Resume::Continue,
+            entered: None,
+        }
+    }
+
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint) -> Self {
+        self.breakpoints.push(breakpoint);
+        self
+    }
+
+    /// Pauses before the first statement of the program.
+    pub fn stop_at_entry(mut self) -> Self {
+        self.resume = Resume::StepIn;
+        self
+    }
+
+    /// Notes that a call to `function` is starting.
+    pub(crate) fn enter_function(&mut self, function: &str) {
+        let breakpoint = self.breakpoints.iter().position(
+            |breakpoint| matches!(breakpoint, Breakpoint::Function(name) if name == function),
+        );
+        if breakpoint.is_some() {
+            self.entered = breakpoint;
+        }
+    }
+
+    /// Whether to pause before the statement at `span`, run `depth` calls
+    /// deep.
+    pub(crate) fn should_pause(&mut self, span: TextSpan, depth: usize) -> Option<PauseReason> {
+        if let Some(breakpoint) = self.entered.take() {
+            return Some(PauseReason::Breakpoint(breakpoint));
+        }
+        let file = self.source_map.get(span.file_id);
+        let line = file.text.line_index(span.start) + 1;
+        let breakpoint = self
+            .breakpoints
+            .iter()
+            .position(|breakpoint| match breakpoint {
+                Breakpoint::Line {
+                    file: breakpoint_file,
+                    line: breakpoint_line,
+                } => {
+                    *breakpoint_line == line
+                        && Breakpoint::matches_file(breakpoint_file, &file.name)
+                }
+                Breakpoint::Function(_) => false,
+            });
+        if let Some(breakpoint) = breakpoint {
+            return Some(PauseReason::Breakpoint(breakpoint));
+        }
+        let step_done = match self.resume {
+            Resume::Continue => false,
+            Resume::StepIn => true,
+            Resume::StepOver(step_depth) => depth <= step_depth,
+            Resume::StepOut(step_depth) => depth < step_depth,
+        };
+        step_done.then_some(PauseReason::Step)
+    }
+
+    /// Hands a pause to the front end. Returns whether the program should
+    /// go on.
+    pub(crate) fn pause(&mut self, pause: &Pause, depth: usize) -> bool {
+        self.resume = match self.frontend.paused(pause, &mut self.breakpoints) {
+            DebugCommand::Continue => Resume::Continue,
+            DebugCommand::StepIn => Resume::StepIn,
+            DebugCommand::StepOver => Resume::StepOver(depth),
+            DebugCommand::StepOut => Resume::StepOut(depth),
+            DebugCommand::Quit => return false,
+        };
+        true
+    }
+}
@@ -1,290 +1,1186 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
 
-use navsharp::Idx;
+use navsharp::{idx, Idx, IdxVec};
 
 use crate::{
+    compilation_unit::{FunctionBody, FunctionIdx, GlobalScope, VariableIdx},
+    definitions::{
+        visitor::ASTVisitor, AssignExpr, Ast, BinOpKind, BinaryExpr, BlockExpr,
+        Body, BoolExpr, CallExpr, ConstDeclaration, Expr, ExprId, FuncDeclaration, IfExpr, ItemId,
+        ItemKind, LetStmt, NullExpr, NumberExpr, ParenthesizedExpr, ReturnStmt, Stmt, StmtId,
+        StringExpr, StringPart, UnOpKind, UnaryExpr, VarExpr, WhileStmt,
+    },
     definitions::{
-        visitor::ASTVisitor, AssignExpr, Ast, BinOpKind, BinaryExpr, BlockExpr, Body, BoolExpr,
-        CallExpr, Expr, FuncDeclaration, IfExpr, ItemId, LetStmt, NumberExpr,
-        ParenthesizedExpr, Stmt, UnOpKind, UnaryExpr, VarExpr, WhileStmt,
+        builtins::NativeContext,
+        debugger::{CallFrame, Debugger, Pause, Variable},
+        limits::{InterruptHandle, Limits},
+        profiler::{Profile, Profiler},
+        runtime_error::{RuntimeError, RuntimeErrorKind, StackFrame},
     },
-    compilation_unit::{FunctionIdx, GlobalScope, VariableIdx},
+    diagnostics::DiagnosticsBagCell,
     text::span::TextSpan,
+    typecheck::Type,
 };
 
-
 #[derive(Debug)]
-
-pub struct Frame{
-    variables: HashMap<VariableIdx, Value>
+pub struct Frame {
+    variables: HashMap<VariableIdx, Value>,
 }
 
-impl Frame{
-    fn new() -> Self{
-        Self{
+impl Frame {
+    fn new() -> Self {
+        Self {
             variables: HashMap::new(),
         }
     }
 
-    fn insert(&mut self, idx: VariableIdx, value: Value){
-        self.variables.insert(idx, value);
+    /// Returns whether the variable is new to the frame.
+    fn insert(&mut self, idx: VariableIdx, value: Value) -> bool {
+        self.variables.insert(idx, value).is_none()
     }
 
-    fn get(&self , idx: &VariableIdx) -> Option<Value>{
+    fn get(&self, idx: &VariableIdx) -> Option<&Value> {
         self.variables.get(idx)
     }
 }
 
+/// The variables of the blocks being executed. The first frame holds the
+/// globals. Each call starts a new frame, and only sees the frames from
+/// there up and the globals, so variables are looked up lexically whatever
+/// the caller has in scope.
 #[derive(Debug)]
-
-pub struct Frames{
-    Frames: Vec<Frame>,
+pub struct Frames {
+    frames: Vec<Frame>,
+    /// The index of the first frame of each call being executed, innermost
+    /// last.
+    call_starts: Vec<usize>,
+    /// How many variables the frames hold together.
+    variable_count: usize,
 }
 
-impl Frames{
-    fn new() -> Self{
-        Self{
+impl Frames {
+    fn new() -> Self {
+        Self {
             frames: vec![Frame::new()],
+            call_starts: Vec::new(),
+            variable_count: 0,
         }
     }
 
-    fn push(&mut self){
+    /// The first frame above the globals' the innermost call can see.
+    fn call_start(&self) -> usize {
+        self.call_starts.last().copied().unwrap_or(1)
+    }
+
+    /// The frames the innermost call can see, innermost first.
+    fn visible(&self) -> impl Iterator<Item = &Frame> {
+        self.frames[self.call_start()..]
+            .iter()
+            .rev()
+            .chain(&self.frames[..1])
+    }
+
+    fn push_call(&mut self) {
+        self.call_starts.push(self.frames.len());
+        self.push();
+    }
+
+    fn pop_call(&mut self) {
+        self.pop();
+        self.call_starts.pop();
+    }
+
+    fn push(&mut self) {
         self.frames.push(Frame::new());
-    
+    }
 
-    fn pop(&mut self){
-        self.frames.pop();
+    fn pop(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            self.variable_count -= frame.variables.len();
+        }
     }
 
-    fn update(&mut self, idx: VariableIdx, value: Value){
-        for frame in self.frames.iter_mut().rev(){
-            if frame.get(&idx).is_some(){
+    /// Assigns to a variable visible to the innermost call. Returns
+    /// whether there was one.
+    fn update(&mut self, idx: VariableIdx, value: Value) -> bool {
+        let call_start = self.call_start();
+        let (globals, locals) = self.frames.split_at_mut(1);
+        let locals = &mut locals[call_start - 1..];
+        for frame in locals.iter_mut().rev().chain(globals.iter_mut()) {
+            if frame.get(&idx).is_some() {
                 frame.insert(idx, value);
-                return;
+                return true;
             }
         }
+        false
+    }
+
+    fn insert(&mut self, idx: VariableIdx, value: Value) {
+        if self.frames.last_mut().unwrap().insert(idx, value) {
+            self.variable_count += 1;
+        }
+    }
+
+    /// The values of every variable, in every call.
+    fn values(&self) -> impl Iterator<Item = &Value> {
+        self.frames.iter().flat_map(|frame| frame.variables.values())
+    }
+
+    /// The bytes taken by the values of every variable.
+    fn heap_size(&self) -> usize {
+        self.variable_count * std::mem::size_of::<Value>()
     }
 
-    fn insert (&mut self, idx: VariableIdx, value: Value){
-        self.frames.last_mut().unwrap().insert(idx, value);
+    fn get(&self, idx: &VariableIdx) -> Option<&Value> {
+        self.visible().find_map(|frame| frame.get(idx))
     }
+}
+
+idx!(StringIdx);
+
+/// The contents of every string value, stored once each so that `Value`
+/// only carries an index. Equal contents always share an index, which
+/// keeps `==` on values a plain comparison.
+///
+/// Strings no value refers to anymore are freed by [`Strings::collect`],
+/// which the evaluators call at every loop iteration and call once
+/// [`Strings::should_collect`] says so. Until then they still count
+/// towards the heap size.
+#[derive(Debug, Clone)]
+pub struct Strings {
+    values: IdxVec<StringIdx, Option<Rc<str>>>,
+    lookup: HashMap<Rc<str>, StringIdx>,
+    /// The indices of freed strings, reused before new ones.
+    free: Vec<StringIdx>,
+    /// The length of every string together.
+    heap_size: usize,
+    /// The heap size at which the next collection is due.
+    next_collection: usize,
+}
+
+impl Strings {
+    /// Strings are collected no earlier than once they take this many bytes.
+    const MIN_COLLECTION_SIZE: usize = 16 * 1024;
 
-    fn get(&self, idx: &VariableIdx) -> Option<&Value>{
-        for frame in self.frame.iter().rev(){
-            if let Some(value) = frame.get(idx){
-                return Some(value);
+    pub fn new() -> Self {
+        Self {
+            values: IdxVec::new(),
+            lookup: HashMap::new(),
+            free: Vec::new(),
+            heap_size: 0,
+            next_collection: Self::MIN_COLLECTION_SIZE,
+        }
+    }
+
+    pub fn intern(&mut self, value: &str) -> StringIdx {
+        if let Some(idx) = self.lookup.get(value) {
+            return *idx;
+        }
+        let value: Rc<str> = Rc::from(value);
+        self.heap_size += value.len();
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.values[idx] = Some(value.clone());
+                idx
+            }
+            None => self.values.push(Some(value.clone())),
+        };
+        self.lookup.insert(value, idx);
+        idx
+    }
+
+    pub fn get(&self, idx: StringIdx) -> &str {
+        self.values[idx]
+            .as_deref()
+            .expect("expected the string to be live")
+    }
+
+    /// Whether the string at `idx` hasn't been freed.
+    pub fn contains(&self, idx: StringIdx) -> bool {
+        self.values.get(idx).is_some()
+    }
+
+    pub fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    /// Whether the strings have grown enough since the last collection for
+    /// another to be worth it: to twice what was left then.
+    pub fn should_collect(&self) -> bool {
+        self.heap_size >= self.next_collection
+    }
+
+    /// Frees every string none of `roots` refers to. The roots must be all
+    /// the values the program can still reach.
+    pub fn collect(&mut self, roots: impl IntoIterator<Item = Value>) {
+        let mut reachable = vec![false; self.values.len()];
+        for root in roots {
+            if let Value::String(idx) = root {
+                reachable[idx.as_index()] = true;
+            }
+        }
+        for (idx, value) in self.values.indexed_iter_mut() {
+            if reachable[idx.as_index()] {
+                continue;
+            }
+            if let Some(value) = value.take() {
+                self.heap_size -= value.len();
+                self.lookup.remove(&value);
+                self.free.push(idx);
             }
         }
-        None
+        self.next_collection = (self.heap_size * 2).max(Self::MIN_COLLECTION_SIZE);
     }
 }
 
-#[derive[Debug,Clone,Copy,PartialEq]]
+impl Default for Strings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-pub enum Value{
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
     Number(i64),
     Boolean(bool),
-    Function(FunctionIdx),
+    String(StringIdx),
+    Null,
+    /// The result of calling a function that returns nothing.
+    Void,
 }
 
-impl Value{
-    pub fn expect_boolean(&self) -> bool{
-        match self{
-            Value::Boolean(value) => *value,
-            _ => panic!("Expected a boolean expression"),   
+impl Value {
+    /// The name of the value's type, as used in runtime errors.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "int",
+            Value::Boolean(_) => "bool",
+            Value::String(_) => "string",
+            Value::Null => "null",
+            Value::Void => "void",
+        }
+    }
+
+    fn mismatch(&self, expected: &'static str) -> RuntimeErrorKind {
+        RuntimeErrorKind::TypeMismatch {
+            expected,
+            found: self.type_name(),
         }
     }
 
-    pub fn expect_number(&self) -> i64 {
+    pub fn expect_boolean(&self) -> Result<bool, RuntimeErrorKind> {
         match self {
-            Value::Number(value) => *value,
-            _ => panic!("Expected a Integer Expression"),
+            Value::Boolean(value) => Ok(*value),
+            _ => Err(self.mismatch("bool")),
+        }
+    }
+
+    pub fn expect_number(&self) -> Result<i64, RuntimeErrorKind> {
+        match self {
+            Value::Number(value) => Ok(*value),
+            _ => Err(self.mismatch("int")),
+        }
+    }
+
+    pub fn expect_string(&self) -> Result<StringIdx, RuntimeErrorKind> {
+        match self {
+            Value::String(value) => Ok(*value),
+            _ => Err(self.mismatch("string")),
+        }
+    }
+
+    /// Whether the value is one of the values of `ty`.
+    pub fn has_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (Value::Null, Type::Null | Type::Nullable(_)) => true,
+            (value, Type::Nullable(inner)) => value.has_type(inner),
+            (Value::Number(_), Type::Int)
+            | (Value::Boolean(_), Type::Bool)
+            | (Value::String(_), Type::String)
+            | (Value::Void, Type::Void) => true,
+            _ => false,
         }
     }
 
-    pub fn expect_function(&self) -> FunctionIdx{
+    /// The text of the value as it appears when interpolated into a string.
+    pub fn display(&self, strings: &Strings) -> String {
         match self {
-            Value::Function(value) => *value,
-            _ => panic!("Expexted a Function Expression")
+            Value::Number(value) => value.to_string(),
+            Value::Boolean(value) => value.to_string(),
+            Value::String(value) => strings.get(*value).to_string(),
+            Value::Null => "null".to_string(),
+            Value::Void => "void".to_string(),
+        }
+    }
+}
+
+pub(crate) fn unary_operation(kind: &UnOpKind, operand: Value) -> Result<Value, RuntimeErrorKind> {
+    let operand = operand.expect_number()?;
+    match kind {
+        UnOpKind::Minus => operand.checked_neg().map(Value::Number).ok_or(RuntimeErrorKind::Overflow),
+        UnOpKind::Bitwise => Ok(Value::Number(!operand)),
+    }
+}
+
+fn compare(left: Value, right: Value, strings: &Strings) -> Result<Ordering, RuntimeErrorKind> {
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => Ok(strings.get(left).cmp(strings.get(right))),
+        _ => Ok(left.expect_number()?.cmp(&right.expect_number()?)),
+    }
+}
+
+pub(crate) fn binary_operation(
+    kind: &BinOpKind,
+    left: Value,
+    right: Value,
+    strings: &mut Strings,
+) -> Result<Value, RuntimeErrorKind> {
+    if let (BinOpKind::Plus, Value::String(left), Value::String(right)) = (kind, left, right) {
+        let concatenated = format!("{}{}", strings.get(left), strings.get(right));
+        return Ok(Value::String(strings.intern(&concatenated)));
+    }
+    let number = |result: Option<i64>| result.map(Value::Number).ok_or(RuntimeErrorKind::Overflow);
+    let divisor = || match right.expect_number()? {
+        0 => Err(RuntimeErrorKind::DivisionByZero),
+        divisor => Ok(divisor),
+    };
+    match kind {
+        BinOpKind::Plus => number(left.expect_number()?.checked_add(right.expect_number()?)),
+        BinOpKind::Minus => number(left.expect_number()?.checked_sub(right.expect_number()?)),
+        BinOpKind::Multiply => number(left.expect_number()?.checked_mul(right.expect_number()?)),
+        BinOpKind::Divide => number(left.expect_number()?.checked_div(divisor()?)),
+        BinOpKind::Modulo => number(left.expect_number()?.checked_rem(divisor()?)),
+        BinOpKind::BitwiseAnd => Ok(Value::Number(left.expect_number()? & right.expect_number()?)),
+        BinOpKind::BitwiseOr => Ok(Value::Number(left.expect_number()? | right.expect_number()?)),
+        BinOpKind::BitwiseXor => Ok(Value::Number(left.expect_number()? ^ right.expect_number()?)),
+        BinOpKind::Power => {
+            let exponent = u32::try_from(right.expect_number()?).map_err(|_| RuntimeErrorKind::Overflow)?;
+            number(left.expect_number()?.checked_pow(exponent))
+        }
+        BinOpKind::Equalsto => Ok(Value::Boolean(left == right)),
+        BinOpKind::NotEqualsto => Ok(Value::Boolean(left != right)),
+        BinOpKind::LessThan => Ok(Value::Boolean(compare(left, right, strings)?.is_lt())),
+        BinOpKind::GreaterThan => Ok(Value::Boolean(compare(left, right, strings)?.is_gt())),
+        BinOpKind::LessThanEqualTo => Ok(Value::Boolean(compare(left, right, strings)?.is_le())),
+        BinOpKind::GreaterThanEqualTo => {
+            Ok(Value::Boolean(compare(left, right, strings)?.is_ge()))
         }
     }
 }
 
-pub struct ASTEval<'a>{
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalMode {
+    Runtime,
+    /// Evaluates const initializers during compilation. Only
+    /// side-effect-free expressions are allowed, and failures are reported
+    /// as compilation errors.
+    Const,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConstEvalError {
+    NotConstant(TextSpan),
+    Runtime(RuntimeErrorKind, TextSpan),
+    /// The const was referenced at this span while its own initializer was
+    /// being evaluated.
+    Cycle(VariableIdx, TextSpan),
+    /// A const this one depends on failed and has been reported already.
+    Dependency,
+}
+
+/// A call to a declared function being executed.
+struct Call {
+    function_idx: FunctionIdx,
+    /// The span of the call expression.
+    span: TextSpan,
+}
+
+pub struct ASTEval<'a> {
     pub last_value: Option<Value>,
-    pub frames : Frames,
-    pub global_scope: &'a GlobalScope
+    pub frames: Frames,
+    pub global_scope: &'a GlobalScope,
+    pub mode: EvalMode,
+    pub strings: Strings,
+    const_error: Option<ConstEvalError>,
+    /// The value of a `return` being executed, set until the call it
+    /// returns from takes it.
+    return_value: Option<Value>,
+    /// Values evaluated but not used yet, such as the left operand of a
+    /// binary expression while the right one is evaluated. Strings they
+    /// refer to must survive collections.
+    temporaries: Vec<Value>,
+    runtime_error: Option<RuntimeError>,
+    /// The functions being called, innermost last.
+    calls: Vec<Call>,
+    limits: Limits,
+    interrupt_handle: InterruptHandle,
+    debugger: Option<Debugger<'a>>,
+    profiler: Option<Profiler<'a>>,
+    output: Box<dyn Write + 'a>,
+    /// `None` for stdin.
+    input: Option<Box<dyn BufRead + 'a>>,
+    /// How many expressions have been evaluated.
+    steps: u64,
+    const_items: HashMap<VariableIdx, ConstDeclaration>,
+    constants: HashMap<VariableIdx, Value>,
+    evaluating: Vec<VariableIdx>,
+    failed: HashSet<VariableIdx>,
+    errors: Vec<(VariableIdx, ConstEvalError)>,
 }
 
-impl<'a> ASTEval<'a>{
-    pub fn new(global_scope: &'a GlobalScope) -> Self{
-        Self{
+impl<'a> ASTEval<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self::with_mode(global_scope, EvalMode::Runtime)
+    }
+
+    pub fn new_const(global_scope: &'a GlobalScope) -> Self {
+        Self::with_mode(global_scope, EvalMode::Const)
+    }
+
+    fn with_mode(global_scope: &'a GlobalScope, mode: EvalMode) -> Self {
+        Self {
             last_value: None,
             frames: Frames::new(),
-            global_scope,  
+            global_scope,
+            mode,
+            strings: Strings::new(),
+            const_error: None,
+            return_value: None,
+            temporaries: Vec::new(),
+            runtime_error: None,
+            calls: Vec::new(),
+            limits: Limits::new(),
+            interrupt_handle: InterruptHandle::new(),
+            debugger: None,
+            profiler: None,
+            output: Box::new(io::stdout()),
+            input: None,
+            steps: 0,
+            const_items: HashMap::new(),
+            constants: HashMap::new(),
+            evaluating: Vec::new(),
+            failed: HashSet::new(),
+            errors: Vec::new(),
         }
     }
 
-    fn push_frame(&mut self){
-        self.frames.push();
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Makes evaluation stop once `interrupt_handle` is interrupted, in place
+    /// of the handle the evaluator created.
+    pub fn with_interrupt_handle(mut self, interrupt_handle: InterruptHandle) -> Self {
+        self.interrupt_handle = interrupt_handle;
+        self
+    }
+
+    /// Pauses evaluation wherever `debugger` asks to.
+    pub fn with_debugger(mut self, debugger: Debugger<'a>) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Sends what the program prints to `output` instead of stdout.
+    pub fn with_output(mut self, output: impl Write + 'a) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Makes the program read its input from `input` instead of stdin.
+    pub fn with_input(mut self, input: impl BufRead + 'a) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Records where time goes while evaluating, to be read with
+    /// [`ASTEval::take_profile`].
+    pub fn with_profiler(mut self, profiler: Profiler<'a>) -> Self {
+        self.profiler = Some(profiler);
+        self
+    }
+
+    /// What the profiler recorded, or `None` if there is none.
+    pub fn take_profile(&mut self, ast: &Ast) -> Option<Profile> {
+        let profiler = self.profiler.take()?;
+        Some(profiler.finish(ast, self.global_scope))
+    }
+
+    /// A handle that cancels this evaluator's evaluation from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt_handle.clone()
+    }
+
+    /// The bytes strings and variables take, bounded by
+    /// [`Limits::max_heap_size`].
+    pub fn heap_size(&self) -> usize {
+        self.strings.heap_size() + self.frames.heap_size()
+    }
+
+    /// Executes the top-level statements of the program, stopping at the
+    /// first runtime error.
+    pub fn run(&mut self, ast: &mut Ast) -> Result<(), RuntimeError> {
+        let items: Vec<ItemId> = ast.items.iter().map(|item| item.id).collect();
+        self.run_items(ast, &items)
+    }
+
+    /// Executes the given top-level items in order, stopping at the first
+    /// runtime error. Globals set by earlier runs stay visible.
+    pub fn run_items(&mut self, ast: &mut Ast, items: &[ItemId]) -> Result<(), RuntimeError> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(None);
+        }
+        let mut result = Ok(());
+        for item_id in items {
+            self.visit_item(ast, *item_id);
+            if let Some(error) = self.runtime_error.take() {
+                result = Err(error);
+                break;
+            }
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+        result
+    }
+
+    /// The value of a variable in scope at the current point of execution.
+    pub fn variable(&self, variable_idx: VariableIdx) -> Option<Value> {
+        self.frames.get(&variable_idx).copied()
+    }
+
+    /// Declares a variable in the innermost frame, the globals' when
+    /// nothing is running.
+    pub fn set_variable(&mut self, variable_idx: VariableIdx, value: Value) {
+        self.frames.insert(variable_idx, value);
+    }
+
+    /// Calls a function with arguments matching its parameters, as a call
+    /// at `span` from the top level would, and returns what it returns.
+    /// Globals set by earlier runs stay visible.
+    pub fn call_function(
+        &mut self,
+        ast: &mut Ast,
+        function_idx: FunctionIdx,
+        arguments: &[Value],
+        span: TextSpan,
+    ) -> Result<Value, RuntimeError> {
+        self.call(ast, function_idx, arguments, span);
+        if let Some(error) = self.runtime_error.take() {
+            return Err(error);
+        }
+        Ok(self.expect_last_value())
+    }
+
+    /// Evaluates every const item, reporting the ones that fail, and
+    /// returns the values of the rest.
+    pub fn evaluate_consts(
+        &mut self,
+        ast: &mut Ast,
+        diagnostics: &DiagnosticsBagCell,
+    ) -> HashMap<VariableIdx, Value> {
+        let mut order = Vec::new();
+        for item in ast.items.iter() {
+            if let ItemKind::Const(const_decl) = &item.kind {
+                if const_decl.variable_idx != VariableIdx::unreachable() {
+                    order.push(const_decl.variable_idx);
+                    self.const_items
+                        .insert(const_decl.variable_idx, const_decl.clone());
+                }
+            }
+        }
+        for variable_idx in order {
+            let _ = self.evaluate_const(ast, variable_idx);
+        }
+        let mut diagnostics = diagnostics.borrow_mut();
+        for (variable_idx, error) in self.errors.drain(..) {
+            let name = &self.global_scope.variables[variable_idx].name;
+            let span = &self.const_items[&variable_idx].identifier.span;
+            match error {
+                ConstEvalError::NotConstant(reason) => {
+                    diagnostics.report_non_constant_expression(name, span, &reason)
+                }
+                ConstEvalError::Runtime(RuntimeErrorKind::DivisionByZero, reason) => {
+                    diagnostics.report_const_division_by_zero(name, span, &reason)
+                }
+                ConstEvalError::Runtime(RuntimeErrorKind::Overflow, reason) => {
+                    diagnostics.report_const_overflow(name, span, &reason)
+                }
+                // Calls are rejected and operands are type-checked, so no
+                // other error can happen evaluating a const.
+                ConstEvalError::Runtime(_, reason) => {
+                    diagnostics.report_non_constant_expression(name, span, &reason)
+                }
+                ConstEvalError::Cycle(reference, reference_span) => {
+                    diagnostics.report_const_cycle(
+                        name,
+                        span,
+                        &self.global_scope.variables[reference].name,
+                        &reference_span,
+                    )
+                }
+                ConstEvalError::Dependency => {}
+            }
+        }
+        std::mem::take(&mut self.constants)
+    }
+
+    fn evaluate_const(&mut self, ast: &mut Ast, variable_idx: VariableIdx) -> Result<Value, ConstEvalError> {
+        if let Some(value) = self.constants.get(&variable_idx) {
+            return Ok(*value);
+        }
+        if self.failed.contains(&variable_idx) {
+            return Err(ConstEvalError::Dependency);
+        }
+        let const_decl = self.const_items[&variable_idx].clone();
+        self.evaluating.push(variable_idx);
+        let frames = std::mem::replace(&mut self.frames, Frames::new());
+        self.visit_expression(ast, const_decl.initializer);
+        self.frames = frames;
+        self.evaluating.pop();
+        match self.const_error.take() {
+            None => {
+                let value = self.expect_last_value();
+                self.constants.insert(variable_idx, value);
+                Ok(value)
+            }
+            Some(error) => {
+                self.failed.insert(variable_idx);
+                if !matches!(error, ConstEvalError::Dependency) {
+                    self.errors.push((variable_idx, error));
+                }
+                Err(ConstEvalError::Dependency)
+            }
+        }
+    }
+
+    /// Records the first error of the const being evaluated; every visit
+    /// stops early once one is set, see [`Self::is_unwinding`].
+    fn fail(&mut self, error: ConstEvalError) {
+        if self.const_error.is_none() {
+            self.const_error = Some(error);
+        }
+    }
+
+    /// Stops evaluation with an error at `span`: a const error when
+    /// evaluating a const, a runtime error carrying the current stack trace
+    /// otherwise.
+    fn raise(&mut self, kind: RuntimeErrorKind, span: TextSpan) {
+        match self.mode {
+            EvalMode::Const => self.fail(ConstEvalError::Runtime(kind, span)),
+            EvalMode::Runtime => {
+                if self.runtime_error.is_some() {
+                    return;
+                }
+                let mut stack_trace = Vec::new();
+                let mut frame_span = span;
+                for call in self.calls.iter().rev() {
+                    stack_trace.push(StackFrame {
+                        function: Some(self.global_scope.functions[call.function_idx].name.clone()),
+                        span: frame_span,
+                    });
+                    frame_span = call.span;
+                }
+                stack_trace.push(StackFrame {
+                    function: None,
+                    span: frame_span,
+                });
+                self.runtime_error = Some(RuntimeError::new(kind, span, stack_trace));
+            }
+        }
+    }
+
+    /// Whether the statements being executed are abandoned, because of an
+    /// error or because a `return` is on its way out to its call.
+    fn is_unwinding(&self) -> bool {
+        self.const_error.is_some() || self.runtime_error.is_some() || self.return_value.is_some()
+    }
+
+    /// Rejects a construct that may have side effects when evaluating a
+    /// const. Returns whether evaluation should stop. The span is only
+    /// computed when it is reported.
+    fn reject_in_const(&mut self, span: impl FnOnce() -> TextSpan) -> bool {
+        if self.mode == EvalMode::Const {
+            self.fail(ConstEvalError::NotConstant(span()));
+            return true;
+        }
+        false
+    }
+
+    /// Accounts for evaluating an expression, failing if a step limit is
+    /// reached or evaluation was interrupted. Returns whether evaluation
+    /// should go on.
+    fn step(&mut self, ast: &Ast, expr_id: ExprId) -> bool {
+        self.steps += 1;
+        let error = if self.interrupt_handle.is_interrupted() {
+            RuntimeErrorKind::Interrupted
+        } else {
+            match self.limits.max_steps {
+                Some(limit) if self.steps > limit => RuntimeErrorKind::StepLimitExceeded { limit },
+                _ => return true,
+            }
+        };
+        let span = ast.query_expr(expr_id).span(ast);
+        self.raise(error, span);
+        false
+    }
+
+    /// Fails if the expression just evaluated grew the heap past its limit.
+    fn check_heap(&mut self, ast: &Ast, expr_id: ExprId) {
+        if let Some(limit) = self.limits.max_heap_size {
+            if self.heap_size() > limit && !self.is_unwinding() {
+                let span = ast.query_expr(expr_id).span(ast);
+                self.raise(RuntimeErrorKind::HeapLimitExceeded { limit }, span);
+            }
+        }
+    }
+
+    /// Gives the debugger the chance to pause before a statement. Returns
+    /// whether execution should go on.
+    fn debug_statement(&mut self, ast: &Ast, stmt_id: StmtId) -> bool {
+        let span = ast.query_stmt(stmt_id).span(ast);
+        let depth = self.calls.len();
+        let Some(debugger) = &mut self.debugger else {
+            return true;
+        };
+        let Some(reason) = debugger.should_pause(span, depth) else {
+            return true;
+        };
+        let pause = Pause {
+            reason,
+            span,
+            frames: self.call_frames(span),
+        };
+        let Some(debugger) = &mut self.debugger else {
+            return true;
+        };
+        if debugger.pause(&pause, depth) {
+            return true;
+        }
+        self.raise(RuntimeErrorKind::Interrupted, span);
+        false
+    }
 
+    /// The calls being executed with the variables they see, innermost
+    /// first, for execution paused at `span`.
+    fn call_frames(&self, span: TextSpan) -> Vec<CallFrame> {
+        let mut call_frames = Vec::new();
+        let mut frame_span = span;
+        let mut frames_end = self.frames.frames.len();
+        for (call, call_start) in self.calls.iter().zip(&self.frames.call_starts).rev() {
+            call_frames.push(CallFrame {
+                stack_frame: StackFrame {
+                    function: Some(self.global_scope.functions[call.function_idx].name.clone()),
+                    span: frame_span,
+                },
+                variables: self.frame_variables(*call_start..frames_end),
+            });
+            frame_span = call.span;
+            frames_end = *call_start;
+        }
+        call_frames.push(CallFrame {
+            stack_frame: StackFrame {
+                function: None,
+                span: frame_span,
+            },
+            variables: self.frame_variables(0..frames_end),
+        });
+        call_frames
+    }
+
+    /// The variables in a range of frames, outer frames first and in order
+    /// of declaration within each.
+    fn frame_variables(&self, frames: std::ops::Range<usize>) -> Vec<Variable> {
+        let mut variables = Vec::new();
+        for frame in &self.frames.frames[frames] {
+            let mut frame_variables: Vec<_> = frame.variables.iter().collect();
+            frame_variables.sort_by_key(|(idx, _)| idx.as_index());
+            for (idx, value) in frame_variables {
+                let symbol = &self.global_scope.variables[*idx];
+                let value = match value {
+                    Value::String(string) => format!("{:?}", self.strings.get(*string)),
+                    value => value.display(&self.strings),
+                };
+                variables.push(Variable {
+                    name: symbol.name.clone(),
+                    ty: symbol.ty.clone(),
+                    value,
+                });
+            }
+        }
+        variables
+    }
+
+    /// Fails on a variable the innermost call can't see, e.g. a global used
+    /// by a function called before the global's `let` ran.
+    fn raise_undeclared(&mut self, variable_idx: VariableIdx, span: TextSpan) {
+        let name = self.global_scope.variables[variable_idx].name.clone();
+        self.raise(RuntimeErrorKind::UndeclaredVariable { name }, span);
+    }
+
+    /// Calls a function, the call being at `span`, and sets `last_value`
+    /// to what it returns.
+    fn call(&mut self, ast: &mut Ast, function_idx: FunctionIdx, arguments: &[Value], span: TextSpan) {
+        let global_scope = self.global_scope;
+        let function = global_scope.functions.get(function_idx);
+        let body = match &function.body {
+            FunctionBody::Declared(body) => body,
+            FunctionBody::Native(native) => {
+                let input = self
+                    .input
+                    .as_deref_mut()
+                    .map(|input| input as &mut dyn BufRead);
+                let mut context = NativeContext::new(&mut self.strings, &mut self.output, input);
+                let result = native.call(&mut context, arguments);
+                self.set_result(result, span);
+                return;
+            }
+        };
+        if self.calls.len() >= self.limits.max_call_depth {
+            let depth = self.limits.max_call_depth;
+            self.raise(RuntimeErrorKind::StackOverflow { depth }, span);
+            return;
+        }
+        self.calls.push(Call {
+            function_idx,
+            span,
+        });
+        if let Some(debugger) = &mut self.debugger {
+            debugger.enter_function(&function.name);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(Some(function_idx));
+        }
+        self.frames.push_call();
+        for (argument, param) in arguments.iter().zip(function.parameters.iter()) {
+            self.frames.insert(*param, *argument);
+        }
+        self.collect_strings();
+        for stmt in body.iter() {
+            self.visit_statement(ast, *stmt);
+            if self.is_unwinding() {
+                break;
+            }
+        }
+        self.frames.pop_call();
+        self.calls.pop();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+        if self.runtime_error.is_some() {
+            return;
+        }
+        self.last_value = Some(self.return_value.take().unwrap_or(Value::Void));
+    }
+
+    fn set_result(&mut self, result: Result<Value, RuntimeErrorKind>, span: TextSpan) {
+        match result {
+            Ok(value) => self.last_value = Some(value),
+            Err(kind) => self.raise(kind, span),
+        }
+    }
+
+    /// Evaluates the condition of an `if` or `while`, or returns `None` if
+    /// evaluation stopped.
+    fn evaluate_condition(&mut self, ast: &mut Ast, condition: ExprId) -> Option<bool> {
+        self.visit_expression(ast, condition);
+        if self.is_unwinding() {
+            return None;
+        }
+        match self.expect_last_value().expect_boolean() {
+            Ok(value) => Some(value),
+            Err(kind) => {
+                let span = ast.query_expr(condition).span(ast);
+                self.raise(kind, span);
+                None
+            }
+        }
+    }
+
+    /// Frees the strings the program can't reach anymore, if a collection
+    /// is due. Only called where no value is held outside of the variables
+    /// and `temporaries`.
+    fn collect_strings(&mut self) {
+        if !self.strings.should_collect() {
+            return;
+        }
+        let roots = self
+            .frames
+            .values()
+            .chain(&self.temporaries)
+            .chain(self.global_scope.constants.values())
+            .copied();
+        self.strings.collect(roots);
+    }
+
+    fn push_frame(&mut self) {
+        self.frames.push();
     }
 
-    fn pop_frame(&mut self){
+    fn pop_frame(&mut self) {
         self.frames.pop();
     }
 
-    fn expect_last_value(&self) -> Value{
+    fn expect_last_value(&self) -> Value {
         *self
-        .last_value
-        .as_ref()
-        .expect("expected last value to be set")
+            .last_value
+            .as_ref()
+            .expect("expected last value to be set")
     }
 }
 
-impl<'a> ASTVisitor for ASTEval<'a>{
-    fn visit_body(&mut self,&mut ast, body: &Body){
+impl<'a> ASTVisitor for ASTEval<'a> {
+    fn visit_expression(&mut self, ast: &mut Ast, expr_id: ExprId) {
+        if self.mode == EvalMode::Runtime && !self.step(ast, expr_id) {
+            return;
+        }
+        self.do_visit_expression(ast, expr_id);
+        if self.mode == EvalMode::Runtime {
+            self.check_heap(ast, expr_id);
+        }
+    }
+
+    fn visit_statement(&mut self, ast: &mut Ast, stmt_id: StmtId) {
+        if self.debugger.is_some() && !self.debug_statement(ast, stmt_id) {
+            return;
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.hit(stmt_id);
+        }
+        self.do_visit_statement(ast, stmt_id);
+    }
+
+    fn visit_body(&mut self, ast: &mut Ast, body: &Body) {
         self.push_frame();
-        for statement in body.iter(){
+        for statement in body.iter() {
             self.visit_statement(ast, *statement);
+            if self.is_unwinding() {
+                break;
+            }
         }
         self.pop_frame();
     }
 
-    fn visit_func_decl(&mut self, _ast: &mut Ast, _func_declaration: &FuncDeclaration, _item_ID: ItemId){
-
-    }
-    
-} 
+    fn visit_func_decl(&mut self, _ast: &mut Ast, _func_decl: &FuncDeclaration, _item_id: ItemId) {}
 
-fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt){
-    self.push_frame();
-    self.visit_statement(ast, while_statement.condition);
-    while self.expect_last_value().expect_boolean(){
-        self.visit_body(ast, &while_statement.body)
-        self.visit_expression(ast, while_statement.condition)
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_stmt: &ReturnStmt) {
+        if self.reject_in_const(|| return_stmt.return_keyword.span) {
+            return;
+        }
+        let value = match &return_stmt.return_value {
+            Some(expr) => {
+                self.visit_expression(ast, *expr);
+                if self.is_unwinding() {
+                    return;
+                }
+                self.expect_last_value()
+            }
+            None => Value::Void,
+        };
+        self.return_value = Some(value);
     }
-    self.pop_frame();
-}
 
-fn visit_block_expr(&mut self, ast: &mut Ast, block_statement: &BlockExpr, _expr: &Expr){
-    self.push_frame();
-    for statement in block_statement.stmts{
-        self.visit_statement(ast, *statement)
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_stmt: &WhileStmt) {
+        // Loops are rejected outright, since whether they terminate can't be
+        // known before running them.
+        if self.reject_in_const(|| while_stmt.while_keyword.span) {
+            return;
+        }
+        self.push_frame();
+        loop {
+            self.collect_strings();
+            if self.evaluate_condition(ast, while_stmt.condition) != Some(true) {
+                break;
+            }
+            self.visit_body(ast, &while_stmt.body);
+            if self.is_unwinding() {
+                break;
+            }
+        }
+        self.pop_frame();
     }
-    self.pop_frame();
-}
 
-fn visit_if_expression(&mut self, ast: &Ast, ifexpr: &IfExpr, _expr: &Expr){
-    self.push_frame();
-    self.visit_statement(ast, *if_statement.condition)
-    if self.expect_last_value().expect_boolean(){
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, _expr: &Expr) {
         self.push_frame();
-        for statement in if_statement.then_branch.iter(){
+        for statement in &block_expr.stmts {
             self.visit_statement(ast, *statement);
+            if self.is_unwinding() {
+                break;
+            }
         }
         self.pop_frame();
     }
-    else if let Some(else_branch)  = &if_statement.else_branch{
+
+    fn visit_if_expression(&mut self, ast: &mut Ast, if_expr: &IfExpr, _expr: &Expr) {
         self.push_frame();
-        for statement in else_branch.body.iter(){
-            self.visit_statement(ast, *statement);
+        match self.evaluate_condition(ast, if_expr.condition) {
+            Some(true) => self.visit_body(ast, &if_expr.then_branch),
+            Some(false) => {
+                if let Some(else_branch) = &if_expr.else_branch {
+                    self.visit_body(ast, &else_branch.body);
+                }
+            }
+            None => {}
         }
         self.pop_frame();
-    }      
-    self.pop();
-}
+    }
 
-fn visit_let_statement(&mut self, ast: &Ast, letstmt: &LetStmt, _expr: &Expr){
-    self.visit_statement(ast, *let_statement.initializer);
-    self.frames.insert(let_statement.variable_idx, self.expect_last_value());
-}
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        // Definite-assignment analysis guarantees a variable declared without
+        // an initializer is assigned before it is read, so the placeholder
+        // value is never observed.
+        let value = match let_stmt.initializer {
+            Some(initializer) => {
+                self.visit_expression(ast, initializer);
+                if self.is_unwinding() {
+                    return;
+                }
+                self.expect_last_value()
+            }
+            None => Value::Null,
+        };
+        self.frames.insert(let_stmt.variable_idx, value);
+    }
 
-fn visit_call_expression(&mut self, ast: &Ast, callexpr: &CallExpr, _expr: &Expr){
-    let function_name = call_expression.function_name();
-    let function = self.global_scope.lookup_function(funtion_name).map(|f| self.global_scope.get(f)).expect(format!("Function {} not found", function_name).as_str());
-    let mut arguments = Vec::new();
-    for argument in &call_expression.arguments{
-        self.visit_statement(ast, *statement)
-        argument.push(self.last_value.unwrap());
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expr: &CallExpr, expr: &Expr) {
+        if self.reject_in_const(|| expr.span(ast)) {
+            return;
+        }
+        let first = self.temporaries.len();
+        for argument in &call_expr.arguments {
+            self.visit_expression(ast, *argument);
+            if self.is_unwinding() {
+                self.temporaries.truncate(first);
+                return;
+            }
+            self.temporaries.push(self.expect_last_value());
+        }
+        let arguments = self.temporaries.split_off(first);
+        self.call(ast, call_expr.function_idx, &arguments, expr.span(ast));
     }
-    self.push_frame();
-    for (argument, param) in arguments.iter().zip(function.parameters.iter()){
-        self.frames.insert(*param, *argument);
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, expr: &Expr) {
+        if self.reject_in_const(|| expr.span(ast)) {
+            return;
+        }
+        self.visit_expression(ast, assign_expr.expression);
+        if self.is_unwinding() {
+            return;
+        }
+        if !self
+            .frames
+            .update(assign_expr.variable_idx, self.expect_last_value())
+        {
+            self.raise_undeclared(assign_expr.variable_idx, expr.span(ast));
+        }
     }
 
-    for stmt in &*function.body{
-        self.visit_statement(ast, *stmt);
+    fn visit_variable_expression(&mut self, ast: &mut Ast, var_expr: &VarExpr, _expr: &Expr) {
+        if let Some(value) = self.global_scope.constants.get(&var_expr.variable_idx) {
+            self.last_value = Some(*value);
+            return;
+        }
+        if self.mode == EvalMode::Const && self.frames.get(&var_expr.variable_idx).is_none() {
+            if self.evaluating.contains(&var_expr.variable_idx) {
+                self.fail(ConstEvalError::Cycle(
+                    var_expr.variable_idx,
+                    var_expr.identifier.span,
+                ));
+            } else if !self.const_items.contains_key(&var_expr.variable_idx) {
+                self.fail(ConstEvalError::NotConstant(var_expr.identifier.span));
+            } else {
+                match self.evaluate_const(ast, var_expr.variable_idx) {
+                    Ok(value) => self.last_value = Some(value),
+                    Err(error) => self.fail(error),
+                }
+            }
+            return;
+        }
+        match self.frames.get(&var_expr.variable_idx) {
+            Some(value) => self.last_value = Some(*value),
+            None => self.raise_undeclared(var_expr.variable_idx, var_expr.identifier.span),
+        }
     }
-    self.pop_frame();
-}
 
-fn visit_assignment_expression(&mut self, ast: &Ast, assignexpr: &AssignExpr, _expr: &Expr){
-    self.visit_statement(ast, assignexpr.expression);
-    self.frames.update(assignexpr.variable_idx  , self.last_value.unwrap());
-}
+    fn visit_number_expression(&mut self, _ast: &mut Ast, number_expr: &NumberExpr, _expr: &Expr) {
+        self.last_value = Some(Value::Number(number_expr.number));
+    }
 
-fn visit_variable_expression(&mut self, _ast: &Ast, varexpr: &VarExpr, _expr: &Expr){
-    let identifier = &varexpr.identifier.span.literal;
-    self.last_value = Some(
-        *self.frames.get(&varexpr.variable_idx).expect(format!
-            ("Variables {} "{}" not found", varexpr.variable_idx.as_index(), identifier).as_str()
-    ),
-);
-}
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, bool_expr: &BoolExpr, _expr: &Expr) {
+        self.last_value = Some(Value::Boolean(bool_expr.value));
+    }
 
-fn visit_number_expression(&mut self, _ast: &Ast, numberexpr: &NumberExpr, _expr: &Expr){
-    self.last_value = Some(Value::Number(number.number));
-}
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {
+        self.last_value = Some(Value::Null);
+    }
 
-fn visit_boolean_expression(&mut self, _ast: &Ast, boolexpr: &BoolExpr, _expr: &Expr){
-    self.last_value = Some(Value::Boolean(boolean.value));
-}
+    fn visit_string_expression(&mut self, ast: &mut Ast, string_expr: &StringExpr, _expr: &Expr) {
+        // The values stay temporaries until the string is built, like the
+        // operands of the virtual machine's `Interpolate`.
+        let first = self.temporaries.len();
+        let mut value = String::new();
+        for part in &string_expr.parts {
+            match part {
+                StringPart::Literal(text) => value.push_str(text),
+                StringPart::Interpolation(expr) => {
+                    self.visit_expression(ast, *expr);
+                    if self.is_unwinding() {
+                        self.temporaries.truncate(first);
+                        return;
+                    }
+                    let part = self.expect_last_value();
+                    value.push_str(&part.display(&self.strings));
+                    self.temporaries.push(part);
+                }
+            }
+        }
+        self.temporaries.truncate(first);
+        self.last_value = Some(Value::String(self.strings.intern(&value)));
+    }
 
-fn visit_error(&mut self, _ast: &Ast, _span: &TextSpan){
-    panic!("Cannot Eval an Error Expression!")
-}
+    fn visit_error(&mut self, _ast: &mut Ast, span: &TextSpan) {
+        self.raise(RuntimeErrorKind::InvalidExpression, *span);
+    }
 
-fn visit_unary_expression(&mut self, ast: &Ast, uexpr: &UnaryExpr, _expr: &Expr){
-    self.visit_expression(ast, unary_expression.operand);
-    self.last_value = Some(Value::Number(match unary_expression.operand.kind{
-        UnOpKind::Minus => -operand,
-        UnOpKind::Bitwise => !operand,
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+        if self.is_unwinding() {
+            return;
+        }
+        let result = unary_operation(&unary_expr.operator.kind, self.expect_last_value());
+        self.set_result(result, expr.span(ast));
     }
-)
-);
-}
 
-fn visit_binary_expression(&mut self, ast: &Ast, binexpr: &BinaryExpr, _expr: &Expr){
-    self.visit_expression(ast, binexpr.left);
-    let left = self.expect_last_value();
-    self.visit_expression(ast, binexpr.right);
-    let right= self.expect_last_value();
-    self.last_value = Some(match binexpr.operator.kind{
-        BinOpKind::Plus =>  Value::Number(left.expect_number() + right.expect_number()),
-        BinOpKind::Minus =>  Value::Number(left.expect_number() - right.expect_number()),
-        BinOpKind::Multiply =>  Value::Number(left.expect_number() * right.expect_number()),
-        BinOpKind::Divide =>  Value::Number(left.expect_number() / right.expect_number()),
-        BinOpKind::Modulo =>  Value::Number(left.expect_number() % right.expect_number()),
-        BinOpKind::BitwiseAnd =>  Value::Number(left.expect_number() & right.expect_number()),
-        BinOpKind::BitwiseOr =>  Value::Number(left.expect_number() | right.expect_number()),
-        BinOpKind::BitwiseXor =>  Value::Number(left.expect_number() ^ right.expect_number()),
-        
-        BinOpKind::Power =>  Value::{Number(left.expect_number().pow(right.expect_number()as u32))}
-
-        BinOpKind::Equalsto =>  Value::Boolean(left == right),
-        BinOpKind::NotEqualsto =>  Value::Boolean(left != right),
-        BinOpKind::LessThan =>  Value::Boolean(left.expect_number() < right.expect_number()),
-        BinOpKind::GreaterThan =>  Value::Boolean(left.expect_number() > right.expect_number()),
-        BinOpKind::LessThanEqualTo =>  {
-            Value::Boolean(left.expect_number() <= right.expect_number()),
-        }
-        BinOpKind::GreaterThanEqualTo =>  {
-            Value::Boolean(left.expect_number() >= right.expect_number()),
-        }
-    });
-}
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expr: &BinaryExpr, expr: &Expr) {
+        self.visit_expression(ast, binary_expr.left);
+        if self.is_unwinding() {
+            return;
+        }
+        let left = self.expect_last_value();
+        self.temporaries.push(left);
+        self.visit_expression(ast, binary_expr.right);
+        self.temporaries.pop();
+        if self.is_unwinding() {
+            return;
+        }
+        let right = self.expect_last_value();
+        let result =
+            binary_operation(&binary_expr.operator.kind, left, right, &mut self.strings);
+        self.set_result(result, expr.span(ast));
+    }
 
-fn visit_parenthesized_expression(&mut self, ast: &mut Ast, parenthesizedexpr: &ParenthesizedExpr, _expr: &Expr){
-    self.visit_expression(ast, parenthesizedexpr.inner);
-}
\ No newline at end of file
+    fn visit_parenthesized_expression(
+        &mut self,
+        ast: &mut Ast,
+        parenthesized_expr: &ParenthesizedExpr,
+        _expr: &Expr,
+    ) {
+        self.visit_expression(ast, parenthesized_expr.inner);
+    }
+}
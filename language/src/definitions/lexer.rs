@@ -1,20 +1,24 @@
-use std::dmt::(Display, Formatter);
+use std::fmt::{Display, Formatter};
 
-use crate::text::span::TextSpan;
+use crate::text::{source_map::FileId, span::TextSpan};
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind{
     Number(i64),
     Plus,
     Minus,
     Asterisk,
     Slash,
+    Percent,
     Ampersand,
     Equals,
     Pipe,
     Caret,
     DoubleAsterisk,
     Tilde,
+    Hash,
     Whitespace,
+    Comment,
     DoubleEquals,
     LessThan,
     LessThanEquals,
@@ -35,6 +39,7 @@ pub enum TokenKind{
     Semicolon,
     Arrow,
     FatArrow,
+    QuestionMark,
     True,
     False,
     Let,
@@ -48,25 +53,32 @@ pub enum TokenKind{
     Break,
     Continue,
     Null,
+    Type,
+    Struct,
+    Enum,
+    Const,
     Eof,
     Error
 }
 
 impl Display for TokenKind{
-    fn fmt(&self, f: &mut Formatter<'_>)->Result<(), Error>{
+    fn fmt(&self, f: &mut Formatter<'_>)->std::fmt::Result{
         match self{
             TokenKind::Number(value)=>write!(f, "Number({})", value),
             TokenKind::Plus=>write!(f, "Plus"),
             TokenKind::Minus=>write!(f, "Minus"),
             TokenKind::Asterisk=>write!(f, "Asterisk"),
             TokenKind::Slash=>write!(f, "Slash"),
+            TokenKind::Percent=>write!(f, "Percent"),
             TokenKind::Ampersand=>write!(f, "Ampersand"),
             TokenKind::Equals=>write!(f, "Equals"),
             TokenKind::Pipe=>write!(f, "Pipe"),
             TokenKind::Caret=>write!(f, "Caret"),
             TokenKind::DoubleAsterisk=>write!(f, "DoubleAsterisk"),
             TokenKind::Tilde=>write!(f, "Tilde"),
+            TokenKind::Hash=>write!(f, "Hash"),
             TokenKind::Whitespace=>write!(f, "Whitespace"),
+            TokenKind::Comment=>write!(f, "Comment"),
             TokenKind::DoubleEquals=>write!(f, "DoubleEquals"),
             TokenKind::LessThan=>write!(f, "LessThan"),
             TokenKind::LessThanEquals=>write!(f, "LessThanEquals"),
@@ -87,6 +99,7 @@ impl Display for TokenKind{
             TokenKind::Semicolon=>write!(f, "Semicolon"),
             TokenKind::Arrow=>write!(f, "Arrow"),
             TokenKind::FatArrow=>write!(f, "FatArrow"),
+            TokenKind::QuestionMark=>write!(f, "QuestionMark"),
             TokenKind::True=>write!(f, "True"),
             TokenKind::False=>write!(f, "False"),
             TokenKind::Let=>write!(f, "Let"),
@@ -100,12 +113,17 @@ impl Display for TokenKind{
             TokenKind::Break=>write!(f, "Break"),
             TokenKind::Continue=>write!(f, "Continue"),
             TokenKind::Null=>write!(f, "Null"),
+            TokenKind::Type=>write!(f, "Type"),
+            TokenKind::Struct=>write!(f, "Struct"),
+            TokenKind::Enum=>write!(f, "Enum"),
+            TokenKind::Const=>write!(f, "Const"),
             TokenKind::Eof=>write!(f, "Eof"),
             TokenKind::Error=>write!(f, "Error"),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token{
     pub kind: TokenKind,
     pub span: TextSpan,
@@ -118,37 +136,45 @@ impl Token{
 }
 
 pub struct Lexer<'a>{
-    input: &'a String,
-    position: usize,
+    input: &'a str,
+    file_id: FileId,
+    current_pos: usize,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str, file_id: FileId) -> Self {
         Self {
             input,
+            file_id,
             current_pos: 0,
         }
     }
 
     pub fn next_token(&mut self) -> Option<Token> {
         if self.current_pos == self.input.len() {
-            let eof_char: char = '\0';
             self.current_pos += 1;
             return Some(Token::new(
                 TokenKind::Eof,
-                TextSpan::new(0, 0, eof_char.to_string()),
+                TextSpan::new(self.file_id, self.input.len(), self.input.len()),
             ));
         }
         let c = self.current_char();
-        return c.map(|c| {
+        c.map(|c| {
             let start = self.current_pos;
-            let mut kind = TokenKind::Bad;
+            let kind;
             if Self::is_number_start(&c) {
                 let number: i64 = self.consume_number();
                 kind = TokenKind::Number(number);
+            } else if self.input[self.current_pos..].starts_with("//") {
+                self.consume_comment();
+                kind = TokenKind::Comment;
             } else if Self::is_whitespace(&c) {
                 self.consume();
                 kind = TokenKind::Whitespace;
+            } else if c == '"' {
+                self.consume();
+                self.consume_string();
+                kind = TokenKind::String;
             } else if Self::is_identifier_start(&c) {
                 let identifier = self.consume_identifier();
                 kind = match identifier.as_str() {
@@ -158,8 +184,13 @@ impl<'a> Lexer<'a> {
                     "true" => TokenKind::True,
                     "false" => TokenKind::False,
                     "while" => TokenKind::While,
-                    "func" => TokenKind::Func,
+                    "func" => TokenKind::Function,
                     "return" => TokenKind::Return,
+                    "null" => TokenKind::Null,
+                    "type" => TokenKind::Type,
+                    "struct" => TokenKind::Struct,
+                    "enum" => TokenKind::Enum,
+                    "const" => TokenKind::Const,
                     _ => TokenKind::Identifier,
                 }
             } else {
@@ -167,10 +198,46 @@ impl<'a> Lexer<'a> {
             }
 
             let end = self.current_pos;
-            let literal = self.input[start..end].to_string();
-            let span = TextSpan::new(start, end, literal);
+            let span = TextSpan::new(self.file_id, start, end);
             Token::new(kind, span)
-        });
+        })
+    }
+
+    /// Consumes the rest of a string literal after its opening quote. Quotes
+    /// inside an interpolation start a nested string rather than ending
+    /// this one; the parser splits the literal into its parts.
+    fn consume_string(&mut self) {
+        // The interpolation depth of each string being consumed, the
+        // innermost last, so that nested strings don't recurse.
+        let mut depths = vec![0];
+        while let Some(c) = self.consume() {
+            let depth = depths.last_mut().unwrap();
+            match c {
+                '\\' => {
+                    self.consume();
+                }
+                '"' if *depth == 0 => {
+                    depths.pop();
+                    if depths.is_empty() {
+                        return;
+                    }
+                }
+                '"' => depths.push(0),
+                '{' => *depth += 1,
+                '}' if *depth > 0 => *depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Consumes a `//` comment up to, but not including, the end of the line.
+    fn consume_comment(&mut self) {
+        while let Some(c) = self.current_char() {
+            if c == '\n' {
+                break;
+            }
+            self.consume();
+        }
     }
 
     fn consume_punctuation(&mut self) -> TokenKind {
@@ -185,12 +252,12 @@ impl<'a> Lexer<'a> {
             ),
             '%' => TokenKind::Percent,
             '/' => TokenKind::Slash,
-            '(' => TokenKind::LeftParen,
-            ')' => TokenKind::RightParen,
+            '(' => TokenKind::OpenParenthesis,
+            ')' => TokenKind::CloseParenthesis,
             '=' => self.lex_potential_double_char_operator(
                 '=',
                 TokenKind::Equals,
-                TokenKind::EqualsEquals,
+                TokenKind::DoubleEquals,
             ),
             '&' => TokenKind::Ampersand,
             '|' => TokenKind::Pipe,
@@ -207,15 +274,19 @@ impl<'a> Lexer<'a> {
                 TokenKind::LessThanEquals,
             ),
             '!' => {
-                self.lex_potential_double_char_operator('=', TokenKind::Bad, TokenKind::BangEquals)
+                self.lex_potential_double_char_operator('=', TokenKind::Bang, TokenKind::BangEquals)
             }
             '{' => TokenKind::OpenBrace,
             '}' => TokenKind::CloseBrace,
+            '[' => TokenKind::OpenBracket,
+            ']' => TokenKind::CloseBracket,
+            '#' => TokenKind::Hash,
             ',' => TokenKind::Comma,
             ':' => TokenKind::Colon,
-            ';' => TokenKind::SemiColon,
+            ';' => TokenKind::Semicolon,
+            '?' => TokenKind::QuestionMark,
 
-            _ => TokenKind::Bad,
+            _ => TokenKind::Error,
         }
     }
 
@@ -238,7 +309,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn is_number_start(c: &char) -> bool {
-        c.is_digit(10)
+        c.is_ascii_digit()
     }
 
     fn is_identifier_start(c: &char) -> bool {
@@ -250,7 +321,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn current_char(&self) -> Option<char> {
-        self.input.chars().nth(self.current_pos)
+        self.input.get(self.current_pos..)?.chars().next()
     }
 
     fn consume(&mut self) -> Option<char> {
@@ -258,7 +329,7 @@ impl<'a> Lexer<'a> {
             return None;
         }
         let c = self.current_char();
-        self.current_pos += 1;
+        self.current_pos += c.map_or(1, |c| c.len_utf8());
 
         c
     }
@@ -279,7 +350,7 @@ impl<'a> Lexer<'a> {
     fn consume_number(&mut self) -> i64 {
         let mut number: i64 = 0;
         while let Some(c) = self.current_char() {
-            if c.is_digit(10) {
+            if c.is_ascii_digit() {
                 self.consume().unwrap();
                 number = number * 10 + c.to_digit(10).unwrap() as i64;
             } else {
@@ -0,0 +1,91 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// How many calls may be nested before evaluation fails with a stack
+/// overflow, unless configured otherwise. Each call nests several visits on
+/// the native stack, so this leaves room for unoptimized builds running on a
+/// thread with the default 2 MiB stack.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+/// How deeply expressions and statements may nest, e.g. parentheses inside
+/// parentheses. Checking and running a program recurse into nested code on
+/// the native stack, so deeper code fails to compile rather than
+/// overflowing it.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+/// Bounds on the resources one evaluation may use, for running untrusted
+/// programs. Exceeding one stops evaluation with a runtime error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// How many expressions may be evaluated, or `None` for no limit.
+    pub max_steps: Option<u64>,
+    /// How many calls may be nested. Raising it far above the default needs
+    /// a correspondingly larger native stack.
+    pub max_call_depth: usize,
+    /// How many bytes strings and variables may occupy, or `None` for no
+    /// limit. Strings nothing refers to are only freed at loop heads and
+    /// function entries, so they count until then.
+    pub max_heap_size: Option<usize>,
+}
+
+impl Limits {
+    /// No step or heap limit, and the default call depth.
+    pub fn new() -> Self {
+        Self {
+            max_steps: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_heap_size: None,
+        }
+    }
+
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    pub fn with_max_heap_size(mut self, max_heap_size: usize) -> Self {
+        self.max_heap_size = Some(max_heap_size);
+        self
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cancels a running evaluation from another thread. The interpreter polls
+/// it before every expression, the virtual machine at least once per call
+/// and loop iteration, and both stop with an interrupted error once it is
+/// set.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Clears the request, so the handle can cancel a later evaluation.
+    pub fn reset(&self) {
+        self.interrupted.store(false, Ordering::Relaxed);
+    }
+}
@@ -1,263 +1,595 @@
-use std::{
-    fmt::{Display, Formatter},
-    hash::Hash,
-    ops::Deref
-};
+use std::ops::Deref;
 
 use navsharp::{idx, Idx, IdxVec};
 use printer::ASTPrinter;
-use termion::color::{Fg, Reset};
 use visitor::ASTVisitor;
 
 use crate::{
-    ast::lexer::Token,
-    compilation_unit{FunctionIdx, VariableIdx},
-    text:span::TextSpan,
+    compilation_unit::{FunctionIdx, VariableIdx},
+    definitions::lexer::Token,
+    text::{source_map::SourceMap, span::TextSpan},
     typecheck::Type,
 };
 
-pub mod lexer;
+pub mod builtins;
+pub mod bytecode;
+pub mod debugger;
 pub mod eval;
+pub mod lexer;
+pub mod limits;
 pub mod parser;
 pub mod printer;
+pub mod profiler;
+pub mod runtime_error;
 pub mod visitor;
+pub mod vm;
 
-idx!(ExprID);
-idx!(ItemID);
+idx!(ExprId);
+idx!(ItemId);
 idx!(StmtId);
 
-#[derive(Debug,Clone)]
-pub struct Ast{
+#[derive(Debug, Clone)]
+pub struct Ast {
     pub statements: IdxVec<StmtId, Stmt>,
-    pub expression: IdxVec<ExprID, Expr>,
-    pub items: IdxVec<ItemID, Item>,
+    pub expressions: IdxVec<ExprId, Expr>,
+    pub items: IdxVec<ItemId, Item>,
 }
 
-impl Ast{
-    pub fn new()=> Self{
-        Self{
+impl Ast {
+    pub fn new() -> Self {
+        Self {
             statements: IdxVec::new(),
             expressions: IdxVec::new(),
             items: IdxVec::new(),
         }
     }
 
-    pub fn query_item(&self, itemid: ItemID) -> &Item{
-        &self.items[itemid];
+    pub fn query_item(&self, item_id: ItemId) -> &Item {
+        &self.items[item_id]
     }
 
-    pub fn query_expr(&self, exprid: ExprID) -> &Expr{
-        &self.expressions[exprid];
+    pub fn query_expr(&self, expr_id: ExprId) -> &Expr {
+        &self.expressions[expr_id]
     }
 
-    pub fn query_expr_mut(&self, exprid: ExprID) -> &mut Expr{
-        &mut self.expressions[exprid];
+    pub fn query_expr_mut(&mut self, expr_id: ExprId) -> &mut Expr {
+        &mut self.expressions[expr_id]
     }
 
-    pub fn query_stmt(&self, stmtid: StmtID) -> &Stmt{
-        &self.statements[stmtid];
+    pub fn query_stmt(&self, stmt_id: StmtId) -> &Stmt {
+        &self.statements[stmt_id]
     }
 
-    pub fn query_stmt_mut(&self, stmtid: StmtID) -> &mut Stmt{
-        &mut self.statements[stmtid];
+    pub fn query_stmt_mut(&mut self, stmt_id: StmtId) -> &mut Stmt {
+        &mut self.statements[stmt_id]
     }
 
-    pub fn set_variable(&mut self, exprid: ExprID, variable_idx: VariableIdx){
-        let variable = self.query_expr_mut(exprid);
-        match &mut expr.kind{
-            ExprKind::Assignment(assignexpr) =>{
-                assignexpr.variable_idx = variable_idx;
+    pub fn set_variable(&mut self, expr_id: ExprId, variable_idx: VariableIdx) {
+        let expr = self.query_expr_mut(expr_id);
+        match &mut expr.kind {
+            ExprKind::Assignment(assign_expr) => {
+                assign_expr.variable_idx = variable_idx;
             }
-            ExprKind::Variable(varexpr)=> {
-                varexpr.variable_idx = variable_idx;
+            ExprKind::Variable(var_expr) => {
+                var_expr.variable_idx = variable_idx;
             }
-
-            _ => unreachable!("Cannot set variables of non-variable statement");
+            _ => unreachable!("Cannot set variables of non-variable statement"),
         }
     }
 
-    pub fn set_variable(&mut self, exprid: ExprID, variable_idx: VariableIdx){
-        let variable = self.query_expr_mut(exprid);
-        match &mut expr.kind{
-            ExprKind::Assignment(assignexpr) =>{
-                assignexpr.variable_idx = variable_idx;
+    pub fn set_function(&mut self, expr_id: ExprId, function_idx: FunctionIdx) {
+        let expr = self.query_expr_mut(expr_id);
+        match &mut expr.kind {
+            ExprKind::Call(call_expr) => {
+                call_expr.function_idx = function_idx;
             }
-            ExprKind::Variable(varexpr)=> {
-                varexpr.variable_idx = variable_idx;
+            _ => unreachable!("Cannot set function of non-call expression"),
+        }
+    }
+
+    pub fn set_let_variable(&mut self, stmt_id: StmtId, variable_idx: VariableIdx) {
+        match &mut self.query_stmt_mut(stmt_id).kind {
+            StmtKind::Let(let_stmt) => {
+                let_stmt.variable_idx = variable_idx;
             }
+            _ => unreachable!("Cannot set variable of non-let statement"),
+        }
+    }
+
+    pub fn set_item_attributes(&mut self, item_id: ItemId, attributes: Vec<Attribute>) {
+        self.items[item_id].attributes = attributes;
+    }
 
-            _ => unreachable!("Cannot set variables of non-variable statement");
+    pub fn set_item_function(&mut self, item_id: ItemId, function_idx: FunctionIdx) {
+        match &mut self.items[item_id].kind {
+            ItemKind::Function(func_decl) => {
+                func_decl.idx = function_idx;
+            }
+            _ => unreachable!("Cannot set function of non-function item"),
         }
     }
 
-    pub fn set_function(&mut self, exprid: ExprID, funcidx: FunctionIdx){
-        let expr = self.query_expr_mut(exprid);
-        match &mut expr.kind{
-            ExprKind::Call(callexpr) => {
-                callexpr.funcidx = funcidx;
+    pub fn set_item_variable(&mut self, item_id: ItemId, variable_idx: VariableIdx) {
+        match &mut self.items[item_id].kind {
+            ItemKind::Const(const_decl) => {
+                const_decl.variable_idx = variable_idx;
             }
+            _ => unreachable!("Cannot set variable of non-const item"),
         }
     }
 
-    pub fn set_type(&mut self, exprid: ExprID, type: Type){
-        let expr = &mut self.expressions[exprid];
+    pub fn set_type(&mut self, expr_id: ExprId, ty: Type) {
+        let expr = &mut self.expressions[expr_id];
         expr.ty = ty;
     }
 
-    fn stmt_of_kind(&mut self, kind:StmtKind) -> &Stmt{
-        let stmt = Stmt::new(kind, StmtId::new(0))
+    fn stmt_of_kind(&mut self, kind: StmtKind) -> &Stmt {
+        let stmt = Stmt::new(kind, StmtId::new(0));
         let id = self.statements.push(stmt);
-        self.statement[id].id = id;
-        &self.statement[id]
+        self.statements[id].id = id;
+        &self.statements[id]
+    }
+
+    pub fn expression_statement(&mut self, expr_id: ExprId) -> &Stmt {
+        self.stmt_of_kind(StmtKind::Expr(expr_id))
     }
 
-    pub fn expression_statement(&mut self, exprid: ExprID) -> &Stmt{
-        self.stmt_of_kind(StmtKind::Expr(exprid));
+    pub fn let_statement(
+        &mut self,
+        identifier: Token,
+        initializer: Option<ExprId>,
+        type_annotation: Option<StaticTypeAnnotation>,
+    ) -> &Stmt {
+        self.stmt_of_kind(StmtKind::Let(LetStmt {
+            identifier,
+            initializer,
+            type_annotation,
+            variable_idx: VariableIdx::unreachable(),
+        }))
     }
 
-    pub fn let_statement(&mut self, identifier: Token, initializer: ExprID, type_annotation: Option<StaticTypeAnnotation>)  -> &Stmt{
-        self.stmt_of_kind(StmtKind::Let(LetStmt{identifier,initializer,type_annotation,variable_idx: VariableIdx::new(0)}))
+    pub fn if_expression(
+        &mut self,
+        if_keyword: Token,
+        condition: ExprId,
+        then_branch: Body,
+        else_branch: Option<ElseBranch>,
+    ) -> &Expr {
+        self.expr_from_kind(ExprKind::If(IfExpr {
+            if_keyword,
+            condition,
+            then_branch,
+            else_branch,
+        }))
     }
 
-    pub fn if_expression(&mut self, if_keyword: Token, condition: ExprID, then: Body, else_branch: Option<ElseBranch>,) -> &Expr{
-        self.expr_from_kind(ExprKind::if(ifexpr{if_keyword, condition,body,}))
-    T
+    pub fn while_statement(&mut self, while_keyword: Token, condition: ExprId, body: Body) -> &Stmt {
+        self.stmt_of_kind(StmtKind::While(WhileStmt {
+            while_keyword,
+            condition,
+            body,
+        }))
+    }
 
-    pub fn while_statement(&mut self, while_keyword: Token, condition: ExprID, body: Body) -> &Stmt{
-        self.stmt_of_kind(StmtKind::While(whileStmt{while_keyword, condition, body,}))
+    pub fn block_statement(
+        &mut self,
+        left_brace: Token,
+        statements: Vec<StmtId>,
+        right_brace: Token,
+    ) -> &Expr {
+        self.expr_from_kind(ExprKind::Block(BlockExpr {
+            left_brace,
+            stmts: statements,
+            right_brace,
+        }))
     }
 
-    pub fn block_statement(&mut self, left_brace:: Token, statements: Vec<StmtID>, right_brace: token) -> &Expr {
-        self.expr_from_kind(ExprKind::Block(BlockExpr{left_brace, stmts: statements, right_brace}))
+    pub fn return_statement(&mut self, return_keyword: Token, return_value: Option<ExprId>) -> &Stmt {
+        self.stmt_of_kind(StmtKind::Return(ReturnStmt {
+            return_keyword,
+            return_value,
+        }))
     }
 
-    pub fn return_statement(&mut self, return_statement: Token, return_value: Option<ExprID>) -> &Stmt{
-        self.stmt_of_kind(StmtKind::Return(ReturnStmt{return_statement, return_value,}))
+    pub fn func_item(
+        &mut self,
+        func_keyword: Token,
+        identifier: Token,
+        parameters: Vec<FuncDeclParameter>,
+        body: Body,
+        return_type: Option<FunctionReturnTypeSyntax>,
+        function_idx: FunctionIdx,
+    ) -> &Item {
+        self.item_from_kind(ItemKind::Function(FuncDeclaration {
+            func_keyword,
+            identifier,
+            parameters,
+            body,
+            return_type,
+            idx: function_idx,
+        }))
     }
 
-    pub fn func_item(&mut self, func_keyword:Token, identifier: Token, parameters: Vec<FuncDeclParameter>, body: Body, return_type: Option<FunctionReturnTypeSyntax>, function_idx: FunctionIdx,) -> &Item{
-        return self.item_from_kind(ItemKind::Function(FuncDeclaration{
-            func_keyword, identifier, parameters, body, return_type, idx: function_idx
+    pub fn type_alias_item(
+        &mut self,
+        type_keyword: Token,
+        identifier: Token,
+        equals: Token,
+        type_name: Token,
+        question_mark: Option<Token>,
+    ) -> &Item {
+        self.item_from_kind(ItemKind::TypeAlias(TypeAliasDeclaration {
+            type_keyword,
+            identifier,
+            equals,
+            type_name,
+            question_mark,
         }))
     }
 
+    pub fn struct_item(
+        &mut self,
+        struct_keyword: Token,
+        identifier: Token,
+        open_brace: Token,
+        fields: Vec<StructField>,
+        close_brace: Token,
+    ) -> &Item {
+        self.item_from_kind(ItemKind::Struct(StructDeclaration {
+            struct_keyword,
+            identifier,
+            open_brace,
+            fields,
+            close_brace,
+        }))
+    }
 
-    pub fn item_from_kind(&mut self, kind: ItemKind) -> &Item{
-        let item = Item::new(kind, ItemID::new(0))
+    pub fn enum_item(
+        &mut self,
+        enum_keyword: Token,
+        identifier: Token,
+        open_brace: Token,
+        variants: Vec<Token>,
+        close_brace: Token,
+    ) -> &Item {
+        self.item_from_kind(ItemKind::Enum(EnumDeclaration {
+            enum_keyword,
+            identifier,
+            open_brace,
+            variants,
+            close_brace,
+        }))
+    }
+
+    pub fn const_item(
+        &mut self,
+        const_keyword: Token,
+        identifier: Token,
+        type_annotation: StaticTypeAnnotation,
+        equals: Token,
+        initializer: ExprId,
+    ) -> &Item {
+        self.item_from_kind(ItemKind::Const(ConstDeclaration {
+            const_keyword,
+            identifier,
+            type_annotation,
+            equals,
+            initializer,
+            variable_idx: VariableIdx::unreachable(),
+        }))
+    }
+
+    pub fn item_from_kind(&mut self, kind: ItemKind) -> &Item {
+        let item = Item::new(kind, ItemId::new(0));
         let id = self.items.push(item);
-        self.item[id].id = id;
-        &self.item[id]
+        self.items[id].id = id;
+        &self.items[id]
     }
-    
-    pub fn expr_from_kind(&mut self, kind: ExprKind) -> &Expr{
-        let expr = Expr::new(kind, ItemID::new(0), Type::Unresolved);
+
+    pub fn expr_from_kind(&mut self, kind: ExprKind) -> &Expr {
+        let expr = Expr::new(kind, ExprId::new(0), Type::Unresolved);
         let id = self.expressions.push(expr);
         self.expressions[id].id = id;
         &self.expressions[id]
     }
 
-    pub fn number_expression(&mut self, token: Token, number: i64) -> &Expr{
-        self.expr_from_kind(ExprKind::Number(NumberExpr{number, token}))
+    pub fn number_expression(&mut self, token: Token, number: i64) -> &Expr {
+        self.expr_from_kind(ExprKind::Number(NumberExpr { number, token }))
     }
 
-    pub fn binary_expression(&mut self, operator: BinOperator, left: ExprID, right: ExprID) -> &Expr{
-        self.expr_from_kind(ExprKind::Binary(BinaryExpr{operator, left, right}))
+    pub fn binary_expression(&mut self, operator: BinOperator, left: ExprId, right: ExprId) -> &Expr {
+        self.expr_from_kind(ExprKind::Binary(BinaryExpr {
+            operator,
+            left,
+            right,
+        }))
     }
 
-    pub fn parenthesized_expression(&mut self, left_paren: Token, expression: ExprID, right_paren: Token) -> &Expr{
-        self.expr_from_kind(ExprKind::Parenthesized(ParenthesizedExpr{inner: expression,left_paren , right_paren}))
+    pub fn parenthesized_expression(
+        &mut self,
+        left_paren: Token,
+        expression: ExprId,
+        right_paren: Token,
+    ) -> &Expr {
+        self.expr_from_kind(ExprKind::Parenthesized(ParenthesizedExpr {
+            inner: expression,
+            left_paren,
+            right_paren,
+        }))
     }
 
-    pub fn variable_expression(&mut self, identifier: Token) -> &Expr{
-        self.expr_from_kind(ExprKind::Variable(VariablevisualieExpr{identifier, variable_idx: VariableIdx::new(0)}))
+    pub fn variable_expression(&mut self, identifier: Token) -> &Expr {
+        self.expr_from_kind(ExprKind::Variable(VarExpr {
+            identifier,
+            variable_idx: VariableIdx::unreachable(),
+        }))
     }
 
-    pub fn unary_expression(&mut self, operator: UnOperator, operand: ExprID) -> &Expr{
-        self.expr_from_kind(ExprKind::Unary(UnaryExpr{operator, operand}))
+    pub fn unary_expression(&mut self, operator: UnOperator, operand: ExprId) -> &Expr {
+        self.expr_from_kind(ExprKind::Unary(UnaryExpr { operator, operand }))
     }
 
-    pub fn assignment_expression(&mut self, identifier: Token, equals: Token, expression: ExprID) -> &Expr{
-        self.expr_from_kind(ExprKind::Assignment(AssignExpr{identifier, equals, expression, variable_idx: VariableIdx(0),}) )
+    pub fn assignment_expression(
+        &mut self,
+        identifier: Token,
+        equals: Token,
+        expression: ExprId,
+    ) -> &Expr {
+        self.expr_from_kind(ExprKind::Assignment(AssignExpr {
+            identifier,
+            equals,
+            expression,
+            variable_idx: VariableIdx::unreachable(),
+        }))
     }
 
-    pub fn boolean_expression(&mut self, token: Token, value: bool) -> &Expr{
-        self.expr_from_kind(ExprKind::Boolean(BoolExpr{token, value}))
+    pub fn boolean_expression(&mut self, token: Token, value: bool) -> &Expr {
+        self.expr_from_kind(ExprKind::Boolean(BoolExpr { token, value }))
     }
 
-    pub fn call_expression(&mut self, callee: Token, left_paren: Token, arguements: Vec<ExprID>, right_paren: Token) -> &Expr{
-        self.expr_from_kind(ExprKind::Call(callexpr{callee,  arguements,left_paren, right_paren, function_idx: FunctionIdx::unreachable(),}))
+    pub fn null_expression(&mut self, token: Token) -> &Expr {
+        self.expr_from_kind(ExprKind::Null(NullExpr { token }))
     }
 
-    pub fn error_expression(&mut self, span:TextSpan, ) -> &Expr {
+    pub fn string_expression(&mut self, token: Token, parts: Vec<StringPart>) -> &Expr {
+        self.expr_from_kind(ExprKind::String(StringExpr { token, parts }))
+    }
+
+    pub fn call_expression(
+        &mut self,
+        callee: Token,
+        left_paren: Token,
+        arguments: Vec<ExprId>,
+        right_paren: Token,
+    ) -> &Expr {
+        self.expr_from_kind(ExprKind::Call(CallExpr {
+            callee,
+            arguments,
+            left_paren,
+            right_paren,
+            function_idx: FunctionIdx::unreachable(),
+        }))
+    }
+
+    pub fn error_expression(&mut self, span: TextSpan) -> &Expr {
         self.expr_from_kind(ExprKind::Error(span))
     }
 
-    pub fn visit(&mut self, visitor: &mut dyn ASTVisitor){
-        for item in self.items.clone().iter(){
+    pub fn visit(&mut self, visitor: &mut dyn ASTVisitor) {
+        for item in self.items.clone().iter() {
             visitor.visit_item(self, item.id);
         }
     }
 
-    pub fn visualize(&mut self) -> (){
-        let mut printer = ASTPrinter::new();
+    pub fn visualize(&mut self, source_map: &SourceMap) {
+        let mut printer = ASTPrinter::new(source_map);
         self.visit(&mut printer);
         println!("{}", printer.result);
     }
 }
 
+impl Default for Ast {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct Item{
-    pub id: ItemID,
-    pub kind: ItemKind
+pub struct Item {
+    pub kind: ItemKind,
+    pub id: ItemId,
+    pub attributes: Vec<Attribute>,
 }
 
-impl Item{
-    pub fn new(kind: ItemKind, id:ItemID) -> Self{
-        Self(kind, id)
+impl Item {
+    pub fn new(kind: ItemKind, id: ItemId) -> Self {
+        Self {
+            kind,
+            id,
+            attributes: Vec::new(),
+        }
     }
 }
 
+/// `#[name(argument, ...)]` in front of an item, e.g.
+/// `#[allow(unused_variables)]`.
 #[derive(Debug, Clone)]
-pub enum ItemKind{
-    Stmt(StmtID),
+pub struct Attribute {
+    pub hash: Token,
+    pub name: Token,
+    pub arguments: Vec<Token>,
+    pub close_bracket: Token,
+}
+
+impl Attribute {
+    pub fn span(&self) -> TextSpan {
+        self.hash.span.to(self.close_bracket.span)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ItemKind {
+    Stmt(StmtId),
     Function(FuncDeclaration),
+    TypeAlias(TypeAliasDeclaration),
+    Struct(StructDeclaration),
+    Enum(EnumDeclaration),
+    Const(ConstDeclaration),
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeAliasDeclaration {
+    pub type_keyword: Token,
+    pub identifier: Token,
+    pub equals: Token,
+    pub type_name: Token,
+    pub question_mark: Option<Token>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructField {
+    pub identifier: Token,
+    pub type_annotation: StaticTypeAnnotation,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDeclaration {
+    pub struct_keyword: Token,
+    pub identifier: Token,
+    pub open_brace: Token,
+    pub fields: Vec<StructField>,
+    pub close_brace: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDeclaration {
+    pub enum_keyword: Token,
+    pub identifier: Token,
+    pub open_brace: Token,
+    pub variants: Vec<Token>,
+    pub close_brace: Token,
+}
+
+/// A module-level `const NAME: type = expr;`. The initializer is evaluated
+/// during compilation, so it may only use side-effect-free expressions.
+#[derive(Debug, Clone)]
+pub struct ConstDeclaration {
+    pub const_keyword: Token,
+    pub identifier: Token,
+    pub type_annotation: StaticTypeAnnotation,
+    pub equals: Token,
+    pub initializer: ExprId,
+    pub variable_idx: VariableIdx,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub id: StmtId,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, id: StmtId) -> Self {
+        Self { kind, id }
+    }
+
+    pub fn span(&self, ast: &Ast) -> TextSpan {
+        match &self.kind {
+            StmtKind::Expr(expr_id) => ast.query_expr(*expr_id).span(ast),
+            StmtKind::Let(let_stmt) => {
+                let mut span = let_stmt.identifier.span;
+                if let Some(type_annotation) = &let_stmt.type_annotation {
+                    span = span.to(type_annotation.type_name.span);
+                }
+                if let Some(initializer) = let_stmt.initializer {
+                    span = span.to(ast.query_expr(initializer).span(ast));
+                }
+                span
+            }
+            StmtKind::While(while_stmt) => while_stmt.while_keyword.span.to(while_stmt.body.span()),
+            StmtKind::Return(return_stmt) => match return_stmt.return_value {
+                Some(return_value) => return_stmt
+                    .return_keyword
+                    .span
+                    .to(ast.query_expr(return_value).span(ast)),
+                None => return_stmt.return_keyword.span,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub enum StmtKind{
-    Expr(ExprID),
+pub enum StmtKind {
+    Expr(ExprId),
     Let(LetStmt),
     While(WhileStmt),
     Return(ReturnStmt),
 }
 
 #[derive(Debug, Clone)]
-pub struct ReturnStmt{
-    pub return_keyword: Token;
-    pub return_value: Option<ExprID>;
+pub struct LetStmt {
+    pub identifier: Token,
+    pub initializer: Option<ExprId>,
+    pub type_annotation: Option<StaticTypeAnnotation>,
+    pub variable_idx: VariableIdx,
 }
 
-pub struct StaticTypeAnnotation{
-    pub fn new(colon: Token, type_name: Token) -> Self{
-        Self(colon, type_name)  
+#[derive(Debug, Clone)]
+pub struct ReturnStmt {
+    pub return_keyword: Token,
+    pub return_value: Option<ExprId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StaticTypeAnnotation {
+    pub colon: Token,
+    pub type_name: Token,
+    pub question_mark: Option<Token>,
+}
+
+impl StaticTypeAnnotation {
+    pub fn new(colon: Token, type_name: Token, question_mark: Option<Token>) -> Self {
+        Self {
+            colon,
+            type_name,
+            question_mark,
+        }
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.question_mark.is_some()
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct FuncDeclParameter{
+pub struct FuncDeclParameter {
     pub identifier: Token,
     pub type_annotation: StaticTypeAnnotation,
 }
 
+#[derive(Debug, Clone)]
+pub struct FunctionReturnTypeSyntax {
+    pub arrow: Token,
+    pub type_name: Token,
+    pub question_mark: Option<Token>,
+}
 
-pub struct FunctionReturnTypeSyntaxpub {
-    pub fn new(arrow: Token, type_name: Token) -> Self{
-        Self(arrow, type_name)
+impl FunctionReturnTypeSyntax {
+    pub fn new(arrow: Token, type_name: Token, question_mark: Option<Token>) -> Self {
+        Self {
+            arrow,
+            type_name,
+            question_mark,
+        }
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.question_mark.is_some()
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct FuncDeclaration{
+pub struct FuncDeclaration {
     pub func_keyword: Token,
     pub identifier: Token,
     pub parameters: Vec<FuncDeclParameter>,
@@ -267,24 +599,282 @@ pub struct FuncDeclaration{
 }
 
 #[derive(Debug, Clone)]
-pub struct WhileStmt{
+pub struct WhileStmt {
     pub while_keyword: Token,
-    pub condition: ExprID,
+    pub condition: ExprId,
     pub body: Body,
 }
 
-pub struct BlockExpr{
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub open_brace: Token,
+    pub stmts: Vec<StmtId>,
+    pub close_brace: Token,
+}
+
+impl Body {
+    pub fn new(open_brace: Token, stmts: Vec<StmtId>, close_brace: Token) -> Self {
+        Self {
+            open_brace,
+            stmts,
+            close_brace,
+        }
+    }
+
+    pub fn span(&self) -> TextSpan {
+        self.open_brace.span.to(self.close_brace.span)
+    }
+}
+
+impl Deref for Body {
+    type Target = Vec<StmtId>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stmts
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub id: ExprId,
+    pub ty: Type,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, id: ExprId, ty: Type) -> Self {
+        Self { kind, id, ty }
+    }
+
+    pub fn span(&self, ast: &Ast) -> TextSpan {
+        match &self.kind {
+            ExprKind::Number(expr) => expr.token.span,
+            ExprKind::Binary(expr) => ast
+                .query_expr(expr.left)
+                .span(ast)
+                .to(ast.query_expr(expr.right).span(ast)),
+            ExprKind::Unary(expr) => expr
+                .operator
+                .token
+                .span
+                .to(ast.query_expr(expr.operand).span(ast)),
+            ExprKind::Parenthesized(expr) => expr.left_paren.span.to(expr.right_paren.span),
+            ExprKind::Variable(expr) => expr.identifier.span,
+            ExprKind::Assignment(expr) => expr
+                .identifier
+                .span
+                .to(ast.query_expr(expr.expression).span(ast)),
+            ExprKind::Boolean(expr) => expr.token.span,
+            ExprKind::Null(expr) => expr.token.span,
+            ExprKind::String(expr) => expr.token.span,
+            ExprKind::Call(expr) => expr.callee.span.to(expr.right_paren.span),
+            ExprKind::If(expr) => {
+                let last = match &expr.else_branch {
+                    Some(else_branch) => else_branch.body.span(),
+                    None => expr.then_branch.span(),
+                };
+                expr.if_keyword.span.to(last)
+            }
+            ExprKind::Block(expr) => expr.left_brace.span.to(expr.right_brace.span),
+            ExprKind::Error(span) => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Number(NumberExpr),
+    Binary(BinaryExpr),
+    Unary(UnaryExpr),
+    Parenthesized(ParenthesizedExpr),
+    Variable(VarExpr),
+    Assignment(AssignExpr),
+    Boolean(BoolExpr),
+    Null(NullExpr),
+    String(StringExpr),
+    Call(CallExpr),
+    If(IfExpr),
+    Block(BlockExpr),
+    Error(TextSpan),
+}
+
+#[derive(Debug, Clone)]
+pub struct NumberExpr {
+    pub number: i64,
+    pub token: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct BoolExpr {
+    pub value: bool,
+    pub token: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct NullExpr {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone)]
+pub enum StringPart {
+    /// Text with its escape sequences already resolved.
+    Literal(String),
+    /// An expression embedded as `{expr}`.
+    Interpolation(ExprId),
+}
+
+#[derive(Debug, Clone)]
+pub struct StringExpr {
+    pub token: Token,
+    pub parts: Vec<StringPart>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VarExpr {
+    pub identifier: Token,
+    pub variable_idx: VariableIdx,
+}
+
+impl VarExpr {
+    pub fn identifier<'a>(&self, source_map: &'a SourceMap) -> &'a str {
+        self.identifier.span.literal(source_map)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssignExpr {
+    pub identifier: Token,
+    pub equals: Token,
+    pub expression: ExprId,
+    pub variable_idx: VariableIdx,
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpr {
+    pub callee: Token,
+    pub left_paren: Token,
+    pub arguments: Vec<ExprId>,
+    pub right_paren: Token,
+    pub function_idx: FunctionIdx,
+}
+
+impl CallExpr {
+    pub fn function_name<'a>(&self, source_map: &'a SourceMap) -> &'a str {
+        self.callee.span.literal(source_map)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParenthesizedExpr {
+    pub left_paren: Token,
+    pub inner: ExprId,
+    pub right_paren: Token,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnOpKind {
+    Minus,
+    Bitwise,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnOperator {
+    pub kind: UnOpKind,
+    pub token: Token,
+}
+
+impl UnOperator {
+    pub fn new(kind: UnOpKind, token: Token) -> Self {
+        Self { kind, token }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnaryExpr {
+    pub operator: UnOperator,
+    pub operand: ExprId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOpKind {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    Equalsto,
+    NotEqualsto,
+    LessThan,
+    LessThanEqualTo,
+    GreaterThan,
+    GreaterThanEqualTo,
+}
+
+pub enum BinOpAssociativity {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct BinOperator {
+    pub kind: BinOpKind,
+    pub token: Token,
+}
+
+impl BinOperator {
+    pub fn new(kind: BinOpKind, token: Token) -> Self {
+        Self { kind, token }
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self.kind {
+            BinOpKind::Power => 7,
+            BinOpKind::Multiply | BinOpKind::Divide | BinOpKind::Modulo => 6,
+            BinOpKind::Plus | BinOpKind::Minus => 5,
+            BinOpKind::BitwiseAnd => 4,
+            BinOpKind::BitwiseXor => 3,
+            BinOpKind::BitwiseOr => 2,
+            BinOpKind::Equalsto
+            | BinOpKind::NotEqualsto
+            | BinOpKind::LessThan
+            | BinOpKind::LessThanEqualTo
+            | BinOpKind::GreaterThan
+            | BinOpKind::GreaterThanEqualTo => 1,
+        }
+    }
+
+    pub fn associativity(&self) -> BinOpAssociativity {
+        match self.kind {
+            BinOpKind::Power => BinOpAssociativity::Right,
+            _ => BinOpAssociativity::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BinaryExpr {
+    pub left: ExprId,
+    pub operator: BinOperator,
+    pub right: ExprId,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockExpr {
     pub left_brace: Token,
-    pub stmt: Vec<StmtID>,
-    pub right_brace: Token
+    pub stmts: Vec<StmtId>,
+    pub right_brace: Token,
 }
 
-impl BlockExpr{
-    pub fn returning_statements(&self, ast: &Ast) -> Option<ExprID>{
+impl BlockExpr {
+    pub fn returning_statements(&self, ast: &Ast) -> Option<ExprId> {
         if let Some(last_stmt) = self.stmts.last() {
             let stmt = ast.query_stmt(*last_stmt);
-            if let StmtKind::Expr(exprid) = &stmt.kind{
-                return Some(*exprid);
+            if let StmtKind::Expr(expr_id) = &stmt.kind {
+                return Some(*expr_id);
             }
         }
         None
@@ -292,21 +882,21 @@ impl BlockExpr{
 }
 
 #[derive(Debug, Clone)]
-pub struct ElseBranch{
-    pub else_key: Token,
+pub struct ElseBranch {
+    pub else_keyword: Token,
     pub body: Body,
 }
 
-impl ElseBranch{
-    pub fn new(else_keyword: Token, body: Body) -> Self{
-        ElseBranch{else_key, body}
+impl ElseBranch {
+    pub fn new(else_keyword: Token, body: Body) -> Self {
+        ElseBranch { else_keyword, body }
     }
 }
 
-pub fn new(else_keyword: Token, body: Body)
-pub struct IfExpr{
+#[derive(Debug, Clone)]
+pub struct IfExpr {
     pub if_keyword: Token,
-    pub condition: ExprID,
+    pub condition: ExprId,
     pub then_branch: Body,
-    pub else_branch: Option<ElseBranch>
-}
\ No newline at end of file
+    pub else_branch: Option<ElseBranch>,
+}
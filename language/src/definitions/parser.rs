@@ -1,92 +1,698 @@
 use std::cell::Cell;
 
-use crate::{definitions::{lexer::{Token, TokenKind}, Ast, BinOpAssociativity, BinOpKind, BinOperator, Body, ElseBranch, Expr, ExprId,
-        FuncDeclParameter, FunctionReturnTypeSyntax, Item, ItemKind, StaticTypeAnnotation, Stmt,
-        StmtId, UnOpKind, UnOperator,
+use navsharp::Idx;
+
+use crate::{
+    compilation_unit::FunctionIdx,
+    definitions::{
+        lexer::{Lexer, Token, TokenKind},
+        limits::MAX_NESTING_DEPTH,
+        Ast, Attribute, BinOpAssociativity, BinOpKind, BinOperator, Body, ElseBranch, ExprId,
+        FuncDeclParameter, FunctionReturnTypeSyntax, Item, ItemKind, StaticTypeAnnotation, StmtId,
+        StringPart, StructField, UnOpKind, UnOperator,
     },
-    compilation_unit::{resolve_type_from_string, GlobalScope},
     diagnostics::DiagnosticsBagCell,
-    typings::Type,
+    text::{
+        source_map::{FileId, SourceMap},
+        span::TextSpan,
+    },
 };
 
-#[Derive(Debug, Clone)]
-
-pub struct Counter{
+#[derive(Debug, Clone)]
+pub struct Counter {
     value: Cell<usize>,
 }
 
-impl Counter{
-    pub fn new() -> Self{
-        Self{
+impl Counter {
+    pub fn new() -> Self {
+        Self {
             value: Cell::new(0),
         }
     }
 
-    pub fn increment(&self){
+    pub fn increment(&self) {
         let current_value = self.value.get();
         self.value.set(current_value + 1);
     }
 
-    pub fn get_value(&self) -> usize{
+    pub fn get_value(&self) -> usize {
         self.value.get()
     }
 }
 
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Parser<'a> {
     tokens: Vec<Token>,
     current: Counter,
     diagnostics_bag: DiagnosticsBagCell,
     ast: &'a mut Ast,
-    global_scope: &'a mut GlobalScope,
+    source_map: &'a SourceMap,
+    /// How many expressions and statements enclose the current one.
+    depth: usize,
+    /// Whether code nested too deeply was reported, which is only done for
+    /// the first such code.
+    reported_nesting: bool,
 }
 
-impl<'a> Parser<'a>{
-    pub fn new(tokens: Vec<Token>,diagnostics_bag: DiagnosticsBagCell,ast: &'a mut Ast,global_scope: &'a GlobalScope,) -> Self{
-        Self{
-            tokens: tokens.iter().filter(|token| token.kind != TokenKind: Whitespace).map(|token| token.clone()).collect(),
+impl<'a> Parser<'a> {
+    pub fn new(
+        tokens: Vec<Token>,
+        diagnostics_bag: DiagnosticsBagCell,
+        ast: &'a mut Ast,
+        source_map: &'a SourceMap,
+    ) -> Self {
+        Self {
+            tokens: tokens
+                .iter()
+                .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+                .cloned()
+                .collect(),
             current: Counter::new(),
             diagnostics_bag,
             ast,
-            global_scope,
+            source_map,
+            depth: 0,
+            reported_nesting: false,
         }
     }
 
-    pub fn parse(&mut self){
-        while let Some(_) = self.next_item().map(|stmt| stmt.id) {}
+    pub fn parse(&mut self) {
+        while self.next_item().is_some() {}
     }
 
-    fn next_item(&mut self){
-        if self.is_at_end(){
+    fn next_item(&mut self) -> Option<&Item> {
+        if self.is_at_end() {
             return None;
         }
         Some(self.parse_item())
     }
 
-    fn is_at_end(&self) -> bool{
+    fn is_at_end(&self) -> bool {
         self.current().kind == TokenKind::Eof
     }
 
-    fn parse_item(&mut self) -> &Item{
-        return match &self.current().kind{
-            TokenKind::Func => self.parse_func_item(),
+    fn parse_item(&mut self) -> &Item {
+        let attributes = self.parse_attributes();
+        let item_id = match &self.current().kind {
+            TokenKind::Function => self.parse_func_item().id,
+            TokenKind::Type => self.parse_type_alias_item().id,
+            TokenKind::Struct => self.parse_struct_item().id,
+            TokenKind::Enum => self.parse_enum_item().id,
+            TokenKind::Const => self.parse_const_item().id,
             _ => {
                 let id = self.parse_statement();
-                self.ast.item_from_kind(ItemID::Stmt(id))
+                self.ast.item_from_kind(ItemKind::Stmt(id)).id
             }
         };
+        self.ast.set_item_attributes(item_id, attributes);
+        self.ast.query_item(item_id)
+    }
+
+    fn parse_attributes(&mut self) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        while let Some(hash) = self.consume_if(TokenKind::Hash).cloned() {
+            self.consume_and_check(TokenKind::OpenBracket);
+            let name = self.consume_and_check(TokenKind::Identifier).clone();
+            let mut arguments = Vec::new();
+            if self.consume_if(TokenKind::OpenParenthesis).is_some() {
+                while self.current().kind != TokenKind::CloseParenthesis && !self.is_at_end() {
+                    arguments.push(self.consume_and_check(TokenKind::Identifier).clone());
+                    if self.current().kind != TokenKind::CloseParenthesis {
+                        self.consume_and_check(TokenKind::Comma);
+                    }
+                }
+                self.consume_and_check(TokenKind::CloseParenthesis);
+            }
+            let close_bracket = self.consume_and_check(TokenKind::CloseBracket).clone();
+            attributes.push(Attribute {
+                hash,
+                name,
+                arguments,
+                close_bracket,
+            });
+        }
+        attributes
+    }
+
+    fn parse_func_item(&mut self) -> &Item {
+        let func_keyword = self.consume_and_check(TokenKind::Function).clone();
+        let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+        let parameters = self.parse_optional_parameter_list();
+        let return_type = self.parse_optional_return_type();
+        let body = self.parse_body();
+        self.ast.func_item(
+            func_keyword,
+            identifier,
+            parameters,
+            body,
+            return_type,
+            FunctionIdx::unreachable(),
+        )
+    }
+
+    fn parse_type_alias_item(&mut self) -> &Item {
+        let type_keyword = self.consume_and_check(TokenKind::Type).clone();
+        let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+        let equals = self.consume_and_check(TokenKind::Equals).clone();
+        let type_name = self.consume_and_check(TokenKind::Identifier).clone();
+        let question_mark = self.parse_optional_question_mark();
+        self.consume_if(TokenKind::Semicolon);
+        self.ast
+            .type_alias_item(type_keyword, identifier, equals, type_name, question_mark)
+    }
+
+    fn parse_const_item(&mut self) -> &Item {
+        let const_keyword = self.consume_and_check(TokenKind::Const).clone();
+        let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+        let type_annotation = self.parse_type_annotation();
+        let equals = self.consume_and_check(TokenKind::Equals).clone();
+        let initializer = self.parse_expression();
+        self.consume_if(TokenKind::Semicolon);
+        self.ast
+            .const_item(const_keyword, identifier, type_annotation, equals, initializer)
+    }
+
+    fn parse_struct_item(&mut self) -> &Item {
+        let struct_keyword = self.consume_and_check(TokenKind::Struct).clone();
+        let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+        let open_brace = self.consume_and_check(TokenKind::OpenBrace).clone();
+        let mut fields = Vec::new();
+        while self.current().kind != TokenKind::CloseBrace && !self.is_at_end() {
+            let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+            let type_annotation = self.parse_type_annotation();
+            fields.push(StructField {
+                identifier,
+                type_annotation,
+            });
+            if self.current().kind != TokenKind::CloseBrace {
+                self.consume_and_check(TokenKind::Comma);
+            }
+        }
+        let close_brace = self.consume_and_check(TokenKind::CloseBrace).clone();
+        self.ast
+            .struct_item(struct_keyword, identifier, open_brace, fields, close_brace)
+    }
+
+    fn parse_enum_item(&mut self) -> &Item {
+        let enum_keyword = self.consume_and_check(TokenKind::Enum).clone();
+        let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+        let open_brace = self.consume_and_check(TokenKind::OpenBrace).clone();
+        let mut variants = Vec::new();
+        while self.current().kind != TokenKind::CloseBrace && !self.is_at_end() {
+            variants.push(self.consume_and_check(TokenKind::Identifier).clone());
+            if self.current().kind != TokenKind::CloseBrace {
+                self.consume_and_check(TokenKind::Comma);
+            }
+        }
+        let close_brace = self.consume_and_check(TokenKind::CloseBrace).clone();
+        self.ast
+            .enum_item(enum_keyword, identifier, open_brace, variants, close_brace)
+    }
+
+    fn parse_optional_parameter_list(&mut self) -> Vec<FuncDeclParameter> {
+        if self.current().kind != TokenKind::OpenParenthesis {
+            return Vec::new();
+        }
+        self.consume_and_check(TokenKind::OpenParenthesis);
+        let mut parameters = Vec::new();
+        while self.current().kind != TokenKind::CloseParenthesis && !self.is_at_end() {
+            let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+            let type_annotation = self.parse_type_annotation();
+            parameters.push(FuncDeclParameter {
+                identifier,
+                type_annotation,
+            });
+            if self.current().kind == TokenKind::Comma {
+                self.consume_and_check(TokenKind::Comma);
+            }
+        }
+        self.consume_and_check(TokenKind::CloseParenthesis);
+        parameters
+    }
+
+    fn parse_optional_return_type(&mut self) -> Option<FunctionReturnTypeSyntax> {
+        if self.current().kind != TokenKind::Arrow {
+            return None;
+        }
+        let arrow = self.consume_and_check(TokenKind::Arrow).clone();
+        let type_name = self.consume_and_check(TokenKind::Identifier).clone();
+        let question_mark = self.parse_optional_question_mark();
+        Some(FunctionReturnTypeSyntax::new(arrow, type_name, question_mark))
+    }
+
+    fn parse_optional_type_annotation(&mut self) -> Option<StaticTypeAnnotation> {
+        if self.current().kind == TokenKind::Colon {
+            return Some(self.parse_type_annotation());
+        }
+        None
+    }
 
+    fn parse_type_annotation(&mut self) -> StaticTypeAnnotation {
+        let colon = self.consume_and_check(TokenKind::Colon).clone();
+        let type_name = self.consume_and_check(TokenKind::Identifier).clone();
+        let question_mark = self.parse_optional_question_mark();
+        StaticTypeAnnotation::new(colon, type_name, question_mark)
     }
 
-    fn parse_func_item(&mut self) -> &Item{
-        let func_keyword = self.consume_and_check(TokenKind::Func).clone();
+    fn parse_optional_question_mark(&mut self) -> Option<Token> {
+        if self.current().kind == TokenKind::QuestionMark {
+            return Some(self.consume().clone());
+        }
+        None
+    }
+
+    fn parse_body(&mut self) -> Body {
+        let open_brace = self.consume_and_check(TokenKind::OpenBrace).clone();
+        let mut stmts = Vec::new();
+        while self.current().kind != TokenKind::CloseBrace && !self.is_at_end() {
+            stmts.push(self.parse_statement());
+        }
+        let close_brace = self.consume_and_check(TokenKind::CloseBrace).clone();
+        Body::new(open_brace, stmts, close_brace)
+    }
+
+    fn parse_statement(&mut self) -> StmtId {
+        let id = match self.nested(|parser| match parser.current().kind {
+            TokenKind::Let => parser.parse_let_statement(),
+            TokenKind::While => parser.parse_while_statement(),
+            TokenKind::Return => parser.parse_return_statement(),
+            _ => parser.parse_expression_statement(),
+        }) {
+            Ok(id) => id,
+            Err(error) => self.ast.expression_statement(error).id,
+        };
+        self.consume_if(TokenKind::Semicolon);
+        id
+    }
+
+    fn parse_let_statement(&mut self) -> StmtId {
+        self.consume_and_check(TokenKind::Let);
         let identifier = self.consume_and_check(TokenKind::Identifier).clone();
-        let parameters = self.consume_and_check(TokenKind::Parameters).clone();
-        let return_type = self.consume_and_check(TokenKind::Return).clone();
-        let opening_brace = self.consume_and_check(TokenKind::OpenBrace).clone();
-        let mut body = Vec::new();
-        while  self.current().kind != TokenKind::CloseBrace && !self.is_at_end(){
-            body.push(self.parse_statement());
-        }
-        let closing_brace = self.consume_and_check(TokenKind::CloseBrace).clone();
+        let type_annotation = self.parse_optional_type_annotation();
+        let initializer = self
+            .consume_if(TokenKind::Equals)
+            .is_some()
+            .then(|| self.parse_expression());
+        self.ast
+            .let_statement(identifier, initializer, type_annotation)
+            .id
+    }
+
+    fn parse_while_statement(&mut self) -> StmtId {
+        let while_keyword = self.consume_and_check(TokenKind::While).clone();
+        let condition = self.parse_expression();
+        let body = self.parse_body();
+        self.ast.while_statement(while_keyword, condition, body).id
+    }
+
+    fn parse_return_statement(&mut self) -> StmtId {
+        let return_keyword = self.consume_and_check(TokenKind::Return).clone();
+        let return_value = match self.current().kind {
+            TokenKind::Semicolon | TokenKind::CloseBrace | TokenKind::Eof => None,
+            _ => Some(self.parse_expression()),
+        };
+        self.ast.return_statement(return_keyword, return_value).id
+    }
+
+    fn parse_expression_statement(&mut self) -> StmtId {
+        let expr = self.parse_expression();
+        self.ast.expression_statement(expr).id
+    }
+
+    fn parse_expression(&mut self) -> ExprId {
+        self.nested(Self::parse_assignment_expression)
+            .unwrap_or_else(|error| error)
+    }
+
+    /// Parses code nested one level deeper than the current one. Code nested
+    /// deeper than `MAX_NESTING_DEPTH` is reported and skipped up to the end
+    /// of the enclosing parentheses, braces or statement, returning an error
+    /// expression in its place.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> T) -> Result<T, ExprId> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(self.skip_nested_code());
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        Ok(result)
+    }
+
+    fn skip_nested_code(&mut self) -> ExprId {
+        let first = self.current().clone();
+        if !self.reported_nesting {
+            self.diagnostics_bag
+                .borrow_mut()
+                .report_nesting_too_deep(&first, MAX_NESTING_DEPTH);
+            self.reported_nesting = true;
+        }
+        let mut span = TextSpan::new(first.span.file_id, first.span.start, first.span.start);
+        let mut depth = 0usize;
+        loop {
+            match self.current().kind {
+                TokenKind::Eof => break,
+                TokenKind::Semicolon if depth == 0 => break,
+                // The body of an `if` or `while` whose condition is skipped.
+                TokenKind::OpenBrace if depth == 0 && span.length() > 0 => break,
+                TokenKind::OpenParenthesis | TokenKind::OpenBrace | TokenKind::OpenBracket => {
+                    depth += 1
+                }
+                TokenKind::CloseParenthesis | TokenKind::CloseBrace | TokenKind::CloseBracket => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+            span = span.to(self.consume().span);
+        }
+        self.ast.error_expression(span).id
+    }
+
+    fn parse_assignment_expression(&mut self) -> ExprId {
+        if self.current().kind == TokenKind::Identifier
+            && self.peek(1).kind == TokenKind::Equals
+        {
+            let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+            let equals = self.consume_and_check(TokenKind::Equals).clone();
+            let expression = self.parse_expression();
+            return self
+                .ast
+                .assignment_expression(identifier, equals, expression)
+                .id;
+        }
+        self.parse_binary_expression(0)
+    }
+
+    fn parse_binary_expression(&mut self, precedence: u8) -> ExprId {
+        let mut left = self.parse_unary_expression();
+
+        while let Some(operator) = self.parse_binary_operator() {
+            let operator_precedence = operator.precedence();
+            if operator_precedence < precedence {
+                break;
+            }
+            self.consume();
+            let next_precedence = match operator.associativity() {
+                BinOpAssociativity::Left => operator_precedence + 1,
+                BinOpAssociativity::Right => operator_precedence,
+            };
+            let right = self
+                .nested(|parser| parser.parse_binary_expression(next_precedence))
+                .unwrap_or_else(|error| error);
+            left = self.ast.binary_expression(operator, left, right).id;
+        }
+        left
+    }
+
+    fn parse_unary_expression(&mut self) -> ExprId {
+        if let Some(operator) = self.parse_unary_operator() {
+            self.consume();
+            let operand = self
+                .nested(Self::parse_unary_expression)
+                .unwrap_or_else(|error| error);
+            return self.ast.unary_expression(operator, operand).id;
+        }
+        self.parse_primary_expression()
+    }
+
+    fn parse_unary_operator(&mut self) -> Option<UnOperator> {
+        let token = self.current();
+        let kind = match token.kind {
+            TokenKind::Minus => Some(UnOpKind::Minus),
+            TokenKind::Tilde => Some(UnOpKind::Bitwise),
+            _ => None,
+        };
+        kind.map(|kind| UnOperator::new(kind, token.clone()))
+    }
+
+    fn parse_binary_operator(&mut self) -> Option<BinOperator> {
+        let token = self.current();
+        let kind = match token.kind {
+            TokenKind::Plus => Some(BinOpKind::Plus),
+            TokenKind::Minus => Some(BinOpKind::Minus),
+            TokenKind::Asterisk => Some(BinOpKind::Multiply),
+            TokenKind::Slash => Some(BinOpKind::Divide),
+            TokenKind::Percent => Some(BinOpKind::Modulo),
+            TokenKind::DoubleAsterisk => Some(BinOpKind::Power),
+            TokenKind::Ampersand => Some(BinOpKind::BitwiseAnd),
+            TokenKind::Pipe => Some(BinOpKind::BitwiseOr),
+            TokenKind::Caret => Some(BinOpKind::BitwiseXor),
+            TokenKind::DoubleEquals => Some(BinOpKind::Equalsto),
+            TokenKind::BangEquals => Some(BinOpKind::NotEqualsto),
+            TokenKind::LessThan => Some(BinOpKind::LessThan),
+            TokenKind::LessThanEquals => Some(BinOpKind::LessThanEqualTo),
+            TokenKind::GreaterThan => Some(BinOpKind::GreaterThan),
+            TokenKind::GreaterThanEquals => Some(BinOpKind::GreaterThanEqualTo),
+            _ => None,
+        };
+        kind.map(|kind| BinOperator::new(kind, token.clone()))
+    }
+
+    fn parse_primary_expression(&mut self) -> ExprId {
+        let token = self.consume().clone();
+        match token.kind {
+            TokenKind::Number(number) => self.ast.number_expression(token, number).id,
+            TokenKind::True => self.ast.boolean_expression(token, true).id,
+            TokenKind::False => self.ast.boolean_expression(token, false).id,
+            TokenKind::Null => self.ast.null_expression(token).id,
+            TokenKind::String => self.parse_string_expression(token),
+            TokenKind::OpenParenthesis => {
+                let expr = self.parse_expression();
+                let right_paren = self
+                    .consume_and_check(TokenKind::CloseParenthesis)
+                    .clone();
+                self.ast
+                    .parenthesized_expression(token, expr, right_paren)
+                    .id
+            }
+            TokenKind::OpenBrace => self.parse_block_expression(token),
+            TokenKind::If => self.parse_if_expression(token),
+            TokenKind::Identifier => {
+                if self.current().kind == TokenKind::OpenParenthesis {
+                    self.parse_call_expression(token)
+                } else {
+                    self.ast.variable_expression(token).id
+                }
+            }
+            _ => {
+                self.diagnostics_bag
+                    .borrow_mut()
+                    .report_expected_expression(&token);
+                self.ast.error_expression(token.span).id
+            }
+        }
+    }
+
+    fn parse_string_expression(&mut self, token: Token) -> ExprId {
+        let literal = token.span.literal(self.source_map);
+        let contents = if literal.len() >= 2 && literal.ends_with('"') {
+            &literal[1..literal.len() - 1]
+        } else {
+            self.diagnostics_bag
+                .borrow_mut()
+                .report_unterminated_string(&token);
+            &literal[1..]
+        };
+        let contents_start = token.span.start + 1;
+
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut position = 0;
+        while let Some(c) = contents[position..].chars().next() {
+            match c {
+                '\\' => {
+                    let Some(escaped) = contents[position + 1..].chars().next() else {
+                        break;
+                    };
+                    let end = position + 1 + escaped.len_utf8();
+                    text.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        '\\' | '"' | '{' | '}' => escaped,
+                        _ => {
+                            let span = TextSpan::new(
+                                token.span.file_id,
+                                contents_start + position,
+                                contents_start + end,
+                            );
+                            self.diagnostics_bag
+                                .borrow_mut()
+                                .report_invalid_escape_sequence(&contents[position..end], &span);
+                            escaped
+                        }
+                    });
+                    position = end;
+                }
+                '{' => {
+                    if !text.is_empty() {
+                        parts.push(StringPart::Literal(std::mem::take(&mut text)));
+                    }
+                    let (expr, end) = self.parse_interpolation(
+                        token.span.file_id,
+                        contents,
+                        position + 1,
+                        contents_start,
+                    );
+                    parts.push(StringPart::Interpolation(expr));
+                    position = end;
+                }
+                c => {
+                    text.push(c);
+                    position += c.len_utf8();
+                }
+            }
+        }
+        if !text.is_empty() {
+            parts.push(StringPart::Literal(text));
+        }
+        self.ast.string_expression(token, parts).id
+    }
+
+    /// Parses the expression of an interpolation starting at `start` in the
+    /// string's contents, up to the matching `}`. Returns the expression and
+    /// the position right after the brace.
+    fn parse_interpolation(
+        &mut self,
+        file_id: FileId,
+        contents: &str,
+        start: usize,
+        contents_start: usize,
+    ) -> (ExprId, usize) {
+        let offset = contents_start + start;
+        let mut lexer = Lexer::new(&contents[start..], file_id);
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        let end = loop {
+            let Some(mut token) = lexer.next_token() else {
+                break contents.len();
+            };
+            token.span.start += offset;
+            token.span.end += offset;
+            match token.kind {
+                TokenKind::OpenBrace => depth += 1,
+                TokenKind::CloseBrace if depth == 0 => {
+                    tokens.push(Token::new(
+                        TokenKind::Eof,
+                        TextSpan::new(file_id, token.span.start, token.span.start),
+                    ));
+                    break token.span.end - contents_start;
+                }
+                TokenKind::CloseBrace => depth -= 1,
+                TokenKind::Eof => {
+                    self.diagnostics_bag
+                        .borrow_mut()
+                        .report_unexpected_token(&TokenKind::CloseBrace, &token);
+                    tokens.push(token);
+                    break contents.len();
+                }
+                _ => {}
+            }
+            tokens.push(token);
+        };
+
+        let mut parser = Parser::new(
+            tokens,
+            self.diagnostics_bag.clone(),
+            &mut *self.ast,
+            self.source_map,
+        );
+        parser.depth = self.depth;
+        parser.reported_nesting = self.reported_nesting;
+        let expr = parser.parse_expression();
+        if !parser.is_at_end() {
+            parser
+                .diagnostics_bag
+                .borrow_mut()
+                .report_unexpected_token(&TokenKind::CloseBrace, parser.current());
+        }
+        self.reported_nesting = parser.reported_nesting;
+        (expr, end)
+    }
+
+    fn parse_call_expression(&mut self, callee: Token) -> ExprId {
+        let left_paren = self.consume_and_check(TokenKind::OpenParenthesis).clone();
+        let mut arguments = Vec::new();
+        while self.current().kind != TokenKind::CloseParenthesis && !self.is_at_end() {
+            arguments.push(self.parse_expression());
+            if self.current().kind != TokenKind::CloseParenthesis {
+                self.consume_and_check(TokenKind::Comma);
+            }
+        }
+        let right_paren = self.consume_and_check(TokenKind::CloseParenthesis).clone();
+        self.ast
+            .call_expression(callee, left_paren, arguments, right_paren)
+            .id
+    }
+
+    fn parse_block_expression(&mut self, left_brace: Token) -> ExprId {
+        let mut statements = Vec::new();
+        while self.current().kind != TokenKind::CloseBrace && !self.is_at_end() {
+            statements.push(self.parse_statement());
+        }
+        let right_brace = self.consume_and_check(TokenKind::CloseBrace).clone();
+        self.ast
+            .block_statement(left_brace, statements, right_brace)
+            .id
+    }
+
+    fn parse_if_expression(&mut self, if_keyword: Token) -> ExprId {
+        let condition = self.parse_expression();
+        let then_branch = self.parse_body();
+        let else_branch = self.parse_optional_else_branch();
+        self.ast
+            .if_expression(if_keyword, condition, then_branch, else_branch)
+            .id
+    }
+
+    fn parse_optional_else_branch(&mut self) -> Option<ElseBranch> {
+        if self.current().kind != TokenKind::Else {
+            return None;
+        }
+        let else_keyword = self.consume_and_check(TokenKind::Else).clone();
+        let body = self.parse_body();
+        Some(ElseBranch::new(else_keyword, body))
+    }
+
+    fn peek(&self, offset: isize) -> &Token {
+        let mut index = (self.current.get_value() as isize + offset) as usize;
+        if index >= self.tokens.len() {
+            index = self.tokens.len() - 1;
+        }
+        self.tokens.get(index).unwrap()
+    }
+
+    fn current(&self) -> &Token {
+        self.peek(0)
+    }
+
+    fn consume(&self) -> &Token {
+        self.current.increment();
+        self.peek(-1)
+    }
+
+    fn consume_if(&self, kind: TokenKind) -> Option<&Token> {
+        if self.current().kind == kind {
+            return Some(self.consume());
+        }
+        None
+    }
+
+    fn consume_and_check(&self, kind: TokenKind) -> &Token {
+        let token = self.consume();
+        if token.kind != kind {
+            self.diagnostics_bag
+                .borrow_mut()
+                .report_unexpected_token(&kind, token);
+        }
+        token
     }
 }
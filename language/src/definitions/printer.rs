@@ -1,70 +1,100 @@
-use termion::color;
+use std::io;
 
-use crate{ast::*, text::span::TextSpan};
+use termion::color::{self, Fg, Reset};
 
-pub struct ASTPrinter{
-    ident: usize,
+use crate::{
+    definitions::{visitor::ASTVisitor, *},
+    text::{source_map::SourceMap, span::TextSpan},
+};
+
+pub struct ASTPrinter<'a>{
+    indent: usize,
     pub result: String,
+    source_map: &'a SourceMap,
+    use_colors: bool,
 }
 
-impl ASTPrinter{
+impl<'a> ASTPrinter<'a>{
     const NUMBER_COLOR: color::Cyan = color::Cyan;
     const TEXT_COLOR: color::LightWhite = color::LightWhite;
     const KEYWORD_COLOR: color::Magenta = color::Magenta;
     const VARIABLE_COLOR: color::Green = color::Green;
     const BOOLEAN_COLOR: color::Yellow = color::Yellow;
     const TYPE_COLOR: color::LightBlue = color::LightBlue; 
+    const STRING_COLOR: color::LightYellow = color::LightYellow;
 
     fn add_whitespace(&mut self){
-        self.result.push_str(" ")
+        self.result.push(' ')
     }
 
     fn add_newline(&mut self){
-        self.result.push_str("
-        ");
+        self.result.push('\n');
     }
 
     fn add_keyword(&mut self, keyword: &str){
-        self.result.push_str(&format!("{}{}", Self::KEYWORD_COLOR.fg_str(),keyword,));
+        self.result.push_str(&format!("{}{}", self.color(Self::KEYWORD_COLOR.fg_str()),keyword,));
     }
 
     fn add_text(&mut self, text: &str){
-        self.result.push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(),text,));
+        self.result.push_str(&format!("{}{}", self.color(Self::TEXT_COLOR.fg_str()),text,));
     }
 
     fn add_variable(&mut self, variable: &str){
-        self.result.push_str(&format!("{}{}", Self::VARIABLE_COLOR.fg_str(),variable));
+        self.result.push_str(&format!("{}{}", self.color(Self::VARIABLE_COLOR.fg_str()),variable));
     }
 
-    fn add_padding(&mut self)(
-        for _ in 0..self.ident{
-            self.result.push_str(" ")
+    fn add_padding(&mut self){
+        for _ in 0..self.indent{
+            self.result.push(' ')
         }
-    )
+    }
 
-    fm add_boolean(&mut self, boolean: bool){
-        self.result.push_str(&format!("{}{}", Self::BOOLEAN_COLOR.fg_str(), boolean,))
+    fn add_boolean(&mut self, boolean: bool){
+        self.result.push_str(&format!("{}{}", self.color(Self::BOOLEAN_COLOR.fg_str()), boolean,))
     }
 
     fn add_type(&mut self, type_: &str){
-        self.result.push_str(&format!("{}{}", Self::TYPE_COLOR.fg_str(), type_,))
+        self.result.push_str(&format!("{}{}", self.color(Self::TYPE_COLOR.fg_str()), type_,))
     }
 
-    fn add_type_annotations(&mut self, type_annotation = &StaticTypeAnnotation){
+    fn add_type_annotation(&mut self, type_annotation: &StaticTypeAnnotation){
         self.add_text(":");
         self.add_whitespace();
-        self.add_type(&type_annotation.type_name.span.literal);
+        self.add_type(type_annotation.type_name.span.literal(self.source_map));
+        if type_annotation.is_nullable() {
+            self.add_type("?");
+        }
+    }
+
+    fn add_null(&mut self){
+        self.result.push_str(&format!("{}{}", self.color(Self::KEYWORD_COLOR.fg_str()), "null",));
+    }
+
+    fn color(&self, color: &'static str) -> &'static str {
+        if self.use_colors { color } else { "" }
+    }
+
+    fn reset_color(&self) -> String {
+        if self.use_colors { Fg(Reset).to_string() } else { String::new() }
     }
 
-    pub fn new() ->Self {
+    /// Colors are on when stdout is a terminal.
+    pub fn new(source_map: &'a SourceMap) ->Self {
         Self{
-            ident: 0,
+            indent: 0,
             result: String::new(),
+            source_map,
+            use_colors: termion::is_tty(&io::stdout()),
         }
     }
+
+    pub fn with_colors(mut self, use_colors: bool) -> Self {
+        self.use_colors = use_colors;
+        self
+    }
 }
 
-impl ASTVisitor for ASTPrinter{
+impl ASTVisitor for ASTPrinter<'_>{
     fn visit_func_decl(
         &mut self,
         ast: &mut Ast,
@@ -74,7 +104,7 @@ impl ASTVisitor for ASTPrinter{
  {
         self.add_keyword("func");
         self.add_whitespace();
-        self.add_text(&func_decl.identifier.span.literal);
+        self.add_text(func_decl.identifier.span.literal(self.source_map));
         self.add_whitespace();
         let are_parameters_empty = func_decl.parameters.is_empty();
         if !are_parameters_empty {
@@ -87,17 +117,94 @@ impl ASTVisitor for ASTPrinter{
                 self.add_text(",");
                 self.add_whitespace();
             }
-            self.add_text(&parameter.identifier.span.literal);
+            self.add_text(parameter.identifier.span.literal(self.source_map));
             self.add_type_annotation(&parameter.type_annotation);
         }
         if !are_parameters_empty {
             self.add_text(")");
             self.add_whitespace();
         }
+        if let Some(return_type) = &func_decl.return_type {
+            self.add_text("->");
+            self.add_whitespace();
+            self.add_type(return_type.type_name.span.literal(self.source_map));
+            if return_type.is_nullable() {
+                self.add_type("?");
+            }
+            self.add_whitespace();
+        }
         for stmt in func_decl.body.iter() {
             self.visit_statement(ast, *stmt);
         }
     }
+    fn visit_type_alias_decl(
+        &mut self,
+        _ast: &mut Ast,
+        type_alias: &TypeAliasDeclaration,
+        _item_id: ItemId,
+    ) {
+        self.add_keyword("type");
+        self.add_whitespace();
+        self.add_type(type_alias.identifier.span.literal(self.source_map));
+        self.add_whitespace();
+        self.add_text("=");
+        self.add_whitespace();
+        self.add_type(type_alias.type_name.span.literal(self.source_map));
+        if type_alias.question_mark.is_some() {
+            self.add_type("?");
+        }
+        self.result.push_str(&format!("{}\n", self.reset_color(),));
+    }
+
+    fn visit_struct_decl(&mut self, _ast: &mut Ast, struct_decl: &StructDeclaration, _item_id: ItemId) {
+        self.add_keyword("struct");
+        self.add_whitespace();
+        self.add_type(struct_decl.identifier.span.literal(self.source_map));
+        self.add_whitespace();
+        self.add_text("{");
+        self.add_newline();
+        self.indent += 1;
+        for field in &struct_decl.fields {
+            self.add_padding();
+            self.add_variable(field.identifier.span.literal(self.source_map));
+            self.add_type_annotation(&field.type_annotation);
+            self.add_newline();
+        }
+        self.indent -= 1;
+        self.add_text("}");
+        self.result.push_str(&format!("{}\n", self.reset_color(),));
+    }
+
+    fn visit_enum_decl(&mut self, _ast: &mut Ast, enum_decl: &EnumDeclaration, _item_id: ItemId) {
+        self.add_keyword("enum");
+        self.add_whitespace();
+        self.add_type(enum_decl.identifier.span.literal(self.source_map));
+        self.add_whitespace();
+        self.add_text("{");
+        self.add_newline();
+        self.indent += 1;
+        for variant in &enum_decl.variants {
+            self.add_padding();
+            self.add_text(variant.span.literal(self.source_map));
+            self.add_newline();
+        }
+        self.indent -= 1;
+        self.add_text("}");
+        self.result.push_str(&format!("{}\n", self.reset_color(),));
+    }
+
+    fn visit_const_decl(&mut self, ast: &mut Ast, const_decl: &ConstDeclaration, _item_id: ItemId) {
+        self.add_keyword("const");
+        self.add_whitespace();
+        self.add_variable(const_decl.identifier.span.literal(self.source_map));
+        self.add_type_annotation(&const_decl.type_annotation);
+        self.add_whitespace();
+        self.add_text("=");
+        self.add_whitespace();
+        self.visit_expression(ast, const_decl.initializer);
+        self.result.push_str(&format!("{}\n", self.reset_color(),));
+    }
+
     fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
         self.add_keyword("return");
         if let Some(expression) = &return_statement.return_value {
@@ -129,7 +236,7 @@ impl ASTVisitor for ASTPrinter{
         self.add_whitespace();
         self.visit_expression(ast, if_statement.condition);
         self.add_whitespace();
-        for statement in &if_statement.then_branch.stmts {
+        for statement in if_statement.then_branch.iter() {
             self.visit_statement(ast, *statement);
         }
 
@@ -146,24 +253,26 @@ impl ASTVisitor for ASTPrinter{
     fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, _stmt: &Stmt) {
         self.add_keyword("let");
         self.add_whitespace();
-        self.add_text(let_statement.identifier.span.literal.as_str());
+        self.add_text(let_statement.identifier.span.literal(self.source_map));
         if let Some(type_annotation) = &let_statement.type_annotation {
             self.add_type_annotation(type_annotation);
         }
-        self.add_whitespace();
-        self.add_text("=");
-        self.add_whitespace();
-        self.visit_expression(ast, let_statement.initializer);
+        if let Some(initializer) = let_statement.initializer {
+            self.add_whitespace();
+            self.add_text("=");
+            self.add_whitespace();
+            self.visit_expression(ast, initializer);
+        }
     }
 
     fn visit_statement(&mut self, ast: &mut Ast, statement: StmtId) {
         self.add_padding();
         self.do_visit_statement(ast, statement);
-        self.result.push_str(&format!("{}\n", Fg(Reset),));
+        self.result.push_str(&format!("{}\n", self.reset_color(),));
     }
 
     fn visit_call_expression(&mut self, ast: &mut Ast, call_expression: &CallExpr, _expr: &Expr) {
-        self.add_text(&call_expression.callee.span.literal);
+        self.add_text(call_expression.callee.span.literal(self.source_map));
         self.add_text("(");
         for (i, argument) in call_expression.arguments.iter().enumerate() {
             if i != 0 {
@@ -181,7 +290,7 @@ impl ASTVisitor for ASTPrinter{
         assignment_expression: &AssignExpr,
         _expr: &Expr,
     ) {
-        self.add_variable(assignment_expression.identifier.span.literal.as_str());
+        self.add_variable(assignment_expression.identifier.span.literal(self.source_map));
         self.add_whitespace();
         self.add_text("=");
         self.add_whitespace();
@@ -196,23 +305,38 @@ impl ASTVisitor for ASTPrinter{
     ) {
         self.result.push_str(&format!(
             "{}{}",
-            Self::VARIABLE_COLOR.fg_str(),
-            variable_expression.identifier.span.literal,
+            self.color(Self::VARIABLE_COLOR.fg_str()),
+            variable_expression.identifier.span.literal(self.source_map),
         ));
     }
 
     fn visit_number_expression(&mut self, _ast: &mut Ast, number: &NumberExpr, _expr: &Expr) {
         self.result
-            .push_str(&format!("{}{}", Self::NUMBER_COLOR.fg_str(), number.number,));
+            .push_str(&format!("{}{}", self.color(Self::NUMBER_COLOR.fg_str()), number.number,));
     }
 
     fn visit_boolean_expression(&mut self, _ast: &mut Ast, boolean: &BoolExpr, _expr: &Expr) {
         self.add_boolean(boolean.value);
     }
 
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null: &NullExpr, _expr: &Expr) {
+        self.add_null();
+    }
+
+    fn visit_string_expression(&mut self, _ast: &mut Ast, string: &StringExpr, _expr: &Expr) {
+        self.result.push_str(&format!(
+            "{}{}",
+            self.color(Self::STRING_COLOR.fg_str()),
+            string.token.span.literal(self.source_map),
+        ));
+    }
+
     fn visit_error(&mut self, _ast: &mut Ast, span: &TextSpan) {
-        self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), span.literal,));
+        self.result.push_str(&format!(
+            "{}{}",
+            self.color(Self::TEXT_COLOR.fg_str()),
+            span.literal(self.source_map),
+        ));
     }
 
     fn visit_unary_expression(
@@ -223,8 +347,8 @@ impl ASTVisitor for ASTPrinter{
     ) {
         self.result.push_str(&format!(
             "{}{}",
-            Self::TEXT_COLOR.fg_str(),
-            unary_expression.operator.token.span.literal,
+            self.color(Self::TEXT_COLOR.fg_str()),
+            unary_expression.operator.token.span.literal(self.source_map),
         ));
         self.visit_expression(ast, unary_expression.operand);
     }
@@ -239,8 +363,8 @@ impl ASTVisitor for ASTPrinter{
         self.add_whitespace();
         self.result.push_str(&format!(
             "{}{}",
-            Self::TEXT_COLOR.fg_str(),
-            binary_expression.operator.token.span.literal,
+            self.color(Self::TEXT_COLOR.fg_str()),
+            binary_expression.operator.token.span.literal(self.source_map),
         ));
         self.add_whitespace();
         self.visit_expression(ast, binary_expression.right);
@@ -253,9 +377,9 @@ impl ASTVisitor for ASTPrinter{
         _expr: &Expr,
     ) {
         self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), "(",));
+            .push_str(&format!("{}{}", self.color(Self::TEXT_COLOR.fg_str()), "(",));
         self.visit_expression(ast, parenthesized_expression.inner);
         self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), ")",));
+            .push_str(&format!("{}{}", self.color(Self::TEXT_COLOR.fg_str()), ")",));
     }
 }
@@ -0,0 +1,274 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    compilation_unit::{FunctionIdx, GlobalScope},
+    definitions::{Ast, StmtId},
+    text::source_map::SourceMap,
+};
+
+/// The name the top level of a script is reported under.
+const TOP_LEVEL: &str = "<top level>";
+
+/// The time spent in one function, summed over its calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    /// The time between entering and leaving the function, including its
+    /// callees. Time spent in recursive calls is only counted once.
+    pub inclusive: Duration,
+    /// The time spent in the function's own statements.
+    pub exclusive: Duration,
+}
+
+/// How many statements starting on a line were executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineProfile {
+    pub file: String,
+    /// Counted from 1.
+    pub line: usize,
+    pub hits: u64,
+    /// The text of the line, without surrounding whitespace.
+    pub source: String,
+}
+
+/// What a [`Profiler`] measured over a run.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Slowest first by exclusive time.
+    pub functions: Vec<FunctionProfile>,
+    /// Most executed first.
+    pub lines: Vec<LineProfile>,
+    /// Every distinct stack of calls, outermost first, with the exclusive
+    /// time of its innermost function.
+    pub stacks: Vec<(Vec<String>, Duration)>,
+}
+
+impl Profile {
+    /// A human-readable table of the functions, then of the lines.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        writeln!(
+            report,
+            "{:>10} {:>14} {:>14}  function",
+            "calls", "inclusive", "exclusive"
+        )
+        .unwrap();
+        for function in &self.functions {
+            writeln!(
+                report,
+                "{:>10} {:>14} {:>14}  {}",
+                function.calls,
+                format_duration(function.inclusive),
+                format_duration(function.exclusive),
+                function.name
+            )
+            .unwrap();
+        }
+        writeln!(report).unwrap();
+        writeln!(report, "{:>10}  line", "hits").unwrap();
+        for line in &self.lines {
+            writeln!(
+                report,
+                "{:>10}  {}:{}  {}",
+                line.hits, line.file, line.line, line.source
+            )
+            .unwrap();
+        }
+        report
+    }
+
+    /// The stacks in the folded format flame graph tools read: one stack
+    /// per line, its functions separated by `;` and followed by its
+    /// exclusive time in nanoseconds.
+    pub fn folded_stacks(&self) -> String {
+        let mut folded = String::new();
+        for (stack, time) in &self.stacks {
+            writeln!(folded, "{} {}", stack.join(";"), time.as_nanos()).unwrap();
+        }
+        folded
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[derive(Debug, Default)]
+struct FunctionTimes {
+    calls: u64,
+    inclusive: Duration,
+    exclusive: Duration,
+    /// How many calls to the function are running, above one when it
+    /// recurses.
+    active: usize,
+}
+
+/// A distinct stack of calls, identified by its innermost function and
+/// the node of the stack calling it.
+#[derive(Debug)]
+struct StackNode {
+    function: Option<FunctionIdx>,
+    parent: usize,
+    children: HashMap<Option<FunctionIdx>, usize>,
+    exclusive: Duration,
+}
+
+#[derive(Debug)]
+struct ActiveCall {
+    node: usize,
+    start: Instant,
+    /// The time spent in the calls this call made.
+    callees: Duration,
+}
+
+/// The node every stack grows from, which stands for no function.
+const ROOT: usize = 0;
+
+/// Records how often functions and lines are run and how long functions
+/// take. `None` stands for the top level of the script.
+pub struct Profiler<'a> {
+    source_map: &'a SourceMap,
+    functions: HashMap<Option<FunctionIdx>, FunctionTimes>,
+    statement_hits: HashMap<StmtId, u64>,
+    nodes: Vec<StackNode>,
+    active: Vec<ActiveCall>,
+}
+
+impl<'a> Profiler<'a> {
+    pub fn new(source_map: &'a SourceMap) -> Self {
+        Self {
+            source_map,
+            functions: HashMap::new(),
+            statement_hits: HashMap::new(),
+            nodes: vec![StackNode {
+                function: None,
+                parent: ROOT,
+                children: HashMap::new(),
+                exclusive: Duration::ZERO,
+            }],
+            active: Vec::new(),
+        }
+    }
+
+    pub(crate) fn hit(&mut self, stmt_id: StmtId) {
+        *self.statement_hits.entry(stmt_id).or_default() += 1;
+    }
+
+    pub(crate) fn enter(&mut self, function: Option<FunctionIdx>) {
+        let parent = self.active.last().map_or(ROOT, |call| call.node);
+        let node = match self.nodes[parent].children.get(&function) {
+            Some(node) => *node,
+            None => {
+                let node = self.nodes.len();
+                self.nodes.push(StackNode {
+                    function,
+                    parent,
+                    children: HashMap::new(),
+                    exclusive: Duration::ZERO,
+                });
+                self.nodes[parent].children.insert(function, node);
+                node
+            }
+        };
+        let times = self.functions.entry(function).or_default();
+        times.calls += 1;
+        times.active += 1;
+        self.active.push(ActiveCall {
+            node,
+            start: Instant::now(),
+            callees: Duration::ZERO,
+        });
+    }
+
+    pub(crate) fn exit(&mut self) {
+        let Some(call) = self.active.pop() else {
+            return;
+        };
+        let elapsed = call.start.elapsed();
+        let exclusive = elapsed.saturating_sub(call.callees);
+        if let Some(caller) = self.active.last_mut() {
+            caller.callees += elapsed;
+        }
+        let node = &mut self.nodes[call.node];
+        node.exclusive += exclusive;
+        let times = self.functions.get_mut(&node.function).unwrap();
+        times.exclusive += exclusive;
+        times.active -= 1;
+        if times.active == 0 {
+            times.inclusive += elapsed;
+        }
+    }
+
+    /// Resolves what was recorded into source names and lines.
+    pub(crate) fn finish(self, ast: &Ast, global_scope: &GlobalScope) -> Profile {
+        let name = |function: Option<FunctionIdx>| match function {
+            Some(function_idx) => global_scope.functions[function_idx].name.clone(),
+            None => TOP_LEVEL.to_string(),
+        };
+
+        let mut functions: Vec<FunctionProfile> = self
+            .functions
+            .iter()
+            .map(|(function, times)| FunctionProfile {
+                name: name(*function),
+                calls: times.calls,
+                inclusive: times.inclusive,
+                exclusive: times.exclusive,
+            })
+            .collect();
+        functions.sort_by(|a, b| b.exclusive.cmp(&a.exclusive).then(a.name.cmp(&b.name)));
+
+        let mut line_hits: HashMap<_, u64> = HashMap::new();
+        for (stmt_id, hits) in &self.statement_hits {
+            let span = ast.query_stmt(*stmt_id).span(ast);
+            let line_index = self
+                .source_map
+                .get(span.file_id)
+                .text
+                .line_index(span.start);
+            *line_hits.entry((span.file_id, line_index)).or_default() += hits;
+        }
+        let mut lines: Vec<LineProfile> = line_hits
+            .into_iter()
+            .map(|((file_id, line_index), hits)| {
+                let file = self.source_map.get(file_id);
+                LineProfile {
+                    file: file.name.clone(),
+                    line: line_index + 1,
+                    hits,
+                    source: file.text.get_line(line_index).trim().to_string(),
+                }
+            })
+            .collect();
+        lines.sort_by(|a, b| {
+            b.hits
+                .cmp(&a.hits)
+                .then(a.file.cmp(&b.file))
+                .then(a.line.cmp(&b.line))
+        });
+
+        let mut stacks = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate().skip(1) {
+            let mut stack = Vec::new();
+            let mut current = index;
+            while current != ROOT {
+                stack.push(name(self.nodes[current].function));
+                current = self.nodes[current].parent;
+            }
+            stack.reverse();
+            stacks.push((stack, node.exclusive));
+        }
+        stacks.sort();
+
+        Profile {
+            functions,
+            lines,
+            stacks,
+        }
+    }
+}
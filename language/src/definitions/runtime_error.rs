@@ -0,0 +1,201 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    text::span::TextSpan,
+};
+
+/// Why evaluation stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    DivisionByZero,
+    Overflow,
+    /// A value of the wrong type reached an operation. Type checking rules
+    /// this out for compiled programs, so it points at a bug in the
+    /// compiler or in a host function.
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// Calls nested deeper than the interpreter allows, usually runaway
+    /// recursion.
+    StackOverflow {
+        depth: usize,
+    },
+    /// More expressions were evaluated than the step limit allows, e.g. by
+    /// an infinite loop.
+    StepLimitExceeded {
+        limit: u64,
+    },
+    /// Strings and variables grew past the heap limit.
+    HeapLimitExceeded {
+        limit: usize,
+    },
+    /// A variable was used or assigned where it isn't declared, which
+    /// scope resolution rules out except for globals used before their
+    /// `let` ran.
+    UndeclaredVariable {
+        name: String,
+    },
+    /// An `assert` whose condition was false.
+    AssertionFailed,
+    /// A native function failed, with its message.
+    Native(String),
+    /// A native function returned a value that isn't of its declared
+    /// return type.
+    NativeReturnType {
+        name: String,
+        expected: String,
+        found: &'static str,
+    },
+    /// Not an error: the program called `exit`. Front ends end the program
+    /// with the code instead of reporting it.
+    Exit {
+        code: i64,
+    },
+    /// Evaluation was cancelled through an interrupt handle.
+    Interrupted,
+    /// An expression that failed to parse, which only a program with
+    /// errors contains.
+    InvalidExpression,
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::DivisionByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::Overflow => write!(f, "integer overflow"),
+            RuntimeErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "type mismatch: expected {}, found {}", expected, found)
+            }
+            RuntimeErrorKind::StackOverflow { depth } => {
+                write!(f, "stack overflow: more than {} nested calls", depth)
+            }
+            RuntimeErrorKind::StepLimitExceeded { limit } => {
+                write!(f, "step limit exceeded: more than {} steps", limit)
+            }
+            RuntimeErrorKind::HeapLimitExceeded { limit } => {
+                write!(f, "heap limit exceeded: more than {} bytes", limit)
+            }
+            RuntimeErrorKind::UndeclaredVariable { name } => {
+                write!(f, "variable '{}' is not declared here", name)
+            }
+            RuntimeErrorKind::AssertionFailed => write!(f, "assertion failed"),
+            RuntimeErrorKind::Native(message) => write!(f, "{}", message),
+            RuntimeErrorKind::NativeReturnType {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "native function '{}' returned {}, but is declared to return {}",
+                name, found, expected
+            ),
+            RuntimeErrorKind::Exit { code } => write!(f, "exited with code {}", code),
+            RuntimeErrorKind::Interrupted => write!(f, "evaluation interrupted"),
+            RuntimeErrorKind::InvalidExpression => {
+                write!(f, "cannot evaluate an invalid expression")
+            }
+        }
+    }
+}
+
+/// A function being executed when an error was raised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    /// The name of the function, or `None` for the top level of the script.
+    pub function: Option<String>,
+    /// Where execution was in the function: the failing expression for the
+    /// innermost frame, the call to the next frame for the others.
+    pub span: TextSpan,
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "{}", function),
+            None => write!(f, "<top level>"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: TextSpan,
+    /// The calls that led to the error, innermost first. The last frame is
+    /// always the top level.
+    pub stack_trace: Vec<StackFrame>,
+}
+
+/// How many calls a diagnostic for a runtime error points out, so that deep
+/// recursion doesn't bury the error.
+const MAX_CALL_LABELS: usize = 10;
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind, span: TextSpan, stack_trace: Vec<StackFrame>) -> Self {
+        Self {
+            kind,
+            span,
+            stack_trace,
+        }
+    }
+
+    /// The calls of the stack trace, innermost first, as the called frame
+    /// and the frame calling it. Runs of the same call, as made by
+    /// recursion, are collapsed into one with its repetition count.
+    pub fn calls(&self) -> Vec<(&StackFrame, &StackFrame, usize)> {
+        let mut calls: Vec<(&StackFrame, &StackFrame, usize)> = Vec::new();
+        for (callee, caller) in self.stack_trace.iter().zip(self.stack_trace.iter().skip(1)) {
+            match calls.last_mut() {
+                Some((last_callee, last_caller, count))
+                    if last_callee.function == callee.function && **last_caller == *caller =>
+                {
+                    *count += 1
+                }
+                _ => calls.push((callee, caller, 1)),
+            }
+        }
+        calls
+    }
+
+    /// The error as a diagnostic, with the calls of the stack trace as
+    /// labels, so it can be reported like compilation errors.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic =
+            Diagnostic::new(Severity::Error, None, self.kind.to_string(), self.span);
+        let calls = self.calls();
+        for (callee, caller, count) in calls.iter().take(MAX_CALL_LABELS) {
+            let message = match count {
+                1 => format!("in this call to '{}'", callee),
+                _ => format!("in this call to '{}', repeated {} times", callee, count),
+            };
+            diagnostic = diagnostic.with_label(caller.span, message);
+        }
+        if calls.len() > MAX_CALL_LABELS {
+            diagnostic = diagnostic.with_help(format!(
+                "{} more calls lead to here",
+                calls[MAX_CALL_LABELS..]
+                    .iter()
+                    .map(|(_, _, count)| count)
+                    .sum::<usize>()
+            ));
+        }
+        diagnostic
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        for (callee, _, count) in self.calls() {
+            write!(f, "\n    at {}", callee)?;
+            if count > 1 {
+                write!(f, " ({} times)", count)?;
+            }
+        }
+        write!(f, "\n    at <top level>")
+    }
+}
+
+impl std::error::Error for RuntimeError {}
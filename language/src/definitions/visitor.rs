@@ -1,127 +1,208 @@
-use crate{::definitions::{
-    AssignExpr, Ast, BinaryExpr, BlockExpr, Body, BoolExpr, CallExpr, Expr, ExprId, ExprKind,
-    FuncDeclaration, IfExpr, ItemId, ItemKind, LetStmt, NumberExpr, ParenthesizedExpr,
-    ReturnStmt, Stmt, StmtId, StmtKind, UnaryExpr, VarExpr, WhileStmt,
+use crate::{
+    definitions::{
+        AssignExpr, Ast, BinaryExpr, BlockExpr, Body, BoolExpr, CallExpr, ConstDeclaration,
+        EnumDeclaration, Expr, ExprId, ExprKind, FuncDeclaration, IfExpr, ItemId, ItemKind,
+        LetStmt, NullExpr, NumberExpr, ParenthesizedExpr, ReturnStmt, Stmt, StmtId, StmtKind,
+        StringExpr, StringPart, StructDeclaration, TypeAliasDeclaration, UnaryExpr, VarExpr,
+        WhileStmt,
     },
     text::span::TextSpan,
 };
 
-pub trait ASTVisitor{
-    fn visit_item(&mut self,ast: &mut Ast, item: ItemId){
+pub trait ASTVisitor {
+    fn visit_item(&mut self, ast: &mut Ast, item: ItemId) {
         self.visit_item_default(ast, item);
     }
 
-    fn visit_body(&mut self, ast: &mut Ast, body: &Body){
+    fn visit_body(&mut self, ast: &mut Ast, body: &Body) {
         self.visit_body_default(ast, body);
     }
 
-    fn visit_body_default(&mut self, ast: &mut Ast, body: &Body){
-        for stmt in body.iter(){
-            self.visit_statement(ast, *statement);
+    fn visit_body_default(&mut self, ast: &mut Ast, body: &Body) {
+        for stmt in body.iter() {
+            self.visit_statement(ast, *stmt);
         }
     }
 
-
-    fn visit_item_default(&mut self, ast: Ast, item: &ItemID){
+    fn visit_item_default(&mut self, ast: &mut Ast, item: ItemId) {
         let item = ast.query_item(item).clone();
-        match &item.kind{
-            ItemKind::Stmt(stmt) => {self.visit_statement(ast, *statement);}
-            ItemKind::Function(func) =>{self.visit_func_decl(ast, func_decl, item.id);}
+        match &item.kind {
+            ItemKind::Stmt(stmt) => {
+                self.visit_statement(ast, *stmt);
+            }
+            ItemKind::Function(func_decl) => {
+                self.visit_func_decl(ast, func_decl, item.id);
+            }
+            ItemKind::TypeAlias(type_alias) => {
+                self.visit_type_alias_decl(ast, type_alias, item.id);
+            }
+            ItemKind::Struct(struct_decl) => {
+                self.visit_struct_decl(ast, struct_decl, item.id);
+            }
+            ItemKind::Enum(enum_decl) => {
+                self.visit_enum_decl(ast, enum_decl, item.id);
+            }
+            ItemKind::Const(const_decl) => {
+                self.visit_const_decl(ast, const_decl, item.id);
+            }
         }
     }
-}
 
-fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, item_id: ItemID);
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, item_id: ItemId);
 
-fn do_visit_statement(&mut self, ast:&mut Ast, statement: StmtId){
-    let statement = stmt.query_stmt(statement).clone();
-    match &statement.kind{
-        StmtKind::Return(stmt) => {self.visit_statement(ast, &stmt);}
-        StmtKind::Let(expr) => {self.visit_let_statement(ast, &statement);}
-        StmtKind::While(stmt) => {self.visit_while_statement(ast, &stmt);}
-        StmtKind::Expr(expr) => {self.visit_expression(ast, *expr);}
+    fn visit_type_alias_decl(
+        &mut self,
+        _ast: &mut Ast,
+        _type_alias: &TypeAliasDeclaration,
+        _item_id: ItemId,
+    ) {
     }
-}
 
-fn visit_return_statement(&mut self, ast:&mut Ast, returnstmt: &ReturnStmt){
-    if let Some<expr> = &return.return_value{
-        self.visit_expression(ast, *expr);
+    fn visit_struct_decl(&mut self, _ast: &mut Ast, _struct_decl: &StructDeclaration, _item_id: ItemId) {}
+
+    fn visit_enum_decl(&mut self, _ast: &mut Ast, _enum_decl: &EnumDeclaration, _item_id: ItemId) {}
+
+    fn visit_const_decl(&mut self, _ast: &mut Ast, _const_decl: &ConstDeclaration, _item_id: ItemId) {}
+
+    fn do_visit_statement(&mut self, ast: &mut Ast, statement: StmtId) {
+        let statement = ast.query_stmt(statement).clone();
+        match &statement.kind {
+            StmtKind::Expr(expr) => {
+                self.visit_expression(ast, *expr);
+            }
+            StmtKind::Let(let_stmt) => {
+                self.visit_let_statement(ast, let_stmt, &statement);
+            }
+            StmtKind::While(while_stmt) => {
+                self.visit_while_statement(ast, while_stmt);
+            }
+            StmtKind::Return(return_stmt) => {
+                self.visit_return_statement(ast, return_stmt);
+            }
+        }
     }
-}
 
-fn visit_while_statement(&mut self, ast: &mut Ast, whilestmt: &WhileStmt){
-    self.visit_expression(ast, whileStmt.condition);
-    self.visit_body(ast, &whileStmt.body);
-}
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_stmt: &ReturnStmt) {
+        if let Some(expr) = &return_stmt.return_value {
+            self.visit_expression(ast, *expr);
+        }
+    }
 
-fn visit_block_expr(&mut self, ast: &mut Ast, blockexpr: &BlockExpr, _expr: &Expr){
-    for stmt in block_statement.stmts{
-        self.visit_statement(ast, *statement);
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_stmt: &WhileStmt) {
+        self.visit_expression(ast, while_stmt.condition);
+        self.visit_body(ast, &while_stmt.body);
     }
-}
 
-fn visit_if_expression(&mut self, ast: &mut Ast, ifexpr: &IfExpr, _expr: &Expr){
-    self.visit_expression(ast, ifexpr.condition);
-    for statement in ifexpr.then_branch.iter(){
-        self.visit_statement(ast, *statement);
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, _expr: &Expr) {
+        for stmt in &block_expr.stmts {
+            self.visit_statement(ast, *stmt);
+        }
     }
 
-    if let Some(else_branch) = &ifexpr.else_branch{
-        for statement in else_branch.body.iter(){
-            self.visit_statement(ast, *statement);
+    fn visit_if_expression(&mut self, ast: &mut Ast, if_expr: &IfExpr, _expr: &Expr) {
+        self.visit_expression(ast, if_expr.condition);
+        self.visit_body(ast, &if_expr.then_branch);
+        if let Some(else_branch) = &if_expr.else_branch {
+            self.visit_body(ast, &else_branch.body);
         }
     }
-}
 
-fn visit_let_statement(&mut self, ast: &mut Ast, letstmt: &LetStmt, stmt: &Stmt);
-fn visit_statement(&mut self, ast: &mut Ast, stmt: StmtId){
-    self.do_visit_statement(ast, statement);
-}
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, stmt: &Stmt);
 
-fn do_visit_expression(&mut self, ast: &mut Ast, expression: &ExprID){
-    let expression = expression.query_expr(expression).clone();
-    match &expression.kind{
-        ExprKind::Number(number) =>{self.visit_number_expression(ast, number, &expression);}
-        ExprKind::Binary(expr) =>{self.visit_binary_expression(ast, expr, &expression);}
-        ExprKind::Parentheshized(expr) =>{self.visit_number_expression(ast, expr, &expression);}
-        ExprKind::Error(span) =>{self.visit_error(ast, span);}
-        ExprKind::variable(expr) =>{self.visit_variable_expression(ast, expr, &expression);}
-        ExprKind::Unary(expr) =>{self.visit_unary_expression(ast, expr, &expression);}
-        ExprKind::Assignment(expr) =>{self.visit_assignment_expression(ast, expr, &expression);}
-        ExprKind::Call(expr) => {self.visit_call_expression(ast,expr, &expression)}
-        ExprKind::Boolean(expr) => {self.visit_boolean_expression(ast,expr, &expression)}
-        ExprKind::If(expr) => {self.visit_if_expression(ast,expr, &expression)}
-        ExprKind::Block(expr) => {self.visit_block_expression(ast,expr, &expression)}
+    fn visit_statement(&mut self, ast: &mut Ast, stmt: StmtId) {
+        self.do_visit_statement(ast, stmt);
     }
-}
 
-fn visit_call_expression(&mut self, ast: &mut Ast, callexpr: &CallExpr, _expr: &Expr){
-    for argument in call_expression.arguments{
-        self.visit_expression(ast, *expression)
+    fn do_visit_expression(&mut self, ast: &mut Ast, expr_id: ExprId) {
+        let expression = ast.query_expr(expr_id).clone();
+        match &expression.kind {
+            ExprKind::Number(expr) => {
+                self.visit_number_expression(ast, expr, &expression);
+            }
+            ExprKind::Binary(expr) => {
+                self.visit_binary_expression(ast, expr, &expression);
+            }
+            ExprKind::Parenthesized(expr) => {
+                self.visit_parenthesized_expression(ast, expr, &expression);
+            }
+            ExprKind::Error(span) => {
+                self.visit_error(ast, span);
+            }
+            ExprKind::Variable(expr) => {
+                self.visit_variable_expression(ast, expr, &expression);
+            }
+            ExprKind::Unary(expr) => {
+                self.visit_unary_expression(ast, expr, &expression);
+            }
+            ExprKind::Assignment(expr) => {
+                self.visit_assignment_expression(ast, expr, &expression);
+            }
+            ExprKind::Call(expr) => {
+                self.visit_call_expression(ast, expr, &expression);
+            }
+            ExprKind::Boolean(expr) => {
+                self.visit_boolean_expression(ast, expr, &expression);
+            }
+            ExprKind::Null(expr) => {
+                self.visit_null_expression(ast, expr, &expression);
+            }
+            ExprKind::String(expr) => {
+                self.visit_string_expression(ast, expr, &expression);
+            }
+            ExprKind::If(expr) => {
+                self.visit_if_expression(ast, expr, &expression);
+            }
+            ExprKind::Block(expr) => {
+                self.visit_block_expr(ast, expr, &expression);
+            }
+        }
     }
-}
 
-fn visit_expression(&mut self, ast: &mut Ast, expression: &ExprID){
-    self.do_visit_expression(ast, expression);
-}
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expr: &CallExpr, _expr: &Expr) {
+        for argument in &call_expr.arguments {
+            self.visit_expression(ast, *argument);
+        }
+    }
 
-fn visit_assignment_expression(&mut self, ast: &mut Ast, assignexpr: &AssignExpr, _expr: &Expr){
-    self.visit_expression(ast, assignment_expression.expression)
-}
+    fn visit_expression(&mut self, ast: &mut Ast, expr_id: ExprId) {
+        self.do_visit_expression(ast, expr_id);
+    }
 
-fn visit_variable_expression(&mut self, ast: &mut Ast, varexpr: &VarExpr, _expr: &Expr);
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, _expr: &Expr) {
+        self.visit_expression(ast, assign_expr.expression);
+    }
 
-fn visit_number_expression(&mut self, ast: &mut Ast, numberexpr: &NumberExpr, _expr: &Expr);
+    fn visit_variable_expression(&mut self, ast: &mut Ast, var_expr: &VarExpr, expr: &Expr);
 
-fn visit_boolean_expression(&mut self, ast: &mut Ast, boolexpr: &BoolExpr, _expr: &Expr);
+    fn visit_number_expression(&mut self, ast: &mut Ast, number_expr: &NumberExpr, expr: &Expr);
 
-fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, _expr: &Expr);
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, bool_expr: &BoolExpr, expr: &Expr);
 
-fn visit_binary_expression(&mut self, ast: &mut Ast, binexpr: &BinaryExpr, _expr: &Expr){
-    self.visit_expression(ast, binary_expression.left);
-    self.visit.expression(ast, binary_expression.right)
-}
+    fn visit_null_expression(&mut self, ast: &mut Ast, null_expr: &NullExpr, expr: &Expr);
 
-fn visit_parenthesized_expression(^&mut self, ast: &mut Ast, parenthesizedexpr: &ParenthesizedExpr, _expr: &Expr){
-    self.visit_expression(ast, parenthesized_expression.inner)
-}
\ No newline at end of file
+    fn visit_string_expression(&mut self, ast: &mut Ast, string_expr: &StringExpr, _expr: &Expr) {
+        for part in &string_expr.parts {
+            if let StringPart::Interpolation(expr) = part {
+                self.visit_expression(ast, *expr);
+            }
+        }
+    }
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan);
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, expr: &Expr);
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expr: &BinaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, binary_expr.left);
+        self.visit_expression(ast, binary_expr.right);
+    }
+
+    fn visit_parenthesized_expression(
+        &mut self,
+        ast: &mut Ast,
+        parenthesized_expr: &ParenthesizedExpr,
+        _expr: &Expr,
+    ) {
+        self.visit_expression(ast, parenthesized_expr.inner);
+    }
+}
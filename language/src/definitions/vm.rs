@@ -0,0 +1,547 @@
+use std::io::{self, BufRead, Write};
+
+use navsharp::Idx;
+
+use crate::{
+    compilation_unit::{FunctionBody, FunctionIdx, GlobalScope, VariableIdx},
+    definitions::{
+        builtins::NativeContext,
+        bytecode::{Bytecode, Chunk, Instruction, TemplatePart},
+        eval::{binary_operation, unary_operation, StringIdx, Strings, Value},
+        limits::InterruptHandle,
+        runtime_error::{RuntimeError, RuntimeErrorKind, StackFrame},
+        BinOpKind,
+    },
+    text::span::TextSpan,
+};
+
+/// The common case of a binary operation on two integers that succeeds,
+/// or `None` to leave it to [`binary_operation`].
+#[inline]
+fn integer_operation(kind: BinOpKind, left: Value, right: Value) -> Option<Value> {
+    let (Value::Number(left), Value::Number(right)) = (left, right) else {
+        return None;
+    };
+    let value = match kind {
+        BinOpKind::Plus => Value::Number(left.checked_add(right)?),
+        BinOpKind::Minus => Value::Number(left.checked_sub(right)?),
+        BinOpKind::Multiply => Value::Number(left.checked_mul(right)?),
+        BinOpKind::Divide => Value::Number(left.checked_div(right)?),
+        BinOpKind::Modulo => Value::Number(left.checked_rem(right)?),
+        BinOpKind::Equalsto => Value::Boolean(left == right),
+        BinOpKind::NotEqualsto => Value::Boolean(left != right),
+        BinOpKind::LessThan => Value::Boolean(left < right),
+        BinOpKind::LessThanEqualTo => Value::Boolean(left <= right),
+        BinOpKind::GreaterThan => Value::Boolean(left > right),
+        BinOpKind::GreaterThanEqualTo => Value::Boolean(left >= right),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// A call to a declared function being executed.
+struct Call<'a> {
+    function_idx: FunctionIdx,
+    /// The span of the call expression.
+    span: TextSpan,
+    /// The chunk and instruction to continue with once the call returns, or
+    /// `None` if the host made the call.
+    return_to: Option<(&'a Chunk, usize)>,
+    /// Where the caller's locals start on the stack.
+    caller_base: usize,
+    /// The variable count and scope depth of the caller, restored on return.
+    variable_count: usize,
+    scope_depth: usize,
+}
+
+/// An error and where it happened, before it is given a stack trace.
+type Failure = (RuntimeErrorKind, TextSpan);
+
+/// Runs [`Bytecode`] with the semantics of [`ASTEval`], many times faster.
+///
+/// The values of every call's locals and temporaries live on one stack,
+/// each call's locals at fixed slots from where its arguments start.
+///
+/// [`ASTEval`]: crate::definitions::eval::ASTEval
+pub struct VirtualMachine<'a> {
+    bytecode: &'a Bytecode,
+    global_scope: &'a GlobalScope,
+    pub strings: Strings,
+    /// The strings of [`Bytecode::strings`] that have been interned.
+    string_constants: Vec<Option<StringIdx>>,
+    stack: Vec<Value>,
+    /// The deepest the stack has been at a loop head or function entry.
+    peak_stack_len: usize,
+    /// The value of every global by variable, `None` until its `let` ran.
+    globals: Vec<Option<Value>>,
+    /// The functions being called, innermost last.
+    calls: Vec<Call<'a>>,
+    /// How many variables are declared, counted towards the heap size.
+    variable_count: usize,
+    /// The variable count when each scope being executed was entered.
+    scopes: Vec<usize>,
+    /// How many expressions have been evaluated.
+    steps: u64,
+    interrupt_handle: InterruptHandle,
+    output: Box<dyn Write + 'a>,
+    /// `None` for stdin.
+    input: Option<Box<dyn BufRead + 'a>>,
+}
+
+impl<'a> VirtualMachine<'a> {
+    /// A machine running `bytecode`, compiled from the program of
+    /// `global_scope`, within the limits it was compiled for.
+    pub fn new(bytecode: &'a Bytecode, global_scope: &'a GlobalScope) -> Self {
+        Self {
+            bytecode,
+            global_scope,
+            strings: Strings::new(),
+            string_constants: vec![None; bytecode.strings.len()],
+            stack: Vec::new(),
+            peak_stack_len: 0,
+            globals: vec![None; global_scope.variables.len()],
+            calls: Vec::new(),
+            variable_count: 0,
+            scopes: Vec::new(),
+            steps: 0,
+            interrupt_handle: InterruptHandle::new(),
+            output: Box::new(io::stdout()),
+            input: None,
+        }
+    }
+
+    /// Makes execution stop once `interrupt_handle` is interrupted, in place
+    /// of the handle the machine created.
+    pub fn with_interrupt_handle(mut self, interrupt_handle: InterruptHandle) -> Self {
+        self.interrupt_handle = interrupt_handle;
+        self
+    }
+
+    /// Sends what the program prints to `output` instead of stdout.
+    pub fn with_output(mut self, output: impl Write + 'a) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Makes the program read its input from `input` instead of stdin.
+    pub fn with_input(mut self, input: impl BufRead + 'a) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// A handle that cancels this machine's execution from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt_handle.clone()
+    }
+
+    /// The bytes strings and variables take, counted as
+    /// [`ASTEval::heap_size`] does. Only kept up to date when the heap is
+    /// limited.
+    ///
+    /// [`ASTEval::heap_size`]: crate::definitions::eval::ASTEval::heap_size
+    pub fn heap_size(&self) -> usize {
+        self.strings.heap_size() + self.variable_count * std::mem::size_of::<Value>()
+    }
+
+    /// The most values the stack has held at a loop head or function entry.
+    /// A loop leaving anything behind would make it grow with every
+    /// iteration.
+    pub fn peak_stack_len(&self) -> usize {
+        self.peak_stack_len
+    }
+
+    /// The value of a global, `None` if it hasn't been declared.
+    pub fn variable(&self, variable_idx: VariableIdx) -> Option<Value> {
+        self.globals[variable_idx.as_index()]
+    }
+
+    /// Declares a global, as its `let` would.
+    pub fn set_variable(&mut self, variable_idx: VariableIdx, value: Value) {
+        if self.globals[variable_idx.as_index()]
+            .replace(value)
+            .is_none()
+        {
+            self.variable_count += 1;
+        }
+    }
+
+    /// Executes the top-level statements of the program, stopping at the
+    /// first runtime error.
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        let chunk = &self.bytecode.top_level;
+        self.stack.resize(chunk.locals, Value::Null);
+        let result = self.execute(chunk, 0, 0);
+        self.finish(result).map(|_| ())
+    }
+
+    /// Calls a function with arguments matching its parameters, as a call
+    /// at `span` from the top level would, and returns what it returns.
+    pub fn call_function(
+        &mut self,
+        function_idx: FunctionIdx,
+        arguments: &[Value],
+        span: TextSpan,
+    ) -> Result<Value, RuntimeError> {
+        let result = match &self.bytecode.functions[function_idx] {
+            None => {
+                self.stack.extend_from_slice(arguments);
+                self.call_native(function_idx, arguments.len())
+                    .map(|()| self.pop())
+                    .map_err(|kind| (kind, span))
+            }
+            Some(chunk) => {
+                let base = self.stack.len();
+                self.stack.extend_from_slice(arguments);
+                self.enter(function_idx, chunk, base, span, None, 0)
+                    .and_then(|()| self.execute(chunk, base, self.calls.len() - 1))
+            }
+        };
+        self.finish(result)
+    }
+
+    /// Gives an error its stack trace, and forgets the calls it aborted.
+    fn finish<T>(&mut self, result: Result<T, Failure>) -> Result<T, RuntimeError> {
+        let (kind, span) = match result {
+            Ok(value) => return Ok(value),
+            Err(failure) => failure,
+        };
+        let mut stack_trace = Vec::new();
+        let mut frame_span = span;
+        for call in self.calls.iter().rev() {
+            stack_trace.push(StackFrame {
+                function: Some(self.global_scope.functions[call.function_idx].name.clone()),
+                span: frame_span,
+            });
+            frame_span = call.span;
+        }
+        stack_trace.push(StackFrame {
+            function: None,
+            span: frame_span,
+        });
+        // Only the globals outlive what was aborted.
+        let outermost = self.calls.first().map(|call| call.variable_count);
+        if let Some(count) = self
+            .scopes
+            .first()
+            .copied()
+            .into_iter()
+            .chain(outermost)
+            .min()
+        {
+            self.variable_count = count;
+        }
+        self.stack.clear();
+        self.calls.clear();
+        self.scopes.clear();
+        Err(RuntimeError::new(kind, span, stack_trace))
+    }
+
+    /// Starts a call to a declared function whose arguments are on the
+    /// stack from `base`.
+    fn enter(
+        &mut self,
+        function_idx: FunctionIdx,
+        chunk: &Chunk,
+        base: usize,
+        span: TextSpan,
+        return_to: Option<(&'a Chunk, usize)>,
+        caller_base: usize,
+    ) -> Result<(), Failure> {
+        let max_call_depth = self.bytecode.limits.max_call_depth;
+        if self.calls.len() >= max_call_depth {
+            return Err((
+                RuntimeErrorKind::StackOverflow {
+                    depth: max_call_depth,
+                },
+                span,
+            ));
+        }
+        self.calls.push(Call {
+            function_idx,
+            span,
+            return_to,
+            caller_base,
+            variable_count: self.variable_count,
+            scope_depth: self.scopes.len(),
+        });
+        self.variable_count += self.stack.len() - base;
+        self.stack.resize(base + chunk.locals, Value::Null);
+        Ok(())
+    }
+
+    /// Calls a native function with the `arguments` values on top of the
+    /// stack, replacing them with its result.
+    fn call_native(
+        &mut self,
+        function_idx: FunctionIdx,
+        arguments: usize,
+    ) -> Result<(), RuntimeErrorKind> {
+        let FunctionBody::Native(native) = &self.global_scope.functions[function_idx].body else {
+            unreachable!("native call to a declared function");
+        };
+        let start = self.stack.len() - arguments;
+        let input = self
+            .input
+            .as_deref_mut()
+            .map(|input| input as &mut dyn BufRead);
+        let mut context = NativeContext::new(&mut self.strings, &mut self.output, input);
+        let result = native.call(&mut context, &self.stack[start..])?;
+        self.stack.truncate(start);
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// Frees the strings the program can't reach anymore. Everything the
+    /// program holds is on the stack or in a global, and the constants of
+    /// the string pool are interned again when they were freed.
+    fn collect_strings(&mut self) {
+        let roots = self
+            .stack
+            .iter()
+            .chain(self.globals.iter().flatten())
+            .chain(self.global_scope.constants.values())
+            .copied();
+        self.strings.collect(roots);
+        for string in &mut self.string_constants {
+            if string.is_some_and(|idx| !self.strings.contains(idx)) {
+                *string = None;
+            }
+        }
+    }
+
+    fn string_constant(&mut self, idx: u32) -> Value {
+        let idx = idx as usize;
+        let string = match self.string_constants[idx] {
+            Some(string) => string,
+            None => {
+                let string = self.strings.intern(&self.bytecode.strings[idx]);
+                self.string_constants[idx] = Some(string);
+                string
+            }
+        };
+        Value::String(string)
+    }
+
+    fn interpolate(&mut self, idx: u32) -> Value {
+        let template = &self.bytecode.templates[idx as usize];
+        let values = template
+            .iter()
+            .filter(|part| **part == TemplatePart::Value)
+            .count();
+        let start = self.stack.len() - values;
+        let mut values = self.stack[start..].iter();
+        let mut text = String::new();
+        for part in template {
+            match part {
+                TemplatePart::Literal(literal) => text.push_str(literal),
+                TemplatePart::Value => {
+                    let value = values.next().expect("expected an interpolated value");
+                    text.push_str(&value.display(&self.strings));
+                }
+            }
+        }
+        self.stack.truncate(start);
+        Value::String(self.strings.intern(&text))
+    }
+
+    fn undeclared(&self, variable_idx: VariableIdx) -> RuntimeErrorKind {
+        RuntimeErrorKind::UndeclaredVariable {
+            name: self.global_scope.variables[variable_idx].name.clone(),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("expected a value on the stack")
+    }
+
+    /// Executes `chunk`, whose locals start at `base`, until the call at
+    /// `depth` returns.
+    fn execute(&mut self, chunk: &'a Chunk, base: usize, depth: usize) -> Result<Value, Failure> {
+        let bytecode = self.bytecode;
+        let limits = bytecode.limits;
+        let mut chunk = chunk;
+        let mut base = base;
+        let mut ip = 0;
+        loop {
+            let instruction = chunk.code[ip];
+            ip += 1;
+            let span = || chunk.spans[ip - 1];
+            match instruction {
+                Instruction::Step { count, first } => {
+                    let before = self.steps;
+                    self.steps += u64::from(count);
+                    let first = first as usize;
+                    if self.interrupt_handle.is_interrupted() {
+                        return Err((RuntimeErrorKind::Interrupted, chunk.step_spans[first]));
+                    }
+                    if let Some(limit) = limits.max_steps {
+                        if self.steps > limit {
+                            // The first of the steps past the limit fails.
+                            let failing = first + (limit - before) as usize;
+                            return Err((
+                                RuntimeErrorKind::StepLimitExceeded { limit },
+                                chunk.step_spans[failing],
+                            ));
+                        }
+                    }
+                }
+                Instruction::CheckInterrupt => {
+                    if self.interrupt_handle.is_interrupted() {
+                        return Err((RuntimeErrorKind::Interrupted, span()));
+                    }
+                }
+                Instruction::CheckHeap => {
+                    if let Some(limit) = limits.max_heap_size {
+                        if self.heap_size() > limit {
+                            return Err((RuntimeErrorKind::HeapLimitExceeded { limit }, span()));
+                        }
+                    }
+                }
+                Instruction::Collect => {
+                    self.peak_stack_len = self.peak_stack_len.max(self.stack.len());
+                    if self.strings.should_collect() {
+                        self.collect_strings();
+                    }
+                }
+                Instruction::EnterScope => self.scopes.push(self.variable_count),
+                Instruction::ExitScope { first, end } => {
+                    if let Some(variable_count) = self.scopes.pop() {
+                        self.variable_count = variable_count;
+                    }
+                    // Their strings mustn't outlive them, as they don't in
+                    // the tree walker.
+                    self.stack[base + first as usize..base + end as usize].fill(Value::Void);
+                }
+                Instruction::Push(value) => self.stack.push(value),
+                Instruction::PushString(idx) => {
+                    let value = self.string_constant(idx);
+                    self.stack.push(value);
+                }
+                Instruction::Interpolate(idx) => {
+                    let value = self.interpolate(idx);
+                    self.stack.push(value);
+                }
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::Duplicate => {
+                    let value = *self.stack.last().expect("expected a value on the stack");
+                    self.stack.push(value);
+                }
+                Instruction::LoadLocal(slot) => {
+                    let value = self.stack[base + slot as usize];
+                    self.stack.push(value);
+                }
+                Instruction::StoreLocal(slot) => {
+                    let value = self.pop();
+                    self.stack[base + slot as usize] = value;
+                }
+                Instruction::DeclareLocal(slot) => {
+                    let value = self.pop();
+                    self.stack[base + slot as usize] = value;
+                    self.variable_count += 1;
+                }
+                Instruction::LoadGlobal(variable_idx) => {
+                    match self.globals[variable_idx.as_index()] {
+                        Some(value) => self.stack.push(value),
+                        None => return Err((self.undeclared(variable_idx), span())),
+                    }
+                }
+                Instruction::StoreGlobal(variable_idx) => {
+                    let value = self.pop();
+                    match &mut self.globals[variable_idx.as_index()] {
+                        Some(global) => *global = value,
+                        None => return Err((self.undeclared(variable_idx), span())),
+                    }
+                }
+                Instruction::DeclareGlobal(variable_idx) => {
+                    let value = self.pop();
+                    self.set_variable(variable_idx, value);
+                }
+                Instruction::Unary(kind) => {
+                    let operand = self.pop();
+                    let value = unary_operation(&kind, operand).map_err(|kind| (kind, span()))?;
+                    self.stack.push(value);
+                }
+                Instruction::Binary(kind) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = match integer_operation(kind, left, right) {
+                        Some(value) => value,
+                        None => binary_operation(&kind, left, right, &mut self.strings)
+                            .map_err(|kind| (kind, span()))?,
+                    };
+                    self.stack.push(value);
+                }
+                Instruction::Jump(target) => ip = target as usize,
+                Instruction::JumpIfFalse(target) => match self.pop() {
+                    Value::Boolean(true) => {}
+                    Value::Boolean(false) => ip = target as usize,
+                    condition => {
+                        let kind = condition.expect_boolean().unwrap_err();
+                        return Err((kind, span()));
+                    }
+                },
+                Instruction::JumpUnless { comparison, target } => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let holds = match integer_operation(comparison, left, right) {
+                        Some(value) => value,
+                        None => binary_operation(&comparison, left, right, &mut self.strings)
+                            .map_err(|kind| (kind, span()))?,
+                    };
+                    if holds == Value::Boolean(false) {
+                        ip = target as usize;
+                    }
+                }
+                Instruction::Call {
+                    function,
+                    arguments,
+                } => {
+                    let callee = bytecode.functions[function]
+                        .as_ref()
+                        .expect("expected a declared function");
+                    let callee_base = self.stack.len() - arguments as usize;
+                    self.enter(
+                        function,
+                        callee,
+                        callee_base,
+                        span(),
+                        Some((chunk, ip)),
+                        base,
+                    )?;
+                    chunk = callee;
+                    base = callee_base;
+                    ip = 0;
+                }
+                Instruction::CallNative {
+                    function,
+                    arguments,
+                } => {
+                    self.call_native(function, arguments as usize)
+                        .map_err(|kind| (kind, span()))?;
+                }
+                Instruction::Return => {
+                    let value = self.pop();
+                    self.stack.truncate(base);
+                    if self.calls.len() == depth {
+                        return Ok(value);
+                    }
+                    let call = self.calls.pop().expect("expected a call to return from");
+                    self.variable_count = call.variable_count;
+                    self.scopes.truncate(call.scope_depth);
+                    let Some((caller, return_ip)) = call.return_to else {
+                        return Ok(value);
+                    };
+                    chunk = caller;
+                    ip = return_ip;
+                    base = call.caller_base;
+                    self.stack.push(value);
+                }
+                Instruction::Invalid => {
+                    return Err((RuntimeErrorKind::InvalidExpression, span()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::diagnostics::ErrorCode;
+
+/// The long-form explanation of an error code, in markdown, shown by
+/// `nsharp --explain`. Each one shows code failing with the error and how
+/// to fix it.
+pub fn explanation(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::UnexpectedToken => include_str!("explanations/E0001.md"),
+        ErrorCode::ExpectedExpression => include_str!("explanations/E0002.md"),
+        ErrorCode::UnterminatedString => include_str!("explanations/E0003.md"),
+        ErrorCode::InvalidEscapeSequence => include_str!("explanations/E0004.md"),
+        ErrorCode::NestingTooDeep => include_str!("explanations/E0005.md"),
+        ErrorCode::UndeclaredVariable => include_str!("explanations/E0010.md"),
+        ErrorCode::UndeclaredFunction => include_str!("explanations/E0011.md"),
+        ErrorCode::UndeclaredType => include_str!("explanations/E0012.md"),
+        ErrorCode::FunctionAlreadyDeclared => include_str!("explanations/E0013.md"),
+        ErrorCode::VariableAlreadyDeclared => include_str!("explanations/E0014.md"),
+        ErrorCode::DuplicateParameter => include_str!("explanations/E0015.md"),
+        ErrorCode::TypeAlreadyDeclared => include_str!("explanations/E0016.md"),
+        ErrorCode::DuplicateField => include_str!("explanations/E0017.md"),
+        ErrorCode::DuplicateVariant => include_str!("explanations/E0018.md"),
+        ErrorCode::RecursiveTypeAlias => include_str!("explanations/E0019.md"),
+        ErrorCode::CannotAssignToConst => include_str!("explanations/E0020.md"),
+        ErrorCode::TypeMismatch => include_str!("explanations/E0030.md"),
+        ErrorCode::PossiblyNullValue => include_str!("explanations/E0031.md"),
+        ErrorCode::InvalidBinaryOperands => include_str!("explanations/E0032.md"),
+        ErrorCode::InvalidArgumentCount => include_str!("explanations/E0033.md"),
+        ErrorCode::CannotInferNullType => include_str!("explanations/E0034.md"),
+        ErrorCode::MissingTypeAnnotation => include_str!("explanations/E0035.md"),
+        ErrorCode::CannotReturnOutsideFunction => include_str!("explanations/E0036.md"),
+        ErrorCode::CannotInterpolate => include_str!("explanations/E0037.md"),
+        ErrorCode::NotAllPathsReturn => include_str!("explanations/E0040.md"),
+        ErrorCode::PossiblyUninitializedVariable => include_str!("explanations/E0041.md"),
+        ErrorCode::UnreachableStatement => include_str!("explanations/E0042.md"),
+        ErrorCode::NonConstantExpression => include_str!("explanations/E0050.md"),
+        ErrorCode::ConstDivisionByZero => include_str!("explanations/E0051.md"),
+        ErrorCode::ConstOverflow => include_str!("explanations/E0052.md"),
+        ErrorCode::ConstCycle => include_str!("explanations/E0053.md"),
+    }
+}
+
+/// A code block of an explanation, fenced with ` ```nsharp `.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example<'a> {
+    pub source: &'a str,
+    /// Whether the example must fail to compile, marked with
+    /// ` ```nsharp,compile_fail `.
+    pub compile_fail: bool,
+    /// The error a failing example is expected to report, given after
+    /// `compile_fail`, e.g. ` ```nsharp,compile_fail,E0010 `.
+    pub expected_error: Option<ErrorCode>,
+}
+
+/// The examples of an explanation, in order. Blocks in other languages are
+/// skipped.
+pub fn examples(explanation: &str) -> Vec<Example<'_>> {
+    let mut examples = Vec::new();
+    let mut rest = explanation;
+    while let Some(start) = rest.find("```nsharp") {
+        let block = &rest[start + "```".len()..];
+        let Some((info, body)) = block.split_once('\n') else {
+            break;
+        };
+        let end = body.find("```").unwrap_or(body.len());
+        examples.push(Example {
+            source: &body[..end],
+            compile_fail: info.split(',').any(|attribute| attribute == "compile_fail"),
+            expected_error: info.split(',').find_map(ErrorCode::parse),
+        });
+        rest = &body[(end + "```".len()).min(body.len())..];
+    }
+    examples
+}
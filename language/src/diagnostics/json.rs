@@ -0,0 +1,172 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    diagnostics::Diagnostic,
+    text::{source_map::SourceMap, span::TextSpan},
+};
+
+/// A JSON document. Object members keep their insertion order so the output
+/// is stable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    pub fn string(value: impl Into<String>) -> Self {
+        Json::String(value.into())
+    }
+
+    pub fn optional_string(value: Option<impl Into<String>>) -> Self {
+        value.map_or(Json::Null, Json::string)
+    }
+}
+
+fn write_escaped(f: &mut Formatter<'_>, value: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_escaped(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Where a span lies in its source, with one-based lines and columns the way
+/// editors count them. The end is exclusive.
+pub struct SpanLocation {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl SpanLocation {
+    pub fn new(source_map: &SourceMap, span: &TextSpan) -> Self {
+        let text = &source_map.get(span.file_id).text;
+        Self {
+            start: span.start,
+            end: span.end,
+            start_line: text.line_index(span.start) + 1,
+            start_column: text.column_index(span.start) + 1,
+            end_line: text.line_index(span.end) + 1,
+            end_column: text.column_index(span.end) + 1,
+        }
+    }
+}
+
+/// Emits one JSON object per diagnostic, one per line, for tools that would
+/// otherwise scrape the human-readable output.
+pub struct JsonEmitter<'a> {
+    source_map: &'a SourceMap,
+}
+
+impl<'a> JsonEmitter<'a> {
+    pub fn new(source_map: &'a SourceMap) -> Self {
+        Self { source_map }
+    }
+
+    pub fn print(&self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            eprintln!("{}", self.emit(diagnostic));
+        }
+    }
+
+    pub fn emit(&self, diagnostic: &Diagnostic) -> Json {
+        let labels = diagnostic
+            .labels
+            .iter()
+            .map(|label| {
+                Json::Object(vec![
+                    ("message", Json::string(&label.message)),
+                    ("span", self.emit_span(&label.span)),
+                ])
+            })
+            .collect();
+        let suggestions = diagnostic
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                Json::Object(vec![
+                    ("message", Json::string(&suggestion.message)),
+                    ("replacement", Json::string(&suggestion.replacement)),
+                    ("span", self.emit_span(&suggestion.span)),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            (
+                "code",
+                Json::optional_string(diagnostic.code.map(|code| code.to_string())),
+            ),
+            ("lint", Json::optional_string(diagnostic.lint)),
+            ("severity", Json::string(diagnostic.severity.to_string())),
+            ("message", Json::string(&diagnostic.message)),
+            ("span", self.emit_span(&diagnostic.span)),
+            ("labels", Json::Array(labels)),
+            ("help", Json::optional_string(diagnostic.help.as_deref())),
+            ("suggestions", Json::Array(suggestions)),
+        ])
+    }
+
+    fn emit_span(&self, span: &TextSpan) -> Json {
+        let location = SpanLocation::new(self.source_map, span);
+        Json::Object(vec![
+            (
+                "file",
+                Json::string(&self.source_map.get(span.file_id).name),
+            ),
+            ("byte_start", Json::Number(location.start as i64)),
+            ("byte_end", Json::Number(location.end as i64)),
+            ("line_start", Json::Number(location.start_line as i64)),
+            ("column_start", Json::Number(location.start_column as i64)),
+            ("line_end", Json::Number(location.end_line as i64)),
+            ("column_end", Json::Number(location.end_column as i64)),
+        ])
+    }
+}
@@ -0,0 +1,652 @@
+pub mod explain;
+pub mod json;
+pub mod printer;
+pub mod sarif;
+
+use std::{
+    cell::RefCell,
+    fmt::{Display, Formatter},
+    rc::Rc,
+};
+
+use crate::{
+    definitions::lexer::{Token, TokenKind},
+    text::span::TextSpan,
+    typecheck::Type,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Identifies a kind of error across releases. The numbers are part of the
+/// language's interface: never reuse or renumber one, only add new ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum ErrorCode {
+    UnexpectedToken = 1,
+    ExpectedExpression = 2,
+    UnterminatedString = 3,
+    InvalidEscapeSequence = 4,
+    NestingTooDeep = 5,
+
+    UndeclaredVariable = 10,
+    UndeclaredFunction = 11,
+    UndeclaredType = 12,
+    FunctionAlreadyDeclared = 13,
+    VariableAlreadyDeclared = 14,
+    DuplicateParameter = 15,
+    TypeAlreadyDeclared = 16,
+    DuplicateField = 17,
+    DuplicateVariant = 18,
+    RecursiveTypeAlias = 19,
+    CannotAssignToConst = 20,
+
+    TypeMismatch = 30,
+    PossiblyNullValue = 31,
+    InvalidBinaryOperands = 32,
+    InvalidArgumentCount = 33,
+    CannotInferNullType = 34,
+    MissingTypeAnnotation = 35,
+    CannotReturnOutsideFunction = 36,
+    CannotInterpolate = 37,
+
+    NotAllPathsReturn = 40,
+    PossiblyUninitializedVariable = 41,
+    UnreachableStatement = 42,
+
+    NonConstantExpression = 50,
+    ConstDivisionByZero = 51,
+    ConstOverflow = 52,
+    ConstCycle = 53,
+}
+
+impl ErrorCode {
+    pub fn all() -> &'static [ErrorCode] {
+        &[
+            ErrorCode::UnexpectedToken,
+            ErrorCode::ExpectedExpression,
+            ErrorCode::UnterminatedString,
+            ErrorCode::InvalidEscapeSequence,
+            ErrorCode::NestingTooDeep,
+            ErrorCode::UndeclaredVariable,
+            ErrorCode::UndeclaredFunction,
+            ErrorCode::UndeclaredType,
+            ErrorCode::FunctionAlreadyDeclared,
+            ErrorCode::VariableAlreadyDeclared,
+            ErrorCode::DuplicateParameter,
+            ErrorCode::TypeAlreadyDeclared,
+            ErrorCode::DuplicateField,
+            ErrorCode::DuplicateVariant,
+            ErrorCode::RecursiveTypeAlias,
+            ErrorCode::CannotAssignToConst,
+            ErrorCode::TypeMismatch,
+            ErrorCode::PossiblyNullValue,
+            ErrorCode::InvalidBinaryOperands,
+            ErrorCode::InvalidArgumentCount,
+            ErrorCode::CannotInferNullType,
+            ErrorCode::MissingTypeAnnotation,
+            ErrorCode::CannotReturnOutsideFunction,
+            ErrorCode::CannotInterpolate,
+            ErrorCode::NotAllPathsReturn,
+            ErrorCode::PossiblyUninitializedVariable,
+            ErrorCode::UnreachableStatement,
+            ErrorCode::NonConstantExpression,
+            ErrorCode::ConstDivisionByZero,
+            ErrorCode::ConstOverflow,
+            ErrorCode::ConstCycle,
+        ]
+    }
+
+    pub fn number(&self) -> u16 {
+        *self as u16
+    }
+
+    /// The code written as `E0010`, the way diagnostics display it.
+    pub fn parse(code: &str) -> Option<Self> {
+        let number: u16 = code.strip_prefix('E')?.parse().ok()?;
+        Self::all()
+            .iter()
+            .find(|error_code| error_code.number() == number)
+            .copied()
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "E{:04}", self.number())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: TextSpan,
+    pub message: String,
+}
+
+/// A replacement of the text under `span` that fixes the diagnostic, so that
+/// editors and CI tooling can offer to apply it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub span: TextSpan,
+    pub replacement: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    /// The lint that reported the diagnostic, if any.
+    pub lint: Option<&'static str>,
+    pub message: String,
+    pub span: TextSpan,
+    /// Related locations, e.g. where a conflicting name was first declared.
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: Option<ErrorCode>,
+        message: String,
+        span: TextSpan,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            lint: None,
+            message,
+            span,
+            labels: Vec::new(),
+            help: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(code: ErrorCode, message: String, span: TextSpan) -> Self {
+        Self::new(Severity::Error, Some(code), message, span)
+    }
+
+    pub fn warning(message: String, span: TextSpan) -> Self {
+        Self::new(Severity::Warning, None, message, span)
+    }
+
+    pub fn note(message: String, span: TextSpan) -> Self {
+        Self::new(Severity::Note, None, message, span)
+    }
+
+    pub fn with_lint(mut self, lint: &'static str) -> Self {
+        self.lint = Some(lint);
+        self
+    }
+
+    pub fn with_label(mut self, span: TextSpan, message: String) -> Self {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_suggestion(mut self, span: TextSpan, replacement: String, message: String) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            replacement,
+            message,
+        });
+        self
+    }
+}
+
+/// Collects the diagnostics of every compiler pass. It is shared through a
+/// `DiagnosticsBagCell` so that each pass can report into the same bag.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsBag {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub type DiagnosticsBagCell = Rc<RefCell<DiagnosticsBag>>;
+
+impl DiagnosticsBag {
+    pub fn new() -> Self {
+        Self {
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Adds a diagnostic unless an identical one was already reported, as
+    /// happens when error recovery revisits the same tokens.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Orders diagnostics by file and where they occur in it, errors first
+    /// among those starting at the same position.
+    pub fn sort(&mut self) {
+        self.diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.span, diagnostic.severity));
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    fn report_error(&mut self, code: ErrorCode, message: String, span: &TextSpan) {
+        self.report(Diagnostic::error(code, message, *span));
+    }
+
+    /// Suggests the names that could have been meant instead of the one
+    /// under `span`, both as help and as fixes replacing it.
+    fn with_candidates(
+        mut diagnostic: Diagnostic,
+        span: &TextSpan,
+        candidates: &[&str],
+    ) -> Diagnostic {
+        if candidates.is_empty() {
+            return diagnostic;
+        }
+        let quoted: Vec<String> = candidates
+            .iter()
+            .map(|candidate| format!("'{}'", candidate))
+            .collect();
+        diagnostic = diagnostic.with_help(format!("did you mean {}?", quoted.join(" or ")));
+        for candidate in candidates {
+            diagnostic = diagnostic.with_suggestion(
+                *span,
+                candidate.to_string(),
+                format!("replace with '{}'", candidate),
+            );
+        }
+        diagnostic
+    }
+
+    pub fn report_unexpected_token(&mut self, expected: &TokenKind, token: &Token) {
+        self.report_error(
+            ErrorCode::UnexpectedToken,
+            format!("Expected <{}>, found <{}>", expected, token.kind),
+            &token.span,
+        );
+    }
+
+    pub fn report_expected_expression(&mut self, token: &Token) {
+        self.report_error(
+            ErrorCode::ExpectedExpression,
+            format!("Expected expression, found <{}>", token.kind),
+            &token.span,
+        );
+    }
+
+    pub fn report_nesting_too_deep(&mut self, token: &Token, max_depth: usize) {
+        self.report_error(
+            ErrorCode::NestingTooDeep,
+            format!("Code is nested more than {} levels deep", max_depth),
+            &token.span,
+        );
+    }
+
+    pub fn report_unterminated_string(&mut self, token: &Token) {
+        self.report_error(
+            ErrorCode::UnterminatedString,
+            "Unterminated string literal".to_string(),
+            &token.span,
+        );
+    }
+
+    pub fn report_invalid_escape_sequence(&mut self, escape: &str, span: &TextSpan) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::InvalidEscapeSequence,
+                format!("Invalid escape sequence '{}'", escape),
+                *span,
+            )
+            .with_help("valid escapes are \\n, \\t, \\r, \\0, \\\\, \\\", \\{ and \\}".to_string()),
+        );
+    }
+
+    pub fn report_undeclared_variable(&mut self, name: &str, span: &TextSpan, candidates: &[&str]) {
+        let diagnostic = Diagnostic::error(
+            ErrorCode::UndeclaredVariable,
+            format!("Undeclared variable '{}'", name),
+            *span,
+        );
+        self.report(Self::with_candidates(diagnostic, span, candidates));
+    }
+
+    pub fn report_undeclared_function(&mut self, name: &str, span: &TextSpan, candidates: &[&str]) {
+        let diagnostic = Diagnostic::error(
+            ErrorCode::UndeclaredFunction,
+            format!("Undeclared function '{}'", name),
+            *span,
+        );
+        self.report(Self::with_candidates(diagnostic, span, candidates));
+    }
+
+    pub fn report_undeclared_type(&mut self, name: &str, span: &TextSpan, candidates: &[&str]) {
+        let diagnostic = Diagnostic::error(
+            ErrorCode::UndeclaredType,
+            format!("Undeclared type '{}'", name),
+            *span,
+        );
+        self.report(Self::with_candidates(diagnostic, span, candidates));
+    }
+
+    pub fn report_function_already_declared(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::FunctionAlreadyDeclared,
+            format!("Function '{}' already declared", name),
+            span,
+        );
+    }
+
+    pub fn report_variable_already_declared(
+        &mut self,
+        name: &str,
+        existing_is_const: bool,
+        span: &TextSpan,
+    ) {
+        let existing = if existing_is_const { "a const" } else { "a variable" };
+        self.report_error(
+            ErrorCode::VariableAlreadyDeclared,
+            format!("'{}' is already declared as {}", name, existing),
+            span,
+        );
+    }
+
+    pub fn report_duplicate_parameter(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::DuplicateParameter,
+            format!("Parameter '{}' declared more than once", name),
+            span,
+        );
+    }
+
+    pub fn report_type_already_declared(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::TypeAlreadyDeclared,
+            format!("Type '{}' already declared", name),
+            span,
+        );
+    }
+
+    pub fn report_duplicate_field(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::DuplicateField,
+            format!("Field '{}' declared more than once", name),
+            span,
+        );
+    }
+
+    pub fn report_duplicate_variant(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::DuplicateVariant,
+            format!("Variant '{}' declared more than once", name),
+            span,
+        );
+    }
+
+    pub fn report_recursive_type_alias(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::RecursiveTypeAlias,
+            format!("Type alias '{}' refers to itself", name),
+            span,
+        );
+    }
+
+    pub fn report_cannot_assign_to_const(&mut self, name: &str, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::CannotAssignToConst,
+            format!("Cannot assign to const '{}'", name),
+            span,
+        );
+    }
+
+    pub fn report_type_mismatch(&mut self, expected: &Type, actual: &Type, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::TypeMismatch,
+            format!("Expected type '{}', found '{}'", expected, actual),
+            span,
+        );
+    }
+
+    pub fn report_possibly_null_value(&mut self, ty: &Type, span: &TextSpan) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::PossiblyNullValue,
+                format!("Value of type '{}' may be null", ty),
+                *span,
+            )
+            .with_help("check for null first, e.g. `if value != null { ... }`".to_string()),
+        );
+    }
+
+    pub fn report_invalid_binary_operands(
+        &mut self,
+        operator: &str,
+        span: &TextSpan,
+        left: &Type,
+        right: &Type,
+    ) {
+        self.report_error(
+            ErrorCode::InvalidBinaryOperands,
+            format!(
+                "Operator '{}' cannot be applied to '{}' and '{}'",
+                operator, left, right
+            ),
+            span,
+        );
+    }
+
+    pub fn report_invalid_argument_count(
+        &mut self,
+        name: &str,
+        span: &TextSpan,
+        expected: usize,
+        actual: usize,
+    ) {
+        self.report_error(
+            ErrorCode::InvalidArgumentCount,
+            format!(
+                "Function '{}' takes {} arguments, but {} were given",
+                name, expected, actual
+            ),
+            span,
+        );
+    }
+
+    pub fn report_cannot_infer_null_type(&mut self, name: &str, span: &TextSpan) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::CannotInferNullType,
+                format!("Cannot infer the type of '{}' from null", name),
+                *span,
+            )
+            .with_help(format!(
+                "add a type annotation, e.g. `let {}: int? = null`",
+                name
+            )),
+        );
+    }
+
+    pub fn report_missing_type_annotation(&mut self, name: &str, span: &TextSpan) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::MissingTypeAnnotation,
+                format!(
+                    "Variable '{}' needs a type annotation or an initializer",
+                    name
+                ),
+                *span,
+            )
+            .with_help(format!("declare it as `let {}: int;`", name)),
+        );
+    }
+
+    pub fn report_cannot_return_outside_function(&mut self, token: &Token) {
+        self.report_error(
+            ErrorCode::CannotReturnOutsideFunction,
+            "Cannot return outside of a function".to_string(),
+            &token.span,
+        );
+    }
+
+    pub fn report_cannot_interpolate(&mut self, ty: &Type, span: &TextSpan) {
+        self.report_error(
+            ErrorCode::CannotInterpolate,
+            format!("Cannot interpolate a value of type '{}' into a string", ty),
+            span,
+        );
+    }
+
+    pub fn report_not_all_paths_return(
+        &mut self,
+        name: &str,
+        span: &TextSpan,
+        close_brace: &TextSpan,
+    ) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::NotAllPathsReturn,
+                format!("Not all paths of '{}' return a value", name),
+                *span,
+            )
+            .with_label(
+                *close_brace,
+                "control reaches the end of the function here".to_string(),
+            ),
+        );
+    }
+
+    pub fn report_possibly_uninitialized_variable(
+        &mut self,
+        name: &str,
+        span: &TextSpan,
+        declaration: &TextSpan,
+    ) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::PossiblyUninitializedVariable,
+                format!("Use of possibly-uninitialized variable '{}'", name),
+                *span,
+            )
+            .with_label(*declaration, "declared here without a value".to_string()),
+        );
+    }
+
+    pub fn report_unknown_lint(&mut self, name: &str, span: &TextSpan, candidates: &[&str]) {
+        self.report(Self::with_candidates(
+            Diagnostic::warning(format!("Unknown lint '{}'", name), *span),
+            span,
+            candidates,
+        ));
+    }
+
+    pub fn report_unknown_attribute(&mut self, name: &str, span: &TextSpan) {
+        self.report(
+            Diagnostic::warning(format!("Unknown attribute '{}'", name), *span)
+                .with_help("expected one of 'allow', 'warn' or 'deny'".to_string()),
+        );
+    }
+
+    pub fn report_non_constant_expression(
+        &mut self,
+        constant: &str,
+        constant_span: &TextSpan,
+        span: &TextSpan,
+    ) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::NonConstantExpression,
+                format!("Const '{}' must be computable at compile time", constant),
+                *constant_span,
+            )
+            .with_label(*span, "not allowed in a const".to_string()),
+        );
+    }
+
+    pub fn report_const_division_by_zero(
+        &mut self,
+        constant: &str,
+        constant_span: &TextSpan,
+        span: &TextSpan,
+    ) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::ConstDivisionByZero,
+                format!("Evaluating const '{}' divides by zero", constant),
+                *constant_span,
+            )
+            .with_label(*span, "division by zero".to_string()),
+        );
+    }
+
+    pub fn report_const_overflow(
+        &mut self,
+        constant: &str,
+        constant_span: &TextSpan,
+        span: &TextSpan,
+    ) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::ConstOverflow,
+                format!("Evaluating const '{}' overflows", constant),
+                *constant_span,
+            )
+            .with_label(*span, "integer overflow".to_string()),
+        );
+    }
+
+    pub fn report_const_cycle(
+        &mut self,
+        constant: &str,
+        constant_span: &TextSpan,
+        reference: &str,
+        reference_span: &TextSpan,
+    ) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::ConstCycle,
+                format!("Const '{}' depends on itself", constant),
+                *constant_span,
+            )
+            .with_label(
+                *reference_span,
+                format!("'{}' is used here while it is being evaluated", reference),
+            ),
+        );
+    }
+}
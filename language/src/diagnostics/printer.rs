@@ -0,0 +1,251 @@
+use std::io;
+
+use termion::{
+    color::{self, Fg, Reset},
+    style,
+};
+
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    text::{
+        source_map::{FileId, SourceMap},
+        span::TextSpan,
+    },
+};
+
+/// Renders diagnostics the way modern compilers do:
+///
+/// ```text
+/// error[E0012]: Undeclared type 'itn'
+///  --> main.ns:1:8
+///   |
+/// 1 | let x: itn = 1;
+///   |        ^^^
+///   |
+///   = help: did you mean 'int'?
+/// ```
+///
+/// Primary spans are underlined with `^`, secondary labels with `-`.
+pub struct DiagnosticsPrinter<'a> {
+    source_map: &'a SourceMap,
+    diagnostics: &'a [Diagnostic],
+    use_colors: bool,
+}
+
+/// An underline below one source line.
+struct Mark<'a> {
+    file_id: FileId,
+    line_index: usize,
+    start_column: usize,
+    end_column: usize,
+    is_primary: bool,
+    message: Option<&'a str>,
+}
+
+impl<'a> DiagnosticsPrinter<'a> {
+    /// Colors are on when stderr is a terminal.
+    pub fn new(source_map: &'a SourceMap, diagnostics: &'a [Diagnostic]) -> Self {
+        Self {
+            source_map,
+            diagnostics,
+            use_colors: termion::is_tty(&io::stderr()),
+        }
+    }
+
+    pub fn with_colors(mut self, use_colors: bool) -> Self {
+        self.use_colors = use_colors;
+        self
+    }
+
+    pub fn print(&self) {
+        for diagnostic in self.diagnostics {
+            eprintln!("{}", self.stringify_diagnostic(diagnostic));
+        }
+    }
+
+    pub fn stringify_diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        let severity_color = self.severity_color(diagnostic.severity);
+        let mut result = match (&diagnostic.code, diagnostic.lint) {
+            (Some(code), _) => format!("{}{}[{}]", severity_color, diagnostic.severity, code),
+            (None, Some(lint)) => format!("{}{}[{}]", severity_color, diagnostic.severity, lint),
+            (None, None) => format!("{}{}", severity_color, diagnostic.severity),
+        };
+        result.push_str(&format!(
+            "{}: {}{}\n",
+            self.reset_color(),
+            diagnostic.message,
+            self.reset_style()
+        ));
+
+        let mut marks = vec![self.mark(&diagnostic.span, true, None)];
+        for label in &diagnostic.labels {
+            marks.push(self.mark(&label.span, false, Some(&label.message)));
+        }
+        let gutter_width = marks
+            .iter()
+            .map(|mark| (mark.line_index + 1).to_string().len())
+            .max()
+            .unwrap();
+        let gutter_padding = " ".repeat(gutter_width);
+
+        // Marks are grouped by file, starting with the file of the primary
+        // span.
+        let mut file_ids: Vec<FileId> = Vec::new();
+        for mark in &marks {
+            if !file_ids.contains(&mark.file_id) {
+                file_ids.push(mark.file_id);
+            }
+        }
+        for (i, file_id) in file_ids.into_iter().enumerate() {
+            let file = self.source_map.get(file_id);
+            let first_mark = marks.iter().find(|mark| mark.file_id == file_id).unwrap();
+            result.push_str(&format!(
+                "{}{}{}{} {}:{}:{}\n",
+                gutter_padding,
+                self.gutter_color(),
+                if i == 0 { "-->" } else { ":::" },
+                self.reset_color(),
+                file.name,
+                first_mark.line_index + 1,
+                first_mark.start_column + 1
+            ));
+            result.push_str(&self.empty_gutter(&gutter_padding));
+            let mut line_indices: Vec<usize> = marks
+                .iter()
+                .filter(|mark| mark.file_id == file_id)
+                .map(|mark| mark.line_index)
+                .collect();
+            line_indices.sort();
+            line_indices.dedup();
+            let mut previous_line_index: Option<usize> = None;
+            for line_index in line_indices {
+                if previous_line_index.is_some_and(|previous| line_index > previous + 1) {
+                    result.push_str(&format!(
+                        "{}...{}\n",
+                        self.gutter_color(),
+                        self.reset_color()
+                    ));
+                }
+                previous_line_index = Some(line_index);
+                result.push_str(&format!(
+                    "{}{:>width$} |{} {}\n",
+                    self.gutter_color(),
+                    line_index + 1,
+                    self.reset_color(),
+                    file.text.get_line(line_index),
+                    width = gutter_width
+                ));
+                for mark in marks
+                    .iter()
+                    .filter(|mark| mark.file_id == file_id && mark.line_index == line_index)
+                {
+                    result.push_str(&self.stringify_mark(mark, &gutter_padding, severity_color));
+                }
+            }
+        }
+        if let Some(help) = &diagnostic.help {
+            result.push_str(&self.empty_gutter(&gutter_padding));
+            result.push_str(&format!(
+                "{} {}={} {}help{}: {}\n",
+                gutter_padding,
+                self.gutter_color(),
+                self.reset_color(),
+                self.style_bold(),
+                self.reset_style(),
+                help
+            ));
+        }
+        result
+    }
+
+    fn mark<'b>(&self, span: &TextSpan, is_primary: bool, message: Option<&'b str>) -> Mark<'b> {
+        let text = &self.source_map.get(span.file_id).text;
+        let line_index = text.line_index(span.start);
+        let start_column = text.column_index(span.start);
+        // Spans running over several lines are underlined to the end of their
+        // first line.
+        let end_column = if text.line_index(span.end) == line_index {
+            text.column_index(span.end)
+        } else {
+            text.get_line(line_index).chars().count()
+        };
+        Mark {
+            file_id: span.file_id,
+            line_index,
+            start_column,
+            end_column: end_column.max(start_column + 1),
+            is_primary,
+            message,
+        }
+    }
+
+    fn stringify_mark(&self, mark: &Mark, gutter_padding: &str, severity_color: &str) -> String {
+        let (underline, color) = if mark.is_primary {
+            ("^", severity_color)
+        } else {
+            ("-", self.gutter_color())
+        };
+        let mut result = format!(
+            "{} {}|{} {}{}{}",
+            gutter_padding,
+            self.gutter_color(),
+            self.reset_color(),
+            " ".repeat(mark.start_column),
+            color,
+            underline.repeat(mark.end_column - mark.start_column)
+        );
+        if let Some(message) = mark.message {
+            result.push_str(&format!(" {}", message));
+        }
+        result.push_str(&format!("{}{}\n", self.reset_color(), self.reset_style()));
+        result
+    }
+
+    fn empty_gutter(&self, gutter_padding: &str) -> String {
+        format!(
+            "{} {}|{}\n",
+            gutter_padding,
+            self.gutter_color(),
+            self.reset_color()
+        )
+    }
+
+    fn severity_color(&self, severity: Severity) -> &'static str {
+        if !self.use_colors {
+            return "";
+        }
+        match severity {
+            Severity::Error => color::Red.fg_str(),
+            Severity::Warning => color::Yellow.fg_str(),
+            Severity::Note => color::Cyan.fg_str(),
+        }
+    }
+
+    fn gutter_color(&self) -> &'static str {
+        if !self.use_colors {
+            return "";
+        }
+        color::LightBlue.fg_str()
+    }
+
+    fn reset_color(&self) -> String {
+        if !self.use_colors {
+            return String::new();
+        }
+        Fg(Reset).to_string()
+    }
+
+    fn style_bold(&self) -> String {
+        if !self.use_colors {
+            return String::new();
+        }
+        style::Bold.to_string()
+    }
+
+    fn reset_style(&self) -> String {
+        if !self.use_colors {
+            return String::new();
+        }
+        style::Reset.to_string()
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    diagnostics::{
+        json::{Json, SpanLocation},
+        Diagnostic, ErrorCode, Severity,
+    },
+    text::{source_map::SourceMap, span::TextSpan},
+};
+
+/// Builds a SARIF 2.1.0 log, the format code-scanning tools ingest. Every
+/// error code or lint that occurs becomes a rule; diagnostics become results
+/// whose labels are related locations and whose suggestions are fixes.
+pub struct SarifEmitter<'a> {
+    source_map: &'a SourceMap,
+}
+
+impl<'a> SarifEmitter<'a> {
+    const SCHEMA: &'static str = "https://json.schemastore.org/sarif-2.1.0.json";
+    const VERSION: &'static str = "2.1.0";
+
+    pub fn new(source_map: &'a SourceMap) -> Self {
+        Self { source_map }
+    }
+
+    pub fn print(&self, diagnostics: &[Diagnostic]) {
+        eprintln!("{}", self.emit(diagnostics));
+    }
+
+    pub fn emit(&self, diagnostics: &[Diagnostic]) -> Json {
+        let mut codes: Vec<ErrorCode> = diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.code)
+            .collect();
+        codes.sort();
+        codes.dedup();
+        let mut lints: Vec<&str> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code.is_none())
+            .filter_map(|diagnostic| diagnostic.lint)
+            .collect();
+        lints.sort();
+        lints.dedup();
+        let rules = codes
+            .iter()
+            .map(|code| {
+                Json::Object(vec![
+                    ("id", Json::string(code.to_string())),
+                    ("name", Json::string(format!("{:?}", code))),
+                ])
+            })
+            .chain(lints.iter().map(|lint| {
+                Json::Object(vec![
+                    ("id", Json::string(*lint)),
+                    ("name", Json::string(*lint)),
+                ])
+            }))
+            .collect();
+        let results = diagnostics
+            .iter()
+            .map(|diagnostic| self.emit_result(diagnostic))
+            .collect();
+        let driver = Json::Object(vec![
+            ("name", Json::string("nsharp")),
+            ("version", Json::string(env!("CARGO_PKG_VERSION"))),
+            ("rules", Json::Array(rules)),
+        ]);
+        let run = Json::Object(vec![
+            ("tool", Json::Object(vec![("driver", driver)])),
+            ("columnKind", Json::string("unicodeCodePoints")),
+            ("results", Json::Array(results)),
+        ]);
+        Json::Object(vec![
+            ("$schema", Json::string(Self::SCHEMA)),
+            ("version", Json::string(Self::VERSION)),
+            ("runs", Json::Array(vec![run])),
+        ])
+    }
+
+    fn emit_result(&self, diagnostic: &Diagnostic) -> Json {
+        let level = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        let message = match &diagnostic.help {
+            Some(help) => format!("{}\nhelp: {}", diagnostic.message, help),
+            None => diagnostic.message.clone(),
+        };
+        let mut result = Vec::new();
+        match (diagnostic.code, diagnostic.lint) {
+            (Some(code), _) => result.push(("ruleId", Json::string(code.to_string()))),
+            (None, Some(lint)) => result.push(("ruleId", Json::string(lint))),
+            (None, None) => {}
+        }
+        result.push(("level", Json::string(level)));
+        result.push(("message", Self::emit_message(&message)));
+        result.push((
+            "locations",
+            Json::Array(vec![Json::Object(vec![(
+                "physicalLocation",
+                self.emit_physical_location(&diagnostic.span),
+            )])]),
+        ));
+        if !diagnostic.labels.is_empty() {
+            let related_locations = diagnostic
+                .labels
+                .iter()
+                .enumerate()
+                .map(|(id, label)| {
+                    Json::Object(vec![
+                        ("id", Json::Number(id as i64)),
+                        ("physicalLocation", self.emit_physical_location(&label.span)),
+                        ("message", Self::emit_message(&label.message)),
+                    ])
+                })
+                .collect();
+            result.push(("relatedLocations", Json::Array(related_locations)));
+        }
+        if !diagnostic.suggestions.is_empty() {
+            let fixes = diagnostic
+                .suggestions
+                .iter()
+                .map(|suggestion| {
+                    let replacement = Json::Object(vec![
+                        ("deletedRegion", self.emit_region(&suggestion.span)),
+                        (
+                            "insertedContent",
+                            Json::Object(vec![("text", Json::string(&suggestion.replacement))]),
+                        ),
+                    ]);
+                    let change = Json::Object(vec![
+                        (
+                            "artifactLocation",
+                            self.emit_artifact_location(&suggestion.span),
+                        ),
+                        ("replacements", Json::Array(vec![replacement])),
+                    ]);
+                    Json::Object(vec![
+                        ("description", Self::emit_message(&suggestion.message)),
+                        ("artifactChanges", Json::Array(vec![change])),
+                    ])
+                })
+                .collect();
+            result.push(("fixes", Json::Array(fixes)));
+        }
+        Json::Object(result)
+    }
+
+    fn emit_message(text: &str) -> Json {
+        Json::Object(vec![("text", Json::string(text))])
+    }
+
+    fn emit_artifact_location(&self, span: &TextSpan) -> Json {
+        let file = self.source_map.get(span.file_id);
+        Json::Object(vec![("uri", Json::string(&file.name))])
+    }
+
+    fn emit_physical_location(&self, span: &TextSpan) -> Json {
+        Json::Object(vec![
+            ("artifactLocation", self.emit_artifact_location(span)),
+            ("region", self.emit_region(span)),
+        ])
+    }
+
+    fn emit_region(&self, span: &TextSpan) -> Json {
+        let location = SpanLocation::new(self.source_map, span);
+        Json::Object(vec![
+            ("startLine", Json::Number(location.start_line as i64)),
+            ("startColumn", Json::Number(location.start_column as i64)),
+            ("endLine", Json::Number(location.end_line as i64)),
+            ("endColumn", Json::Number(location.end_column as i64)),
+            ("byteOffset", Json::Number(location.start as i64)),
+            (
+                "byteLength",
+                Json::Number((location.end - location.start) as i64),
+            ),
+        ])
+    }
+}
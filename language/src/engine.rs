@@ -0,0 +1,570 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Debug, Display, Formatter},
+};
+
+use crate::{
+    compilation_unit::{CompilationUnit, FunctionBody, VariableIdx},
+    definitions::{
+        builtins::{NativeContext, NativeRegistry},
+        bytecode::Bytecode,
+        eval::{Strings, Value},
+        limits::{InterruptHandle, Limits},
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+        vm::VirtualMachine,
+        ItemKind,
+    },
+    diagnostics::{printer::DiagnosticsPrinter, Diagnostic, Severity},
+    lint::config::LintConfig,
+    text::{
+        source_map::{FileId, SourceMap},
+        span::TextSpan,
+    },
+    typecheck::Type,
+};
+
+/// A value passed between Rust and a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptValue {
+    Int(i64),
+    Bool(bool),
+    String(String),
+    Null,
+    Void,
+}
+
+impl ScriptValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ScriptValue::Int(_) => "int",
+            ScriptValue::Bool(_) => "bool",
+            ScriptValue::String(_) => "string",
+            ScriptValue::Null => "null",
+            ScriptValue::Void => "void",
+        }
+    }
+
+    /// Whether the value can be assigned to a variable of type `ty`.
+    pub fn matches(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (ScriptValue::Null, Type::Nullable(_)) => true,
+            (value, Type::Nullable(inner)) => value.matches(inner),
+            (ScriptValue::Int(_), Type::Int)
+            | (ScriptValue::Bool(_), Type::Bool)
+            | (ScriptValue::String(_), Type::String)
+            | (ScriptValue::Void, Type::Void) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for ScriptValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptValue::Int(value) => write!(f, "{}", value),
+            ScriptValue::Bool(value) => write!(f, "{}", value),
+            ScriptValue::String(value) => write!(f, "{:?}", value),
+            ScriptValue::Null => write!(f, "null"),
+            ScriptValue::Void => write!(f, "void"),
+        }
+    }
+}
+
+impl From<i64> for ScriptValue {
+    fn from(value: i64) -> Self {
+        ScriptValue::Int(value)
+    }
+}
+
+impl From<bool> for ScriptValue {
+    fn from(value: bool) -> Self {
+        ScriptValue::Bool(value)
+    }
+}
+
+impl From<&str> for ScriptValue {
+    fn from(value: &str) -> Self {
+        ScriptValue::String(value.to_string())
+    }
+}
+
+impl From<String> for ScriptValue {
+    fn from(value: String) -> Self {
+        ScriptValue::String(value)
+    }
+}
+
+impl From<()> for ScriptValue {
+    fn from(_: ()) -> Self {
+        ScriptValue::Void
+    }
+}
+
+impl<T: Into<ScriptValue>> From<Option<T>> for ScriptValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(ScriptValue::Null, Into::into)
+    }
+}
+
+/// A Rust type a program's value can be converted to.
+pub trait FromScriptValue: Sized {
+    /// The type expected, as error messages name it.
+    const EXPECTED: &'static str;
+
+    /// Converts the value, or gives it back if it has another type.
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue>;
+}
+
+impl FromScriptValue for ScriptValue {
+    const EXPECTED: &'static str = "any value";
+
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue> {
+        Ok(value)
+    }
+}
+
+impl FromScriptValue for i64 {
+    const EXPECTED: &'static str = "int";
+
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue> {
+        match value {
+            ScriptValue::Int(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+impl FromScriptValue for bool {
+    const EXPECTED: &'static str = "bool";
+
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue> {
+        match value {
+            ScriptValue::Bool(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+impl FromScriptValue for String {
+    const EXPECTED: &'static str = "string";
+
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue> {
+        match value {
+            ScriptValue::String(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+impl FromScriptValue for () {
+    const EXPECTED: &'static str = "void";
+
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue> {
+        match value {
+            ScriptValue::Void => Ok(()),
+            value => Err(value),
+        }
+    }
+}
+
+impl<T: FromScriptValue> FromScriptValue for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_script_value(value: ScriptValue) -> Result<Self, ScriptValue> {
+        match value {
+            ScriptValue::Null => Ok(None),
+            value => T::from_script_value(value).map(Some),
+        }
+    }
+}
+
+/// The errors of a program that failed to compile, and the sources they
+/// point into.
+pub struct CompileError {
+    pub diagnostics: Vec<Diagnostic>,
+    source_map: SourceMap,
+}
+
+impl CompileError {
+    /// The diagnostics as the compiler prints them, without colors.
+    pub fn render(&self) -> String {
+        let printer =
+            DiagnosticsPrinter::new(&self.source_map, &self.diagnostics).with_colors(false);
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| printer.stringify_diagnostic(diagnostic))
+            .collect()
+    }
+}
+
+impl Debug for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompileError")
+            .field("diagnostics", &self.diagnostics)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    Compile(CompileError),
+    Runtime(RuntimeError),
+    UnknownFunction(String),
+    /// The arguments of a call don't match the function's parameters.
+    Arguments {
+        function: String,
+        expected: Vec<Type>,
+        found: Vec<ScriptValue>,
+    },
+    /// A function returned a value that can't be converted to the type the
+    /// caller asked for.
+    ReturnValue {
+        function: String,
+        expected: &'static str,
+        found: ScriptValue,
+    },
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Compile(error) => {
+                let errors = error
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                    .count();
+                write!(f, "could not compile the program due to {} errors", errors)
+            }
+            EngineError::Runtime(error) => write!(f, "{}", error),
+            EngineError::UnknownFunction(name) => write!(f, "no function named '{}'", name),
+            EngineError::Arguments {
+                function,
+                expected,
+                found,
+            } => {
+                let expected: Vec<String> = expected.iter().map(ToString::to_string).collect();
+                let found: Vec<&str> = found.iter().map(ScriptValue::type_name).collect();
+                write!(
+                    f,
+                    "'{}' takes ({}), but was given ({})",
+                    function,
+                    expected.join(", "),
+                    found.join(", ")
+                )
+            }
+            EngineError::ReturnValue {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' returned {}, which is not {}",
+                function, found, expected
+            ),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+impl From<RuntimeError> for EngineError {
+    fn from(error: RuntimeError) -> Self {
+        EngineError::Runtime(error)
+    }
+}
+
+/// Compiles programs for a host to run, with the natives and limits the
+/// host chose.
+///
+/// ```
+/// use nsharp_compiler::engine::Engine;
+///
+/// let source = "func add(a: int, b: int) -> int { return a + b; }";
+/// let mut program = Engine::new().compile(source).unwrap();
+/// let sum: i64 = program.call("add", &[1.into(), 2.into()]).unwrap();
+/// assert_eq!(sum, 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Engine {
+    natives: NativeRegistry,
+    limits: Limits,
+    lint_config: LintConfig,
+}
+
+impl Engine {
+    /// An engine with the builtin natives, no limits and the default lint
+    /// levels.
+    pub fn new() -> Self {
+        Self {
+            natives: NativeRegistry::with_builtins(),
+            limits: Limits::new(),
+            lint_config: LintConfig::new(),
+        }
+    }
+
+    /// The limits every run of the compiled programs is held to.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_lint_config(mut self, lint_config: LintConfig) -> Self {
+        self.lint_config = lint_config;
+        self
+    }
+
+    /// The natives programs compiled from now on can call.
+    pub fn natives_mut(&mut self) -> &mut NativeRegistry {
+        &mut self.natives
+    }
+
+    /// Adds a native function, see `NativeRegistry::register`.
+    pub fn register(
+        &mut self,
+        name: &str,
+        parameters: &[(&str, Type)],
+        return_type: Type,
+        function: impl Fn(&mut NativeContext, &[Value]) -> Result<Value, RuntimeErrorKind> + 'static,
+    ) -> &mut Self {
+        self.natives
+            .register(name, parameters, return_type, function);
+        self
+    }
+
+    pub fn compile(&self, source: &str) -> Result<Program, EngineError> {
+        self.compile_named("<script>", source)
+    }
+
+    /// Compiles like `compile`, naming the source `name` in diagnostics.
+    pub fn compile_named(&self, name: &str, source: &str) -> Result<Program, EngineError> {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(name.to_string(), source.to_string());
+        match CompilationUnit::compile_files_with_natives(
+            &source_map,
+            &[file_id],
+            &self.lint_config,
+            &self.natives,
+        ) {
+            Ok(compilation_unit) => {
+                let warnings = compilation_unit
+                    .diagnostics_bag
+                    .borrow()
+                    .diagnostics
+                    .clone();
+                let strings = compilation_unit.strings().clone();
+                let bytecode = Bytecode::compile(
+                    &compilation_unit.ast,
+                    &compilation_unit.global_scope,
+                    self.limits,
+                );
+                Ok(Program {
+                    source_map,
+                    file_id,
+                    compilation_unit,
+                    bytecode,
+                    warnings,
+                    limits: self.limits,
+                    interrupt_handle: InterruptHandle::new(),
+                    globals: None,
+                    strings,
+                })
+            }
+            Err(diagnostics_bag) => {
+                let diagnostics = diagnostics_bag.borrow().diagnostics.clone();
+                Err(EngineError::Compile(CompileError {
+                    diagnostics,
+                    source_map,
+                }))
+            }
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A compiled program, whose functions can be called any number of times.
+///
+/// The top level runs once, before the first call, and the values of the
+/// globals carry over from one call to the next.
+pub struct Program {
+    source_map: SourceMap,
+    file_id: FileId,
+    compilation_unit: CompilationUnit,
+    /// The program compiled with `limits`.
+    bytecode: Bytecode,
+    warnings: Vec<Diagnostic>,
+    limits: Limits,
+    interrupt_handle: InterruptHandle,
+    /// The values of the `let` globals once the top level has run.
+    globals: Option<HashMap<VariableIdx, Value>>,
+    /// The strings `globals` refer to.
+    strings: Strings,
+}
+
+impl Debug for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Program")
+            .field("warnings", &self.warnings)
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Program {
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// The warnings compiling the program reported.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// A handle to interrupt the program from another thread, which applies
+    /// to every run.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt_handle.clone()
+    }
+
+    /// The error as the compiler prints it, pointing into the source,
+    /// without colors.
+    pub fn render_error(&self, error: &RuntimeError) -> String {
+        let diagnostics = [error.to_diagnostic()];
+        DiagnosticsPrinter::new(&self.source_map, &diagnostics)
+            .with_colors(false)
+            .stringify_diagnostic(&diagnostics[0])
+    }
+
+    /// Runs the top level, starting the globals over.
+    pub fn run(&mut self) -> Result<(), EngineError> {
+        self.globals = None;
+        let unit = &self.compilation_unit;
+        let mut vm = VirtualMachine::new(&self.bytecode, &unit.global_scope)
+            .with_interrupt_handle(self.interrupt_handle.clone());
+        vm.strings = unit.strings().clone();
+        vm.run()?;
+        self.globals = Some(
+            unit.global_scope
+                .global_variables
+                .iter()
+                .filter_map(|variable_idx| Some((*variable_idx, vm.variable(*variable_idx)?)))
+                .collect(),
+        );
+        self.strings = std::mem::take(&mut vm.strings);
+        Ok(())
+    }
+
+    /// The value of a global, once the top level has run.
+    pub fn global(&self, name: &str) -> Option<ScriptValue> {
+        let global_scope = &self.compilation_unit.global_scope;
+        let variable_idx = global_scope.lookup_global_variable(name)?;
+        let value = match global_scope.constants.get(&variable_idx) {
+            Some(value) => {
+                return Some(self.to_script_value(*value, self.compilation_unit.strings()))
+            }
+            None => self.globals.as_ref()?.get(&variable_idx)?,
+        };
+        Some(self.to_script_value(*value, &self.strings))
+    }
+
+    /// Calls a function with `arguments`, converting what it returns to
+    /// `R`. Runs the top level first if it hasn't run yet.
+    pub fn call<R: FromScriptValue>(
+        &mut self,
+        name: &str,
+        arguments: &[ScriptValue],
+    ) -> Result<R, EngineError> {
+        let global_scope = &self.compilation_unit.global_scope;
+        let function_idx = global_scope
+            .lookup_function(name)
+            .ok_or_else(|| EngineError::UnknownFunction(name.to_string()))?;
+        let function = &global_scope.functions[function_idx];
+        let expected: Vec<Type> = function
+            .parameters
+            .iter()
+            .map(|variable_idx| global_scope.variables[*variable_idx].ty.clone())
+            .collect();
+        if expected.len() != arguments.len()
+            || !arguments
+                .iter()
+                .zip(&expected)
+                .all(|(argument, ty)| argument.matches(ty))
+        {
+            return Err(EngineError::Arguments {
+                function: name.to_string(),
+                expected,
+                found: arguments.to_vec(),
+            });
+        }
+        // Runtime errors point at the function, as if called from there.
+        let span = match &function.body {
+            FunctionBody::Declared(_) => {
+                self.compilation_unit
+                    .ast
+                    .items
+                    .iter()
+                    .find_map(|item| match &item.kind {
+                        ItemKind::Function(func_decl) if func_decl.idx == function_idx => {
+                            Some(func_decl.identifier.span)
+                        }
+                        _ => None,
+                    })
+            }
+            FunctionBody::Native(_) => None,
+        }
+        .unwrap_or(TextSpan::new(self.file_id, 0, 0));
+
+        if self.globals.is_none() {
+            self.run()?;
+        }
+        let globals = self.globals.as_mut().unwrap();
+        let mut vm = VirtualMachine::new(&self.bytecode, &self.compilation_unit.global_scope)
+            .with_interrupt_handle(self.interrupt_handle.clone());
+        vm.strings = std::mem::take(&mut self.strings);
+        for (variable_idx, value) in globals.iter() {
+            vm.set_variable(*variable_idx, *value);
+        }
+        let values: Vec<Value> = arguments
+            .iter()
+            .map(|argument| match argument {
+                ScriptValue::Int(value) => Value::Number(*value),
+                ScriptValue::Bool(value) => Value::Boolean(*value),
+                ScriptValue::String(value) => Value::String(vm.strings.intern(value)),
+                ScriptValue::Null => Value::Null,
+                ScriptValue::Void => Value::Void,
+            })
+            .collect();
+        let result = vm.call_function(function_idx, &values, span);
+        // The globals keep what the call did to them, even if it failed.
+        for (variable_idx, value) in globals.iter_mut() {
+            if let Some(new_value) = vm.variable(*variable_idx) {
+                *value = new_value;
+            }
+        }
+        self.strings = std::mem::take(&mut vm.strings);
+        drop(vm);
+
+        let value = self.to_script_value(result?, &self.strings);
+        R::from_script_value(value).map_err(|found| EngineError::ReturnValue {
+            function: name.to_string(),
+            expected: R::EXPECTED,
+            found,
+        })
+    }
+
+    fn to_script_value(&self, value: Value, strings: &Strings) -> ScriptValue {
+        match value {
+            Value::Number(value) => ScriptValue::Int(value),
+            Value::Boolean(value) => ScriptValue::Bool(value),
+            Value::String(idx) => ScriptValue::String(strings.get(idx).to_string()),
+            Value::Null => ScriptValue::Null,
+            Value::Void => ScriptValue::Void,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod compilation_unit;
+pub mod definitions;
+pub mod diagnostics;
+pub mod engine;
+pub mod lint;
+pub mod repl;
+pub mod text;
+pub mod typecheck;
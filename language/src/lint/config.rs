@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::lint::{Lint, LintLevel};
+
+#[derive(Debug)]
+pub enum LintConfigError {
+    Io(PathBuf, io::Error),
+    /// Every malformed line of the `[lints]` section, counted from one.
+    Syntax(PathBuf, Vec<(usize, String)>),
+}
+
+impl Display for LintConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintConfigError::Io(path, error) => {
+                write!(f, "could not read {}: {}", path.display(), error)
+            }
+            LintConfigError::Syntax(path, errors) => {
+                for (index, (line, message)) in errors.iter().enumerate() {
+                    if index != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}:{}: {}", path.display(), line, message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The lint levels of a project. Lints it doesn't mention keep their
+/// default level.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<Lint, LintLevel>,
+}
+
+impl LintConfig {
+    /// The file, next to the sources or in any directory above them, that
+    /// configures a project.
+    pub const FILE_NAME: &'static str = "nsharp.toml";
+
+    pub fn new() -> Self {
+        Self {
+            levels: HashMap::new(),
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+
+    pub fn set_level(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    /// The configuration of the project `source_file` belongs to, or the
+    /// default one if there is no `nsharp.toml` above it.
+    pub fn for_source_file(source_file: &Path) -> Result<Self, LintConfigError> {
+        let directory = source_file.parent().unwrap_or(Path::new(""));
+        let directory = directory.canonicalize().unwrap_or(directory.to_path_buf());
+        for directory in directory.ancestors() {
+            let path = directory.join(Self::FILE_NAME);
+            if path.is_file() {
+                return Self::load(&path);
+            }
+        }
+        Ok(Self::new())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, LintConfigError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| LintConfigError::Io(path.to_path_buf(), error))?;
+        Self::parse(&text).map_err(|errors| LintConfigError::Syntax(path.to_path_buf(), errors))
+    }
+
+    /// Reads the `[lints]` table of an `nsharp.toml`, where each key is a
+    /// lint and each value a quoted level:
+    ///
+    /// ```toml
+    /// [lints]
+    /// unused_variables = "allow"
+    /// shadowing = "deny"
+    /// ```
+    ///
+    /// Other tables are skipped. Every malformed line of the table, or
+    /// table header, is an error carrying its one-based line.
+    pub fn parse(text: &str) -> Result<Self, Vec<(usize, String)>> {
+        let mut config = Self::new();
+        let mut errors = Vec::new();
+        let mut configured_on: HashMap<Lint, usize> = HashMap::new();
+        let mut in_lints = false;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(table) = line.strip_prefix('[') {
+                let Some(table) = table.strip_suffix(']') else {
+                    errors.push((line_number, format!("unclosed table header '{}'", line)));
+                    in_lints = false;
+                    continue;
+                };
+                in_lints = table.trim() == "lints";
+                continue;
+            }
+            if !in_lints {
+                continue;
+            }
+            match Self::parse_entry(line) {
+                Ok((lint, level)) => {
+                    let first_line = *configured_on.entry(lint).or_insert(line_number);
+                    if first_line != line_number {
+                        errors.push((
+                            line_number,
+                            format!(
+                                "lint '{}' is already configured on line {}",
+                                lint.name(),
+                                first_line
+                            ),
+                        ));
+                    }
+                    config.set_level(lint, level);
+                }
+                Err(message) => errors.push((line_number, message)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses one `lint = "level"` line of the `[lints]` table.
+    fn parse_entry(line: &str) -> Result<(Lint, LintLevel), String> {
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("expected 'lint = \"level\"', found '{}'", line));
+        };
+        let key = key.trim();
+        let Some(lint) = Lint::from_name(key) else {
+            return Err(format!("unknown lint '{}'", key));
+        };
+        let value = value.trim();
+        let level = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .and_then(LintLevel::from_name)
+            .ok_or_else(|| {
+                format!(
+                    "invalid level {} for lint '{}', expected \"allow\", \"warn\" or \"deny\"",
+                    value, key
+                )
+            })?;
+        Ok((lint, level))
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    compilation_unit::GlobalScope,
+    definitions::{
+        visitor::ASTVisitor, Ast, BoolExpr, Expr, ExprId, ExprKind, FuncDeclaration, IfExpr,
+        ItemId, LetStmt, NullExpr, NumberExpr, Stmt, StringPart, UnaryExpr, VarExpr,
+    },
+    lint::{Lint, LintDiagnostic, LintPass},
+    text::span::TextSpan,
+};
+
+/// An `if` whose condition is built from literals and consts only, so the
+/// same branch is taken every time.
+pub struct ConstantCondition<'a> {
+    global_scope: &'a GlobalScope,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> ConstantCondition<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self {
+            global_scope,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn is_constant(&self, ast: &Ast, expr_id: ExprId) -> bool {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Number(_) | ExprKind::Boolean(_) | ExprKind::Null(_) => true,
+            ExprKind::String(string_expr) => string_expr
+                .parts
+                .iter()
+                .all(|part| matches!(part, StringPart::Literal(_))),
+            ExprKind::Variable(var_expr) => {
+                self.global_scope.variables[var_expr.variable_idx].is_const
+            }
+            ExprKind::Unary(unary_expr) => self.is_constant(ast, unary_expr.operand),
+            ExprKind::Binary(binary_expr) => {
+                self.is_constant(ast, binary_expr.left) && self.is_constant(ast, binary_expr.right)
+            }
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                self.is_constant(ast, parenthesized_expr.inner)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl LintPass for ConstantCondition<'_> {
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for ConstantCondition<'_> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        self.visit_body(ast, &func_decl.body);
+    }
+
+    fn visit_if_expression(&mut self, ast: &mut Ast, if_expr: &IfExpr, _expr: &Expr) {
+        if self.is_constant(ast, if_expr.condition) {
+            let condition = ast.query_expr(if_expr.condition);
+            let message = match &condition.kind {
+                ExprKind::Boolean(bool_expr) => {
+                    format!("'if' condition is always {}", bool_expr.value)
+                }
+                _ => "'if' condition is constant".to_string(),
+            };
+            self.diagnostics.push(
+                LintDiagnostic::new(Lint::ConstantCondition, message, condition.span(ast))
+                    .with_help("the same branch is taken every time".to_string()),
+            );
+        }
+        self.visit_expression(ast, if_expr.condition);
+        self.visit_body(ast, &if_expr.then_branch);
+        if let Some(else_branch) = &if_expr.else_branch {
+            self.visit_body(ast, &else_branch.body);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, _var_expr: &VarExpr, _expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    definitions::{
+        visitor::ASTVisitor, Ast, BoolExpr, Expr, ExprId, ExprKind, FuncDeclaration, ItemId,
+        LetStmt, NullExpr, NumberExpr, ReturnStmt, Stmt, UnaryExpr, VarExpr, WhileStmt,
+    },
+    lint::{Lint, LintDiagnostic, LintPass},
+    text::span::TextSpan,
+};
+
+/// A `while true` loop without a way out. The language has no `break`, so
+/// only a `return` somewhere in the body can end the loop.
+#[derive(Default)]
+pub struct InfiniteLoop {
+    /// The `return` statements visited so far.
+    returns: usize,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl InfiniteLoop {
+    pub fn new() -> Self {
+        Self {
+            returns: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn is_true_literal(ast: &Ast, expr_id: ExprId) -> bool {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Boolean(bool_expr) => bool_expr.value,
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                Self::is_true_literal(ast, parenthesized_expr.inner)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl LintPass for InfiniteLoop {
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for InfiniteLoop {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        self.visit_body(ast, &func_decl.body);
+    }
+
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_stmt: &WhileStmt) {
+        self.visit_expression(ast, while_stmt.condition);
+        let returns = self.returns;
+        self.visit_body(ast, &while_stmt.body);
+        if Self::is_true_literal(ast, while_stmt.condition) && self.returns == returns {
+            let condition_span = ast.query_expr(while_stmt.condition).span(ast);
+            self.diagnostics.push(
+                LintDiagnostic::new(
+                    Lint::InfiniteLoop,
+                    "'while true' loop never exits".to_string(),
+                    while_stmt.while_keyword.span.to(condition_span),
+                )
+                .with_help("add a 'return' to leave the loop".to_string()),
+            );
+        }
+    }
+
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_stmt: &ReturnStmt) {
+        self.returns += 1;
+        if let Some(expr) = &return_stmt.return_value {
+            self.visit_expression(ast, *expr);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, _var_expr: &VarExpr, _expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
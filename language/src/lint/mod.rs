@@ -0,0 +1,314 @@
+pub mod config;
+pub mod constant_condition;
+pub mod infinite_loop;
+pub mod self_assignment;
+pub mod shadowing;
+pub mod unused;
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    compilation_unit::GlobalScope,
+    definitions::{visitor::ASTVisitor, Ast, Attribute, ItemId},
+    diagnostics::{Diagnostic, DiagnosticsBagCell, ErrorCode, Severity},
+    lint::{
+        config::LintConfig,
+        constant_condition::ConstantCondition,
+        infinite_loop::InfiniteLoop,
+        self_assignment::SelfAssignment,
+        shadowing::Shadowing,
+        unused::{UnusedFunctions, UnusedParameters, UnusedVariables},
+    },
+    text::{similarity::closest_matches, source_map::SourceMap, span::TextSpan},
+};
+
+/// What happens to the diagnostics of a lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    /// Reported as an error, so the program doesn't compile.
+    Deny,
+}
+
+impl LintLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        }
+    }
+}
+
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A check for code that compiles but is likely a mistake. Unlike error
+/// codes, lints are referred to by name, e.g. `#[allow(unused_variables)]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    UnusedVariables,
+    UnusedParameters,
+    UnusedFunctions,
+    Shadowing,
+    InfiniteLoop,
+    ConstantCondition,
+    SelfAssignment,
+    UnreachableCode,
+}
+
+impl Lint {
+    pub fn all() -> &'static [Lint] {
+        &[
+            Lint::UnusedVariables,
+            Lint::UnusedParameters,
+            Lint::UnusedFunctions,
+            Lint::Shadowing,
+            Lint::InfiniteLoop,
+            Lint::ConstantCondition,
+            Lint::SelfAssignment,
+            Lint::UnreachableCode,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedParameters => "unused_parameters",
+            Lint::UnusedFunctions => "unused_functions",
+            Lint::Shadowing => "shadowing",
+            Lint::InfiniteLoop => "infinite_loop",
+            Lint::ConstantCondition => "constant_condition",
+            Lint::SelfAssignment => "self_assignment",
+            Lint::UnreachableCode => "unreachable_code",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().iter().find(|lint| lint.name() == name).copied()
+    }
+
+    pub fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A diagnostic of a lint pass. Its severity is decided by the driver from
+/// the lint's level in the item it was reported in.
+#[derive(Debug, Clone)]
+pub struct LintDiagnostic {
+    pub lint: Lint,
+    /// The item whose attributes apply; the item being visited if `None`.
+    pub item: Option<ItemId>,
+    pub diagnostic: Diagnostic,
+}
+
+impl LintDiagnostic {
+    pub fn new(lint: Lint, message: String, span: TextSpan) -> Self {
+        Self {
+            lint,
+            item: None,
+            diagnostic: Diagnostic::warning(message, span),
+        }
+    }
+
+    /// Gives the diagnostic an error code, for lints with an entry in the
+    /// error index.
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.diagnostic.code = Some(code);
+        self
+    }
+
+    pub fn in_item(mut self, item: ItemId) -> Self {
+        self.item = Some(item);
+        self
+    }
+
+    pub fn with_label(mut self, span: TextSpan, message: String) -> Self {
+        self.diagnostic = self.diagnostic.with_label(span, message);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.diagnostic = self.diagnostic.with_help(help);
+        self
+    }
+
+    pub fn with_suggestion(mut self, span: TextSpan, replacement: String, message: String) -> Self {
+        self.diagnostic = self.diagnostic.with_suggestion(span, replacement, message);
+        self
+    }
+}
+
+/// A lint, run by the `LintDriver` over one item at a time.
+pub trait LintPass: ASTVisitor {
+    /// Called once every item was visited, for lints that need the whole
+    /// program. Diagnostics reported here must name their item.
+    fn finish(&mut self, _ast: &mut Ast) {}
+
+    /// The diagnostics reported since the last call.
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic>;
+}
+
+/// Runs the registered lint passes over a program that passed every other
+/// check, and reports their diagnostics at the configured levels. A level
+/// set by an item's `#[allow(..)]`, `#[warn(..)]` or `#[deny(..)]`
+/// attribute overrides the project's.
+pub struct LintDriver<'a> {
+    config: &'a LintConfig,
+    source_map: &'a SourceMap,
+    passes: Vec<Box<dyn LintPass + 'a>>,
+    /// Diagnostics found outside of the passes, e.g. unreachable code found
+    /// by flow analysis.
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> LintDriver<'a> {
+    pub fn new(config: &'a LintConfig, source_map: &'a SourceMap) -> Self {
+        Self {
+            config,
+            source_map,
+            passes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// A driver with every lint of the compiler registered.
+    pub fn with_builtin_lints(
+        config: &'a LintConfig,
+        global_scope: &'a GlobalScope,
+        source_map: &'a SourceMap,
+    ) -> Self {
+        let mut driver = Self::new(config, source_map);
+        driver.register(Box::new(UnusedVariables::new(global_scope)));
+        driver.register(Box::new(UnusedParameters::new(global_scope)));
+        driver.register(Box::new(UnusedFunctions::new(global_scope)));
+        driver.register(Box::new(Shadowing::new(global_scope)));
+        driver.register(Box::new(InfiniteLoop::new()));
+        driver.register(Box::new(ConstantCondition::new(global_scope)));
+        driver.register(Box::new(SelfAssignment::new(global_scope)));
+        driver
+    }
+
+    pub fn register(&mut self, pass: Box<dyn LintPass + 'a>) {
+        self.passes.push(pass);
+    }
+
+    /// Reports diagnostics of a lint checked by an earlier phase along with
+    /// those of the passes. Each must name its item.
+    pub fn with_diagnostics(mut self, lint_diagnostics: Vec<LintDiagnostic>) -> Self {
+        self.diagnostics.extend(lint_diagnostics);
+        self
+    }
+
+    pub fn run(&mut self, ast: &mut Ast, diagnostics: &DiagnosticsBagCell) {
+        let item_levels = self.item_levels(ast, diagnostics);
+        let item_ids = ast.items.cloned_indices();
+        let mut lint_diagnostics = std::mem::take(&mut self.diagnostics);
+        for pass in &mut self.passes {
+            for item_id in &item_ids {
+                pass.visit_item(ast, *item_id);
+                lint_diagnostics.extend(pass.take_diagnostics().into_iter().map(
+                    |mut lint_diagnostic| {
+                        lint_diagnostic.item.get_or_insert(*item_id);
+                        lint_diagnostic
+                    },
+                ));
+            }
+            pass.finish(ast);
+            lint_diagnostics.extend(pass.take_diagnostics());
+        }
+
+        let mut diagnostics = diagnostics.borrow_mut();
+        for lint_diagnostic in lint_diagnostics {
+            let level = lint_diagnostic
+                .item
+                .and_then(|item_id| item_levels.get(&item_id))
+                .and_then(|levels| levels.get(&lint_diagnostic.lint))
+                .copied()
+                .unwrap_or_else(|| self.config.level(lint_diagnostic.lint));
+            let mut diagnostic = lint_diagnostic
+                .diagnostic
+                .with_lint(lint_diagnostic.lint.name());
+            diagnostic.severity = match level {
+                LintLevel::Allow => continue,
+                LintLevel::Warn => Severity::Warning,
+                LintLevel::Deny => Severity::Error,
+            };
+            diagnostics.report(diagnostic);
+        }
+    }
+
+    /// The levels set by the attributes of each item. Malformed attributes
+    /// are reported and otherwise ignored.
+    fn item_levels(
+        &self,
+        ast: &Ast,
+        diagnostics: &DiagnosticsBagCell,
+    ) -> HashMap<ItemId, HashMap<Lint, LintLevel>> {
+        let mut item_levels = HashMap::new();
+        for (item_id, item) in ast.items.indexed_iter() {
+            let levels: HashMap<Lint, LintLevel> = item
+                .attributes
+                .iter()
+                .flat_map(|attribute| self.attribute_levels(attribute, diagnostics))
+                .collect();
+            if !levels.is_empty() {
+                item_levels.insert(item_id, levels);
+            }
+        }
+        item_levels
+    }
+
+    fn attribute_levels(
+        &self,
+        attribute: &Attribute,
+        diagnostics: &DiagnosticsBagCell,
+    ) -> Vec<(Lint, LintLevel)> {
+        let name = attribute.name.span.literal(self.source_map);
+        let Some(level) = LintLevel::from_name(name) else {
+            diagnostics
+                .borrow_mut()
+                .report_unknown_attribute(name, &attribute.name.span);
+            return Vec::new();
+        };
+        let mut levels = Vec::new();
+        for argument in &attribute.arguments {
+            let lint_name = argument.span.literal(self.source_map);
+            match Lint::from_name(lint_name) {
+                Some(lint) => levels.push((lint, level)),
+                None => {
+                    let names = Lint::all().iter().map(|lint| lint.name());
+                    diagnostics.borrow_mut().report_unknown_lint(
+                        lint_name,
+                        &argument.span,
+                        &closest_matches(lint_name, names, 3),
+                    );
+                }
+            }
+        }
+        levels
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{
+    compilation_unit::GlobalScope,
+    definitions::{
+        visitor::ASTVisitor, AssignExpr, Ast, BoolExpr, Expr, ExprId, ExprKind, FuncDeclaration,
+        ItemId, LetStmt, NullExpr, NumberExpr, Stmt, UnaryExpr, VarExpr,
+    },
+    lint::{Lint, LintDiagnostic, LintPass},
+    text::span::TextSpan,
+};
+
+/// An assignment of a variable to itself, `x = x`, which has no effect.
+pub struct SelfAssignment<'a> {
+    global_scope: &'a GlobalScope,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> SelfAssignment<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self {
+            global_scope,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn is_variable(ast: &Ast, expr_id: ExprId, assign_expr: &AssignExpr) -> bool {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Variable(var_expr) => var_expr.variable_idx == assign_expr.variable_idx,
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                Self::is_variable(ast, parenthesized_expr.inner, assign_expr)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl LintPass for SelfAssignment<'_> {
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for SelfAssignment<'_> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        self.visit_body(ast, &func_decl.body);
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        ast: &mut Ast,
+        assign_expr: &AssignExpr,
+        expr: &Expr,
+    ) {
+        self.visit_expression(ast, assign_expr.expression);
+        if Self::is_variable(ast, assign_expr.expression, assign_expr) {
+            let name = &self.global_scope.variables[assign_expr.variable_idx].name;
+            self.diagnostics.push(
+                LintDiagnostic::new(
+                    Lint::SelfAssignment,
+                    format!("Variable '{}' is assigned to itself", name),
+                    expr.span(ast),
+                )
+                .with_help("the assignment has no effect".to_string()),
+            );
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, _var_expr: &VarExpr, _expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::{
+    compilation_unit::GlobalScope,
+    definitions::{
+        visitor::ASTVisitor, Ast, BlockExpr, Body, BoolExpr, ConstDeclaration, Expr,
+        FuncDeclaration, ItemId, LetStmt, NullExpr, NumberExpr, Stmt, UnaryExpr, VarExpr,
+    },
+    lint::{Lint, LintDiagnostic, LintPass},
+    text::span::TextSpan,
+};
+
+/// A `let` that hides a variable of an enclosing scope: a parameter, a
+/// global or a local of an outer body or block. Redeclaring a variable in
+/// the same scope, as in `let x = x + 1`, is not reported.
+pub struct Shadowing<'a> {
+    global_scope: &'a GlobalScope,
+    /// Where each visible global was declared.
+    globals: HashMap<String, TextSpan>,
+    scopes: Vec<HashMap<String, TextSpan>>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> Shadowing<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self {
+            global_scope,
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn lookup_outer(&self, name: &str) -> Option<TextSpan> {
+        let (current, outer) = self.scopes.split_last()?;
+        if current.contains_key(name) {
+            return None;
+        }
+        outer
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+    }
+}
+
+impl LintPass for Shadowing<'_> {
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for Shadowing<'_> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        let parameters = func_decl
+            .parameters
+            .iter()
+            .zip(&self.global_scope.functions[func_decl.idx].parameters)
+            .map(|(parameter, variable_idx)| {
+                (
+                    self.global_scope.variables[*variable_idx].name.clone(),
+                    parameter.identifier.span,
+                )
+            })
+            .collect();
+        self.scopes.push(parameters);
+        self.visit_body(ast, &func_decl.body);
+        self.scopes.pop();
+    }
+
+    fn visit_const_decl(&mut self, ast: &mut Ast, const_decl: &ConstDeclaration, _item_id: ItemId) {
+        self.visit_expression(ast, const_decl.initializer);
+        let name = &self.global_scope.variables[const_decl.variable_idx].name;
+        self.globals
+            .insert(name.clone(), const_decl.identifier.span);
+    }
+
+    fn visit_body(&mut self, ast: &mut Ast, body: &Body) {
+        self.scopes.push(HashMap::new());
+        self.visit_body_default(ast, body);
+        self.scopes.pop();
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, _expr: &Expr) {
+        self.scopes.push(HashMap::new());
+        for stmt in &block_expr.stmts {
+            self.visit_statement(ast, *stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+        let name = self.global_scope.variables[let_stmt.variable_idx]
+            .name
+            .clone();
+        let span = let_stmt.identifier.span;
+        if let Some(shadowed) = self.lookup_outer(&name) {
+            if !name.starts_with('_') {
+                self.diagnostics.push(
+                    LintDiagnostic::new(
+                        Lint::Shadowing,
+                        format!("Variable '{}' shadows a variable of an outer scope", name),
+                        span,
+                    )
+                    .with_label(shadowed, format!("shadowed '{}' declared here", name))
+                    .with_help("consider renaming one of the variables".to_string()),
+                );
+            }
+        }
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name, span),
+            None => self.globals.insert(name, span),
+        };
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, _var_expr: &VarExpr, _expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
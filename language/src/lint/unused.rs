@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use crate::{
+    compilation_unit::{FunctionIdx, GlobalScope, VariableIdx},
+    definitions::{
+        lexer::Token, visitor::ASTVisitor, Ast, BoolExpr, CallExpr, Expr, FuncDeclaration, ItemId,
+        LetStmt, NullExpr, NumberExpr, Stmt, UnaryExpr, VarExpr,
+    },
+    lint::{Lint, LintDiagnostic, LintPass},
+    text::span::TextSpan,
+};
+
+/// Names starting with an underscore are unused on purpose.
+fn is_intentionally_unused(name: &str) -> bool {
+    name.starts_with('_')
+}
+
+fn unused_diagnostic(lint: Lint, kind: &str, name: &str, span: TextSpan) -> LintDiagnostic {
+    LintDiagnostic::new(lint, format!("Unused {} '{}'", kind, name), span)
+        .with_help(format!(
+            "if this is intentional, prefix it with an underscore: '_{}'",
+            name
+        ))
+        .with_suggestion(span, format!("_{}", name), format!("rename to '_{}'", name))
+}
+
+/// `let` variables, local or global, that are never read. Assigning to a
+/// variable doesn't count as using it.
+pub struct UnusedVariables<'a> {
+    global_scope: &'a GlobalScope,
+    item: Option<ItemId>,
+    declarations: Vec<(VariableIdx, Token, ItemId)>,
+    used: HashSet<VariableIdx>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> UnusedVariables<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self {
+            global_scope,
+            item: None,
+            declarations: Vec::new(),
+            used: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl LintPass for UnusedVariables<'_> {
+    fn finish(&mut self, _ast: &mut Ast) {
+        for (variable_idx, identifier, item_id) in &self.declarations {
+            let name = &self.global_scope.variables[*variable_idx].name;
+            if self.used.contains(variable_idx) || is_intentionally_unused(name) {
+                continue;
+            }
+            self.diagnostics.push(
+                unused_diagnostic(Lint::UnusedVariables, "variable", name, identifier.span)
+                    .in_item(*item_id),
+            );
+        }
+    }
+
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for UnusedVariables<'_> {
+    fn visit_item(&mut self, ast: &mut Ast, item: ItemId) {
+        self.item = Some(item);
+        self.visit_item_default(ast, item);
+    }
+
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        self.visit_body(ast, &func_decl.body);
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+        self.declarations.push((
+            let_stmt.variable_idx,
+            let_stmt.identifier.clone(),
+            self.item.unwrap(),
+        ));
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, var_expr: &VarExpr, _expr: &Expr) {
+        self.used.insert(var_expr.variable_idx);
+    }
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
+
+/// Function parameters that are never read. Each function is reported as
+/// soon as it was visited, since parameters are only visible inside it.
+pub struct UnusedParameters<'a> {
+    global_scope: &'a GlobalScope,
+    used: HashSet<VariableIdx>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> UnusedParameters<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self {
+            global_scope,
+            used: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl LintPass for UnusedParameters<'_> {
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for UnusedParameters<'_> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        self.visit_body(ast, &func_decl.body);
+        let function = &self.global_scope.functions[func_decl.idx];
+        for (variable_idx, parameter) in function.parameters.iter().zip(&func_decl.parameters) {
+            let name = &self.global_scope.variables[*variable_idx].name;
+            if self.used.contains(variable_idx) || is_intentionally_unused(name) {
+                continue;
+            }
+            self.diagnostics.push(unused_diagnostic(
+                Lint::UnusedParameters,
+                "parameter",
+                name,
+                parameter.identifier.span,
+            ));
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, var_expr: &VarExpr, _expr: &Expr) {
+        self.used.insert(var_expr.variable_idx);
+    }
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
+
+/// Functions that are never called, other than by themselves.
+pub struct UnusedFunctions<'a> {
+    global_scope: &'a GlobalScope,
+    /// The function being visited, whose calls to itself don't count.
+    function: Option<FunctionIdx>,
+    declarations: Vec<(FunctionIdx, Token, ItemId)>,
+    called: HashSet<FunctionIdx>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> UnusedFunctions<'a> {
+    pub fn new(global_scope: &'a GlobalScope) -> Self {
+        Self {
+            global_scope,
+            function: None,
+            declarations: Vec::new(),
+            called: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl LintPass for UnusedFunctions<'_> {
+    fn finish(&mut self, _ast: &mut Ast) {
+        for (function_idx, identifier, item_id) in &self.declarations {
+            let name = &self.global_scope.functions[*function_idx].name;
+            if self.called.contains(function_idx) || is_intentionally_unused(name) {
+                continue;
+            }
+            self.diagnostics.push(
+                unused_diagnostic(Lint::UnusedFunctions, "function", name, identifier.span)
+                    .in_item(*item_id),
+            );
+        }
+    }
+
+    fn take_diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl ASTVisitor for UnusedFunctions<'_> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, item_id: ItemId) {
+        self.declarations
+            .push((func_decl.idx, func_decl.identifier.clone(), item_id));
+        self.function = Some(func_decl.idx);
+        self.visit_body(ast, &func_decl.body);
+        self.function = None;
+    }
+
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expr: &CallExpr, _expr: &Expr) {
+        for argument in &call_expr.arguments {
+            self.visit_expression(ast, *argument);
+        }
+        if self.function != Some(call_expr.function_idx) {
+            self.called.insert(call_expr.function_idx);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, _var_expr: &VarExpr, _expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {
+    }
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{env, fs, path::Path, process::ExitCode};
+
+use nsharp_compiler::{
+    compilation_unit::CompilationUnit,
+    definitions::{
+        profiler::Profiler,
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+    },
+    diagnostics::{
+        explain::explanation, json::JsonEmitter, printer::DiagnosticsPrinter, sarif::SarifEmitter,
+        Diagnostic, DiagnosticsBagCell, ErrorCode,
+    },
+    lint::config::LintConfig,
+    repl,
+    text::source_map::SourceMap,
+};
+
+const USAGE: &str = "usage: nsharp [--error-format human|json|sarif] [--profile]
+              [--folded-stacks <output>] <file>
+       nsharp --explain <code>
+       nsharp repl
+       nsharp debug <file>";
+
+/// How diagnostics are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Human,
+    /// One JSON object per diagnostic, one per line.
+    Json,
+    /// A single SARIF 2.1.0 log.
+    Sarif,
+}
+
+impl ErrorFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            "sarif" => Some(ErrorFormat::Sarif),
+            _ => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut error_format = ErrorFormat::Human;
+    let mut path = None;
+    let mut show_profile = false;
+    let mut folded_stacks_path = None;
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("repl") {
+        return match repl::run() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    if args.peek().map(String::as_str) == Some("debug") {
+        args.next();
+        let (Some(path), None) = (args.next(), args.next()) else {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        };
+        return match repl::debugger::run(&path) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(error) => {
+                eprintln!("could not read {}: {}", path, error);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    while let Some(arg) = args.next() {
+        if arg == "--explain" {
+            let Some(code) = args.next() else {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            };
+            return explain(&code);
+        }
+        if arg == "--profile" {
+            show_profile = true;
+            continue;
+        }
+        if arg == "--folded-stacks" {
+            let Some(output) = args.next() else {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            };
+            folded_stacks_path = Some(output);
+            continue;
+        }
+        let format = match arg.strip_prefix("--error-format") {
+            Some("") => args.next(),
+            Some(value) if value.starts_with('=') => Some(value[1..].to_string()),
+            _ => {
+                path = Some(arg);
+                continue;
+            }
+        };
+        match format.as_deref().and_then(ErrorFormat::parse) {
+            Some(format) => error_format = format,
+            None => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let input = match fs::read_to_string(&path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let lint_config = match LintConfig::for_source_file(Path::new(&path)) {
+        Ok(lint_config) => lint_config,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file(path.clone(), input);
+    match CompilationUnit::compile_with_lints(&source_map, file_id, &lint_config) {
+        Ok(mut compilation_unit) => {
+            let mut diagnostics = sorted_diagnostics(&compilation_unit.diagnostics_bag);
+            // Warnings are shown before the program's output, but the machine
+            // formats wait for the run so that a runtime error ends up in the
+            // same document.
+            if error_format == ErrorFormat::Human {
+                emit_diagnostics(&diagnostics, &source_map, error_format);
+                diagnostics.clear();
+            }
+            let result = if show_profile || folded_stacks_path.is_some() {
+                let (result, profile) =
+                    compilation_unit.run_with_profiler(Profiler::new(&source_map));
+                if show_profile {
+                    eprint!("{}", profile.report());
+                }
+                if let Some(output) = &folded_stacks_path {
+                    if let Err(error) = fs::write(output, profile.folded_stacks()) {
+                        eprintln!("could not write {}: {}", output, error);
+                        emit_diagnostics(&diagnostics, &source_map, error_format);
+                        return ExitCode::FAILURE;
+                    }
+                }
+                result
+            } else {
+                compilation_unit.run()
+            };
+            let exit_code = match result {
+                Ok(()) => ExitCode::SUCCESS,
+                // Like a process exit status, only the low byte is kept.
+                Err(RuntimeError {
+                    kind: RuntimeErrorKind::Exit { code },
+                    ..
+                }) => ExitCode::from(code as u8),
+                Err(error) => {
+                    diagnostics.push(error.to_diagnostic());
+                    ExitCode::FAILURE
+                }
+            };
+            emit_diagnostics(&diagnostics, &source_map, error_format);
+            exit_code
+        }
+        Err(diagnostics_bag) => {
+            let diagnostics = sorted_diagnostics(&diagnostics_bag);
+            emit_diagnostics(&diagnostics, &source_map, error_format);
+            if error_format == ErrorFormat::Human {
+                let error_count = diagnostics_bag.borrow().error_count();
+                eprintln!(
+                    "could not compile '{}' due to {} previous error{}",
+                    path,
+                    error_count,
+                    if error_count == 1 { "" } else { "s" }
+                );
+                let first_code = diagnostics_bag
+                    .borrow()
+                    .diagnostics
+                    .iter()
+                    .find_map(|diagnostic| diagnostic.code);
+                if let Some(code) = first_code {
+                    eprintln!(
+                        "for more information about an error, try `nsharp --explain {}`",
+                        code
+                    );
+                }
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn explain(code: &str) -> ExitCode {
+    match ErrorCode::parse(code) {
+        Some(error_code) => {
+            print!("{}", explanation(error_code));
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("error: {} is not a valid error code", code);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn sorted_diagnostics(diagnostics_bag: &DiagnosticsBagCell) -> Vec<Diagnostic> {
+    let mut diagnostics_bag = diagnostics_bag.borrow_mut();
+    diagnostics_bag.sort();
+    diagnostics_bag.diagnostics.clone()
+}
+
+fn emit_diagnostics(diagnostics: &[Diagnostic], source_map: &SourceMap, error_format: ErrorFormat) {
+    match error_format {
+        ErrorFormat::Human => DiagnosticsPrinter::new(source_map, diagnostics).print(),
+        ErrorFormat::Json => JsonEmitter::new(source_map).print(diagnostics),
+        ErrorFormat::Sarif => SarifEmitter::new(source_map).print(diagnostics),
+    }
+}
@@ -0,0 +1,264 @@
+use std::{fs, io};
+
+use crate::{
+    compilation_unit::CompilationUnit,
+    definitions::{
+        debugger::{
+            Breakpoint, CallFrame, DebugCommand, DebugFrontend, Debugger, Pause, PauseReason,
+        },
+        runtime_error::RuntimeErrorKind,
+    },
+    diagnostics::{printer::DiagnosticsPrinter, Diagnostic},
+    repl::line_editor::LineEditor,
+    text::{source_map::SourceMap, span::TextSpan},
+};
+
+const HELP: &str = "\
+Commands:
+  s, step              run to the next statement, entering calls
+  n, next              run to the next statement, stepping over calls
+  o, out               run until the current function returns
+  c, continue          run to the next breakpoint
+  b, break [location]  add a breakpoint at <file>:<line> or <function>,
+                       or list the breakpoints
+  d, delete <number>   remove a breakpoint
+  bt, backtrace        show the calls being executed
+  v, vars [frame]      show the variables of every frame, or of one
+  p, print <name>      show a variable of the current function
+  l, list              show the source around the current statement
+  q, quit              stop the program
+  h, help              show this message
+An empty line repeats the last command.";
+
+/// Lines shown on each side of the current one by `list`.
+const LIST_CONTEXT: usize = 3;
+
+/// A debugger front end reading commands from the terminal.
+struct TerminalFrontend<'a> {
+    source_map: &'a SourceMap,
+    editor: LineEditor,
+    last_command: String,
+    /// The number shown for each breakpoint, in the order of the
+    /// debugger's breakpoints. Numbers aren't reused after a deletion, so
+    /// a number always means the same breakpoint.
+    breakpoint_numbers: Vec<usize>,
+    next_breakpoint_number: usize,
+}
+
+impl<'a> TerminalFrontend<'a> {
+    fn location(&self, span: TextSpan) -> String {
+        let file = self.source_map.get(span.file_id);
+        format!(
+            "{}:{}:{}",
+            file.name,
+            file.text.line_index(span.start) + 1,
+            file.text.column_index(span.start) + 1
+        )
+    }
+
+    fn print_line(&self, span: TextSpan, line_index: usize) {
+        let text = &self.source_map.get(span.file_id).text;
+        let current = text.line_index(span.start) == line_index;
+        println!(
+            "{} {:>4} | {}",
+            if current { ">" } else { " " },
+            line_index + 1,
+            text.get_line(line_index).trim_end()
+        );
+    }
+
+    fn list(&self, span: TextSpan) {
+        let text = &self.source_map.get(span.file_id).text;
+        let line_index = text.line_index(span.start);
+        let first = line_index.saturating_sub(LIST_CONTEXT);
+        let last = (line_index + LIST_CONTEXT).min(text.line_count().saturating_sub(1));
+        for index in first..=last {
+            self.print_line(span, index);
+        }
+    }
+
+    fn print_frame(&self, index: usize, frame: &CallFrame) {
+        println!(
+            "#{} {} at {}",
+            index,
+            frame.stack_frame,
+            self.location(frame.stack_frame.span)
+        );
+    }
+
+    fn print_variables(&self, index: usize, frame: &CallFrame) {
+        self.print_frame(index, frame);
+        if frame.variables.is_empty() {
+            println!("    no variables");
+        }
+        for variable in &frame.variables {
+            println!(
+                "    {}: {} = {}",
+                variable.name, variable.ty, variable.value
+            );
+        }
+    }
+
+    /// Runs a command given while paused. Returns how to resume, or `None`
+    /// to stay paused.
+    fn execute(
+        &mut self,
+        command: &str,
+        argument: &str,
+        pause: &Pause,
+        breakpoints: &mut Vec<Breakpoint>,
+    ) -> Option<DebugCommand> {
+        match command {
+            "s" | "step" => return Some(DebugCommand::StepIn),
+            "n" | "next" => return Some(DebugCommand::StepOver),
+            "o" | "out" => return Some(DebugCommand::StepOut),
+            "c" | "continue" => return Some(DebugCommand::Continue),
+            "q" | "quit" => return Some(DebugCommand::Quit),
+            "b" | "break" if argument.is_empty() => {
+                if breakpoints.is_empty() {
+                    println!("no breakpoints");
+                }
+                for (number, breakpoint) in self.breakpoint_numbers.iter().zip(breakpoints.iter()) {
+                    println!("{}: {}", number, breakpoint);
+                }
+            }
+            "b" | "break" => match Breakpoint::parse(argument) {
+                Some(breakpoint) => {
+                    let number = self.next_breakpoint_number;
+                    self.next_breakpoint_number += 1;
+                    println!("breakpoint {} at {}", number, breakpoint);
+                    breakpoints.push(breakpoint);
+                    self.breakpoint_numbers.push(number);
+                }
+                None => eprintln!("expected <file>:<line> or a function name"),
+            },
+            "d" | "delete" => match argument.parse::<usize>().ok().and_then(|number| {
+                self.breakpoint_numbers
+                    .iter()
+                    .position(|existing| *existing == number)
+            }) {
+                Some(index) => {
+                    breakpoints.remove(index);
+                    self.breakpoint_numbers.remove(index);
+                }
+                None => eprintln!("no breakpoint '{}'", argument),
+            },
+            "bt" | "backtrace" => {
+                for (index, frame) in pause.frames.iter().enumerate() {
+                    self.print_frame(index, frame);
+                }
+            }
+            "v" | "vars" if argument.is_empty() => {
+                for (index, frame) in pause.frames.iter().enumerate() {
+                    self.print_variables(index, frame);
+                }
+            }
+            "v" | "vars" => match argument
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| pause.frames.get(index).map(|frame| (index, frame)))
+            {
+                Some((index, frame)) => self.print_variables(index, frame),
+                None => eprintln!("no frame '{}'", argument),
+            },
+            "p" | "print" => match pause.variable(0, argument) {
+                Some(variable) => {
+                    println!("{}: {} = {}", variable.name, variable.ty, variable.value)
+                }
+                None => eprintln!("no variable '{}' in scope", argument),
+            },
+            "l" | "list" => self.list(pause.span),
+            "h" | "help" => println!("{}", HELP),
+            _ => eprintln!("unknown command '{}', 'help' lists the commands", command),
+        }
+        None
+    }
+}
+
+impl<'a> DebugFrontend for TerminalFrontend<'a> {
+    fn paused(&mut self, pause: &Pause, breakpoints: &mut Vec<Breakpoint>) -> DebugCommand {
+        if let PauseReason::Breakpoint(index) = pause.reason {
+            println!(
+                "breakpoint {}, {}",
+                self.breakpoint_numbers[index], breakpoints[index]
+            );
+        }
+        let text = &self.source_map.get(pause.span.file_id).text;
+        println!("{}", self.location(pause.span));
+        self.print_line(pause.span, text.line_index(pause.span.start));
+        loop {
+            // The input ending, or failing to be read, leaves nobody to
+            // resume the program.
+            let Ok(Some(line)) = self.editor.read_line("(debug) ") else {
+                return DebugCommand::Quit;
+            };
+            let line = match line.trim() {
+                "" => self.last_command.clone(),
+                line => {
+                    self.editor.add_history(line);
+                    line.to_string()
+                }
+            };
+            if line.is_empty() {
+                continue;
+            }
+            let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((&line, ""));
+            let resume = self.execute(command, argument.trim(), pause, breakpoints);
+            self.last_command = line;
+            if let Some(command) = resume {
+                return command;
+            }
+        }
+    }
+}
+
+/// Runs the file under the debugger on the terminal, paused before its
+/// first statement. Returns whether it compiled and ran to completion.
+pub fn run(path: &str) -> io::Result<bool> {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file(path.to_string(), fs::read_to_string(path)?);
+    let mut compilation_unit = match CompilationUnit::compile(&source_map, file_id) {
+        Ok(compilation_unit) => compilation_unit,
+        Err(diagnostics_bag) => {
+            let mut diagnostics_bag = diagnostics_bag.borrow_mut();
+            diagnostics_bag.sort();
+            print_diagnostics(&source_map, &diagnostics_bag.diagnostics);
+            return Ok(false);
+        }
+    };
+    println!("nsharp debugger, 'help' for help");
+    let frontend = TerminalFrontend {
+        source_map: &source_map,
+        editor: LineEditor::new(),
+        last_command: String::new(),
+        breakpoint_numbers: Vec::new(),
+        next_breakpoint_number: 1,
+    };
+    let debugger = Debugger::new(&source_map, frontend).stop_at_entry();
+    match compilation_unit.run_with_debugger(debugger) {
+        Ok(()) => {
+            println!("program finished");
+            Ok(true)
+        }
+        Err(error) if matches!(error.kind, RuntimeErrorKind::Exit { code: 0 }) => {
+            println!("program finished");
+            Ok(true)
+        }
+        Err(error) if matches!(error.kind, RuntimeErrorKind::Exit { .. }) => {
+            println!("program {}", error.kind);
+            Ok(false)
+        }
+        Err(error) if error.kind == RuntimeErrorKind::Interrupted => {
+            println!("program stopped");
+            Ok(false)
+        }
+        Err(error) => {
+            print_diagnostics(&source_map, &[error.to_diagnostic()]);
+            Ok(false)
+        }
+    }
+}
+
+fn print_diagnostics(source_map: &SourceMap, diagnostics: &[Diagnostic]) {
+    DiagnosticsPrinter::new(source_map, diagnostics).print();
+}
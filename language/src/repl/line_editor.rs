@@ -0,0 +1,118 @@
+use std::io::{self, BufRead, Write};
+
+use termion::{clear, cursor, event::Key, input::TermRead, raw::IntoRawMode};
+
+/// Reads lines from the terminal with basic editing: moving the cursor,
+/// deleting around it and recalling earlier lines with the arrow keys.
+/// When stdin isn't a terminal, lines are read as they are.
+pub struct LineEditor {
+    history: Vec<String>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self {
+            history: Vec::new(),
+        }
+    }
+
+    /// Remembers an entry, to be recalled with the up arrow.
+    pub fn add_history(&mut self, entry: &str) {
+        if self.history.last().map(String::as_str) != Some(entry) {
+            self.history.push(entry.to_string());
+        }
+    }
+
+    /// Reads a line after showing `prompt`, or returns `None` at the end of
+    /// the input.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let stdin = io::stdin();
+        if !termion::is_tty(&stdin) {
+            return Self::read_plain_line(prompt);
+        }
+        let mut stdout = io::stdout().into_raw_mode()?;
+        write!(stdout, "{}", prompt)?;
+        stdout.flush()?;
+
+        let mut line: Vec<char> = Vec::new();
+        let mut position = 0;
+        // Where the up and down arrows are in the history, which is past its
+        // end while editing a new line.
+        let mut history_index = self.history.len();
+        for key in stdin.lock().keys() {
+            match key? {
+                Key::Char('\n') | Key::Char('\r') => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(Some(line.into_iter().collect()));
+                }
+                Key::Char(c) => {
+                    line.insert(position, c);
+                    position += 1;
+                }
+                Key::Backspace if position > 0 => {
+                    position -= 1;
+                    line.remove(position);
+                }
+                Key::Delete | Key::Ctrl('d') if position < line.len() => {
+                    line.remove(position);
+                }
+                Key::Ctrl('d') if line.is_empty() => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(None);
+                }
+                Key::Ctrl('c') => {
+                    write!(stdout, "^C\r\n")?;
+                    return Ok(Some(String::new()));
+                }
+                Key::Left | Key::Ctrl('b') if position > 0 => position -= 1,
+                Key::Right | Key::Ctrl('f') if position < line.len() => position += 1,
+                Key::Home | Key::Ctrl('a') => position = 0,
+                Key::End | Key::Ctrl('e') => position = line.len(),
+                Key::Ctrl('u') => {
+                    line.drain(..position);
+                    position = 0;
+                }
+                Key::Ctrl('k') => line.truncate(position),
+                Key::Up if history_index > 0 => {
+                    history_index -= 1;
+                    line = self.history[history_index].chars().collect();
+                    position = line.len();
+                }
+                Key::Down if history_index < self.history.len() => {
+                    history_index += 1;
+                    line = match self.history.get(history_index) {
+                        Some(entry) => entry.chars().collect(),
+                        None => Vec::new(),
+                    };
+                    position = line.len();
+                }
+                _ => {}
+            }
+            let text: String = line.iter().collect();
+            write!(stdout, "\r{}{}{}", clear::CurrentLine, prompt, text)?;
+            let after_cursor = line.len() - position;
+            if after_cursor > 0 {
+                write!(stdout, "{}", cursor::Left(after_cursor as u16))?;
+            }
+            stdout.flush()?;
+        }
+        write!(stdout, "\r\n")?;
+        Ok(None)
+    }
+
+    fn read_plain_line(prompt: &str) -> io::Result<Option<String>> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if BufRead::read_line(&mut io::stdin().lock(), &mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,403 @@
+pub mod debugger;
+pub mod line_editor;
+
+use std::{
+    fmt::{Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+use crate::{
+    compilation_unit::{CompilationUnit, VariableIdx},
+    definitions::{
+        eval::{ASTEval, Strings, Value},
+        lexer::{Token, TokenKind},
+        printer::ASTPrinter,
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+        ItemKind, StmtKind,
+    },
+    diagnostics::{printer::DiagnosticsPrinter, Diagnostic, Severity},
+    lint::{config::LintConfig, Lint, LintLevel},
+    repl::line_editor::LineEditor,
+    text::source_map::{FileId, SourceMap},
+    typecheck::Type,
+};
+
+#[derive(Debug)]
+pub enum SessionError {
+    /// The errors compiling the input, and the warnings in it.
+    Compile(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+    /// `:type` was given something other than an expression.
+    NotAnExpression,
+    Io(String, io::Error),
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::Compile(diagnostics) => {
+                let errors = diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                    .count();
+                write!(f, "could not compile the input due to {} errors", errors)
+            }
+            SessionError::Runtime(error) => write!(f, "{}", error),
+            SessionError::NotAnExpression => write!(f, "not an expression"),
+            SessionError::Io(path, error) => write!(f, "could not read {}: {}", path, error),
+        }
+    }
+}
+
+/// What evaluating an input produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// The value and type of the input if it ends with an expression that
+    /// has a value.
+    pub value: Option<(String, Type)>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// The state of a REPL: every input accepted so far, and the values of the
+/// globals they declared.
+///
+/// Each input is compiled together with the ones before it, as the next
+/// file of one program, so that it can use their functions, consts and
+/// globals. Only the new input's items are run, with the globals restored
+/// from the previous run. An input that fails to compile or run is
+/// dropped, and leaves the state as it was.
+pub struct Session {
+    source_map: SourceMap,
+    inputs: Vec<FileId>,
+    /// How many items the accepted inputs declare.
+    item_count: usize,
+    /// The values of the program's `let` globals, in declaration order.
+    globals: Vec<Value>,
+    /// The strings `globals` refer to.
+    strings: Strings,
+    lint_config: LintConfig,
+    /// Numbers the inputs, to name them in diagnostics.
+    input_count: usize,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        // Most lints are about whole programs, and only noise for code
+        // entered a line at a time.
+        let mut lint_config = LintConfig::new();
+        for lint in Lint::all() {
+            lint_config.set_level(*lint, LintLevel::Allow);
+        }
+        Self {
+            source_map: SourceMap::new(),
+            inputs: Vec::new(),
+            item_count: 0,
+            globals: Vec::new(),
+            strings: Strings::new(),
+            lint_config,
+            input_count: 0,
+        }
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Forgets every input.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Whether the input opens more parentheses, braces or brackets than it
+    /// closes, so that the next line should be read as part of it.
+    pub fn is_incomplete(input: &str) -> bool {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(String::new(), input.to_string());
+        let mut depth = 0;
+        for token in CompilationUnit::lex(&source_map, file_id) {
+            match token.kind {
+                TokenKind::OpenParenthesis | TokenKind::OpenBrace | TokenKind::OpenBracket => {
+                    depth += 1
+                }
+                TokenKind::CloseParenthesis | TokenKind::CloseBrace | TokenKind::CloseBracket => {
+                    depth -= 1
+                }
+                _ => {}
+            }
+        }
+        depth > 0
+    }
+
+    fn add_input(&mut self, name: Option<String>, text: &str) -> FileId {
+        self.input_count += 1;
+        let name = name.unwrap_or_else(|| format!("<repl:{}>", self.input_count));
+        self.source_map.add_file(name, text.to_string())
+    }
+
+    /// Compiles the accepted inputs followed by `file_id`.
+    fn compile(&self, file_id: FileId) -> Result<(CompilationUnit, Vec<Diagnostic>), SessionError> {
+        let mut files = self.inputs.clone();
+        files.push(file_id);
+        let in_input = |diagnostic: &Diagnostic| {
+            diagnostic.severity == Severity::Error || diagnostic.span.file_id == file_id
+        };
+        match CompilationUnit::compile_files(&self.source_map, &files, &self.lint_config) {
+            Ok(compilation_unit) => {
+                let warnings = compilation_unit
+                    .diagnostics_bag
+                    .borrow()
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| in_input(diagnostic))
+                    .cloned()
+                    .collect();
+                Ok((compilation_unit, warnings))
+            }
+            Err(diagnostics_bag) => Err(SessionError::Compile(
+                diagnostics_bag
+                    .borrow()
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| in_input(diagnostic))
+                    .cloned()
+                    .collect(),
+            )),
+        }
+    }
+
+    /// The non-const globals of the program, in declaration order. Inputs
+    /// only ever add globals after those of earlier inputs.
+    fn global_variables(compilation_unit: &CompilationUnit) -> Vec<VariableIdx> {
+        let global_scope = &compilation_unit.global_scope;
+        global_scope
+            .global_variables
+            .iter()
+            .filter(|variable_idx| !global_scope.variables[**variable_idx].is_const)
+            .copied()
+            .collect()
+    }
+
+    /// The value as the REPL shows it, with strings quoted.
+    fn format_value(value: Value, strings: &Strings) -> String {
+        match value {
+            Value::String(idx) => format!("{:?}", strings.get(idx)),
+            value => value.display(strings),
+        }
+    }
+
+    pub fn evaluate(&mut self, input: &str) -> Result<Evaluation, SessionError> {
+        let file_id = self.add_input(None, input);
+        self.evaluate_file(file_id)
+    }
+
+    /// Evaluates the file as if its contents were entered.
+    pub fn load(&mut self, path: &Path) -> Result<Evaluation, SessionError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| SessionError::Io(path.display().to_string(), error))?;
+        let file_id = self.add_input(Some(path.display().to_string()), &text);
+        self.evaluate_file(file_id)
+    }
+
+    fn evaluate_file(&mut self, file_id: FileId) -> Result<Evaluation, SessionError> {
+        let (mut compilation_unit, warnings) = self.compile(file_id)?;
+        let items: Vec<_> = compilation_unit
+            .ast
+            .items
+            .iter()
+            .skip(self.item_count)
+            .map(|item| item.id)
+            .collect();
+        let globals = Self::global_variables(&compilation_unit);
+
+        let mut eval = ASTEval::new(&compilation_unit.global_scope);
+        eval.strings = compilation_unit.strings().clone();
+        for (variable_idx, value) in globals.iter().zip(&self.globals) {
+            let value = match value {
+                Value::String(idx) => Value::String(eval.strings.intern(self.strings.get(*idx))),
+                value => *value,
+            };
+            eval.set_variable(*variable_idx, value);
+        }
+        eval.run_items(&mut compilation_unit.ast, &items)
+            .map_err(SessionError::Runtime)?;
+
+        let last_expression = items.last().and_then(|item_id| {
+            match &compilation_unit.ast.query_item(*item_id).kind {
+                ItemKind::Stmt(stmt_id) => match compilation_unit.ast.query_stmt(*stmt_id).kind {
+                    StmtKind::Expr(expr_id) => Some(expr_id),
+                    _ => None,
+                },
+                _ => None,
+            }
+        });
+        let value = last_expression
+            .map(|expr_id| compilation_unit.ast.query_expr(expr_id).ty.clone())
+            .filter(|ty| *ty != Type::Void)
+            .and_then(|ty| Some((Self::format_value(eval.last_value?, &eval.strings), ty)));
+
+        self.globals = globals
+            .iter()
+            .map(|variable_idx| eval.variable(*variable_idx).unwrap_or(Value::Null))
+            .collect();
+        self.strings = std::mem::take(&mut eval.strings);
+        self.inputs.push(file_id);
+        self.item_count = compilation_unit.ast.items.len();
+        Ok(Evaluation { value, warnings })
+    }
+
+    /// The type of an expression, without evaluating it.
+    pub fn type_of(&mut self, expression: &str) -> Result<Type, SessionError> {
+        let file_id = self.add_input(None, expression);
+        let (compilation_unit, _) = self.compile(file_id)?;
+        let ast = &compilation_unit.ast;
+        let items: Vec<_> = ast.items.iter().skip(self.item_count).collect();
+        match items.as_slice() {
+            [item] => match &item.kind {
+                ItemKind::Stmt(stmt_id) => match ast.query_stmt(*stmt_id).kind {
+                    StmtKind::Expr(expr_id) => Ok(ast.query_expr(expr_id).ty.clone()),
+                    _ => Err(SessionError::NotAnExpression),
+                },
+                _ => Err(SessionError::NotAnExpression),
+            },
+            _ => Err(SessionError::NotAnExpression),
+        }
+    }
+
+    /// The syntax tree of the code, as printed by `ASTPrinter`.
+    pub fn ast(&mut self, code: &str) -> Result<String, SessionError> {
+        let file_id = self.add_input(None, code);
+        let mut ast =
+            CompilationUnit::parse(&self.source_map, file_id).map_err(|diagnostics_bag| {
+                SessionError::Compile(diagnostics_bag.borrow().diagnostics.clone())
+            })?;
+        let mut printer = ASTPrinter::new(&self.source_map);
+        ast.visit(&mut printer);
+        Ok(printer.result)
+    }
+
+    /// The tokens of the code, without whitespace.
+    pub fn tokens(&mut self, code: &str) -> Vec<Token> {
+        let file_id = self.add_input(None, code);
+        CompilationUnit::lex(&self.source_map, file_id)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .collect()
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const HELP: &str = "\
+Enter statements, declarations or expressions to evaluate them.
+Commands:
+  :type <expr>    show the type of an expression
+  :ast <code>     show the syntax tree of the code
+  :tokens <code>  show the tokens of the code
+  :load <file>    evaluate a file
+  :reset          forget everything entered so far
+  :help           show this message
+  :quit           leave the REPL";
+
+/// Runs the interactive REPL on the terminal until the input ends or
+/// `:quit` is entered.
+pub fn run() -> io::Result<()> {
+    let mut session = Session::new();
+    let mut editor = LineEditor::new();
+    println!("nsharp repl, :help for help");
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { ">> " } else { ".. " };
+        let Some(line) = editor.read_line(prompt)? else {
+            break;
+        };
+        if !line.trim().is_empty() {
+            editor.add_history(line.trim());
+        }
+        input.push_str(&line);
+        input.push('\n');
+        let trimmed = input.trim();
+        if trimmed.is_empty() {
+            input.clear();
+            continue;
+        }
+        if !trimmed.starts_with(':') && Session::is_incomplete(trimmed) {
+            continue;
+        }
+        let quit = execute(&mut session, trimmed);
+        input.clear();
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Executes an input or command, printing its result. Returns whether the
+/// REPL should quit.
+fn execute(session: &mut Session, input: &str) -> bool {
+    let (command, argument) = match input.strip_prefix(':') {
+        Some(command) => {
+            let (command, argument) = command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, ""));
+            (Some(command), argument.trim())
+        }
+        None => (None, input),
+    };
+    let result = match command {
+        None => session.evaluate(argument).map(|evaluation| {
+            print_diagnostics(session, &evaluation.warnings);
+            if let Some((value, ty)) = evaluation.value {
+                println!("{}: {}", value, ty);
+            }
+        }),
+        Some("type") => session.type_of(argument).map(|ty| println!("{}", ty)),
+        Some("ast") => session.ast(argument).map(|ast| println!("{}", ast)),
+        Some("tokens") => {
+            for token in session.tokens(argument) {
+                println!(
+                    "{} {:?}",
+                    token.kind,
+                    token.span.literal(session.source_map())
+                );
+            }
+            Ok(())
+        }
+        Some("load") => session.load(Path::new(argument)).map(|evaluation| {
+            print_diagnostics(session, &evaluation.warnings);
+        }),
+        Some("reset") => {
+            session.reset();
+            Ok(())
+        }
+        Some("help") => {
+            println!("{}", HELP);
+            Ok(())
+        }
+        Some("quit" | "q") => return true,
+        Some(command) => {
+            eprintln!("unknown command ':{}', :help lists the commands", command);
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => {}
+        Err(SessionError::Compile(diagnostics)) => print_diagnostics(session, &diagnostics),
+        Err(SessionError::Runtime(RuntimeError {
+            kind: RuntimeErrorKind::Exit { .. },
+            ..
+        })) => return true,
+        Err(SessionError::Runtime(error)) => print_diagnostics(session, &[error.to_diagnostic()]),
+        Err(error) => eprintln!("error: {}", error),
+    }
+    false
+}
+
+fn print_diagnostics(session: &Session, diagnostics: &[Diagnostic]) {
+    DiagnosticsPrinter::new(session.source_map(), diagnostics).print();
+}
@@ -1,23 +1,124 @@
+pub mod similarity;
+pub mod source_map;
 pub mod span;
 
-pub struct SourceText{
-    text:String
+/// The unit a column is counted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Byte,
+    Char,
+    /// UTF-16 code units, as counted by editors and LSP clients.
+    Utf16,
 }
 
-impl SourceText{
-    pub fn new(text:String) -> Self{
-        Self{ text }
+/// A zero-based position in a source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+pub struct SourceText {
+    text: String,
+    /// The byte offset of the first character of every line.
+    line_starts: Vec<usize>,
+}
+
+impl SourceText {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(position, _)| position + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The zero-based line the byte offset lies on.
+    pub fn line_index(&self, position: usize) -> usize {
+        let position = position.min(self.text.len());
+        self.line_starts.partition_point(|&start| start <= position) - 1
     }
 
-    pub fn line_index(&self,position: usize) -> usize{
-        self.position[..=position].nth(index).unwrap()
+    /// The byte offset of the first character of a line.
+    pub fn line_start(&self, index: usize) -> usize {
+        self.line_starts
+            .get(index)
+            .copied()
+            .unwrap_or(self.text.len())
     }
 
-    pub fn line_start(&self, position: usize) -> usize{
-        self.text
-            .lines()
-            .take(index)
-            .map(|line| line.len + 1)
-            .sum()
+    /// The byte offset just past the last character of a line, before its
+    /// line terminator.
+    pub fn line_end(&self, index: usize) -> usize {
+        let start = self.line_start(index);
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        if end > start && self.text.as_bytes()[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        }
     }
-}
\ No newline at end of file
+
+    /// The text of a line, without its line terminator.
+    pub fn get_line(&self, index: usize) -> &str {
+        &self.text[self.line_start(index)..self.line_end(index)]
+    }
+
+    /// The zero-based column of a byte offset, counted in chars.
+    pub fn column_index(&self, position: usize) -> usize {
+        self.line_column(position, ColumnKind::Char).column
+    }
+
+    /// Converts a byte offset into a line and a column counted in `kind`.
+    /// Offsets inside a multi-byte character count as that character's start.
+    pub fn line_column(&self, position: usize, kind: ColumnKind) -> LineColumn {
+        let mut position = position.min(self.text.len());
+        while !self.text.is_char_boundary(position) {
+            position -= 1;
+        }
+        let line = self.line_index(position);
+        let prefix = &self.text[self.line_start(line)..position];
+        let column = match kind {
+            ColumnKind::Byte => prefix.len(),
+            ColumnKind::Char => prefix.chars().count(),
+            ColumnKind::Utf16 => prefix.chars().map(char::len_utf16).sum(),
+        };
+        LineColumn { line, column }
+    }
+
+    /// Converts a line and a column counted in `kind` back into a byte
+    /// offset. Lines past the end clamp to the end of the text and columns
+    /// past the end of a line clamp to the end of that line, the way LSP
+    /// clients expect. A column inside a character, such as between the two
+    /// halves of a UTF-16 surrogate pair, resolves to that character's start.
+    pub fn offset(&self, line_column: LineColumn, kind: ColumnKind) -> usize {
+        if line_column.line >= self.line_count() {
+            return self.text.len();
+        }
+        let start = self.line_start(line_column.line);
+        let line = self.get_line(line_column.line);
+        let mut column = 0;
+        for (index, c) in line.char_indices() {
+            let width = match kind {
+                ColumnKind::Byte => c.len_utf8(),
+                ColumnKind::Char => 1,
+                ColumnKind::Utf16 => c.len_utf16(),
+            };
+            if column + width > line_column.column {
+                return start + index;
+            }
+            column += width;
+        }
+        start + line.len()
+    }
+}
@@ -0,0 +1,49 @@
+use navsharp::{idx, Idx, IdxVec};
+
+use crate::text::{span::TextSpan, SourceText};
+
+idx!(FileId);
+
+pub struct SourceFile {
+    pub name: String,
+    pub text: SourceText,
+}
+
+/// Owns the text of every loaded file. Spans only record which file they
+/// belong to and where, so their text is looked up here when needed.
+pub struct SourceMap {
+    files: IdxVec<FileId, SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self {
+            files: IdxVec::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, name: String, text: String) -> FileId {
+        self.files.push(SourceFile {
+            name,
+            text: SourceText::new(text),
+        })
+    }
+
+    pub fn get(&self, file_id: FileId) -> &SourceFile {
+        self.files.get(file_id)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files.indexed_iter()
+    }
+
+    pub fn literal(&self, span: &TextSpan) -> &str {
+        &self.get(span.file_id).text.text()[span.start..span.end]
+    }
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
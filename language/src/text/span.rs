@@ -1,41 +1,39 @@
-use nsharp_compiler::bug;
+use crate::text::source_map::{FileId, SourceMap};
 
-#[derive(Debug,Clone, PartialEq,Eq)]
-pub struct TextSpan{
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextSpan {
+    pub file_id: FileId,
     pub start: usize,
     pub end: usize,
-    pub literal: String
 }
 
-impl TextSpan{
-    pub fn new(start: usize,end: usize, literal: usize) -> Self{
-        Self{
+impl TextSpan {
+    pub fn new(file_id: FileId, start: usize, end: usize) -> Self {
+        Self {
+            file_id,
             start,
             end,
-            literal,
         }
     }
 
-    pub fn combine(mut spans: Vec<TextSpan>) -> Self{
-        if spans.is_empty(){
-            bug!("Cannot Combine Empty Spans")
-        }
-        spans.sort_by(|a,b| a.start.cmp(&b.start))
-        let start = spans.first().unwrap().start;
-        let end = spans.end().unwrap().end;
-
+    /// The span covering both spans and everything between them.
+    pub fn to(self, other: TextSpan) -> Self {
+        assert_eq!(
+            self.file_id, other.file_id,
+            "cannot combine spans of different files"
+        );
         TextSpan::new(
-            start,
-            end,
-            spans.into_iter().map(|spans| spans.literal).collect()
+            self.file_id,
+            self.start.min(other.start),
+            self.end.max(other.end),
         )
     }
 
-    pub fn length(&self) -> usize{
+    pub fn length(&self) -> usize {
         self.end - self.start
     }
 
-    pub fn literal<'a>(&self , input: 'a str) -> &'a str{
-        &input[self.start..self.end]
+    pub fn literal<'a>(&self, source_map: &'a SourceMap) -> &'a str {
+        source_map.literal(self)
     }
-}
\ No newline at end of file
+}
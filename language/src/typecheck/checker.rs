@@ -0,0 +1,793 @@
+use std::collections::{HashMap, HashSet};
+
+use navsharp::Idx;
+
+use crate::{
+    compilation_unit::{
+        FieldSymbol, FunctionIdx, GlobalScope, TypeIdx, TypeSymbolKind, VariableIdx,
+    },
+    definitions::{
+        lexer::Token, visitor::ASTVisitor, AssignExpr, Ast, BinOpKind, BinaryExpr, BlockExpr,
+        Body, BoolExpr, CallExpr, ConstDeclaration, EnumDeclaration, Expr, ExprId, ExprKind,
+        FuncDeclaration, IfExpr, ItemId, ItemKind, LetStmt, NullExpr, NumberExpr,
+        ParenthesizedExpr, ReturnStmt, Stmt, StmtKind, StringExpr, StringPart, StructDeclaration,
+        TypeAliasDeclaration, UnaryExpr, VarExpr, WhileStmt,
+    },
+    diagnostics::DiagnosticsBagCell,
+    text::{similarity::closest_matches, source_map::SourceMap, span::TextSpan},
+    typecheck::Type,
+};
+
+/// Assigns a type to every expression and checks that values are used
+/// consistently with their declared types.
+///
+/// Nullable variables are tracked flow-sensitively: inside
+/// `if x != null { ... }` (and after `if x == null { return }`) the checker
+/// knows `x` holds a value and lets it be used as the non-nullable type.
+pub struct TypeChecker<'a> {
+    global_scope: &'a mut GlobalScope,
+    diagnostics: DiagnosticsBagCell,
+    source_map: &'a SourceMap,
+    function_return_type: Option<Type>,
+    non_null: HashSet<VariableIdx>,
+    type_aliases: HashMap<TypeIdx, TypeAliasDeclaration>,
+    resolving_aliases: HashSet<TypeIdx>,
+    /// `if` expressions whose branches have different types, with the type
+    /// of the `then` branch, the type of the `else` branch and where the
+    /// `else` value is. They are only errors once their value is used.
+    mismatched_branches: HashMap<ExprId, (Type, Type, TextSpan)>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(
+        global_scope: &'a mut GlobalScope,
+        diagnostics: DiagnosticsBagCell,
+        source_map: &'a SourceMap,
+    ) -> Self {
+        Self {
+            global_scope,
+            diagnostics,
+            source_map,
+            function_return_type: None,
+            non_null: HashSet::new(),
+            type_aliases: HashMap::new(),
+            resolving_aliases: HashSet::new(),
+            mismatched_branches: HashMap::new(),
+        }
+    }
+
+    pub fn check(&mut self, ast: &mut Ast) {
+        self.declare_types(ast);
+        for item in ast.items.iter() {
+            match &item.kind {
+                ItemKind::Function(func_decl) => self.declare_signature(func_decl),
+                ItemKind::Const(const_decl) => self.declare_const(const_decl),
+                _ => {}
+            }
+        }
+        ast.visit(self);
+    }
+
+    /// Consts are declared up front so they can be used above their
+    /// definition, like functions.
+    fn declare_const(&mut self, const_decl: &ConstDeclaration) {
+        let annotation = &const_decl.type_annotation;
+        let ty = self.resolve_type(&annotation.type_name, &annotation.question_mark);
+        if const_decl.variable_idx != VariableIdx::unreachable() {
+            self.global_scope.variables[const_decl.variable_idx].ty = ty;
+        }
+    }
+
+    fn declare_signature(&mut self, func_decl: &FuncDeclaration) {
+        let return_type = match &func_decl.return_type {
+            Some(return_type) => self.resolve_type(&return_type.type_name, &return_type.question_mark),
+            None => Type::Void,
+        };
+        let parameters = self.global_scope.functions[func_decl.idx].parameters.clone();
+        for (parameter, variable_idx) in func_decl.parameters.iter().zip(parameters) {
+            let annotation = &parameter.type_annotation;
+            let ty = self.resolve_type(&annotation.type_name, &annotation.question_mark);
+            self.global_scope.variables[variable_idx].ty = ty;
+        }
+        self.global_scope.functions[func_decl.idx].return_type = return_type;
+    }
+
+    /// Declares every type item before resolving any of them, so that
+    /// annotations may name types declared further down the file.
+    fn declare_types(&mut self, ast: &Ast) {
+        let mut aliases = Vec::new();
+        let mut structs = Vec::new();
+        for item in ast.items.iter() {
+            let (identifier, kind) = match &item.kind {
+                ItemKind::TypeAlias(type_alias) => (
+                    &type_alias.identifier,
+                    TypeSymbolKind::Alias(Type::Unresolved),
+                ),
+                ItemKind::Struct(struct_decl) => {
+                    (&struct_decl.identifier, TypeSymbolKind::Struct(Vec::new()))
+                }
+                ItemKind::Enum(enum_decl) => (
+                    &enum_decl.identifier,
+                    TypeSymbolKind::Enum(self.enum_variants(enum_decl)),
+                ),
+                _ => continue,
+            };
+            let name = identifier.span.literal(self.source_map);
+            let Ok(type_idx) = self.global_scope.declare_type(name.to_string(), kind) else {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_type_already_declared(name, &identifier.span);
+                continue;
+            };
+            match &item.kind {
+                ItemKind::TypeAlias(type_alias) => {
+                    self.type_aliases.insert(type_idx, type_alias.clone());
+                    aliases.push(type_idx);
+                }
+                ItemKind::Struct(struct_decl) => structs.push((type_idx, struct_decl)),
+                _ => {}
+            }
+        }
+
+        for type_idx in aliases {
+            self.resolve_alias(type_idx);
+        }
+        for (type_idx, struct_decl) in structs {
+            let fields = self.struct_fields(struct_decl);
+            self.global_scope.types[type_idx].kind = TypeSymbolKind::Struct(fields);
+        }
+    }
+
+    fn enum_variants(&mut self, enum_decl: &EnumDeclaration) -> Vec<String> {
+        let mut variants: Vec<String> = Vec::new();
+        for variant in &enum_decl.variants {
+            let name = variant.span.literal(self.source_map);
+            if variants.iter().any(|existing| existing == name) {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_duplicate_variant(name, &variant.span);
+                continue;
+            }
+            variants.push(name.to_string());
+        }
+        variants
+    }
+
+    fn struct_fields(&mut self, struct_decl: &StructDeclaration) -> Vec<FieldSymbol> {
+        let mut fields: Vec<FieldSymbol> = Vec::new();
+        for field in &struct_decl.fields {
+            let annotation = &field.type_annotation;
+            let ty = self.resolve_type(&annotation.type_name, &annotation.question_mark);
+            let name = field.identifier.span.literal(self.source_map);
+            if fields.iter().any(|existing| existing.name == name) {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_duplicate_field(name, &field.identifier.span);
+                continue;
+            }
+            fields.push(FieldSymbol {
+                name: name.to_string(),
+                ty,
+            });
+        }
+        fields
+    }
+
+    /// Resolves an alias to the type it names, following chains of aliases
+    /// and reporting cycles such as `type A = B; type B = A;`.
+    fn resolve_alias(&mut self, type_idx: TypeIdx) -> Type {
+        if let TypeSymbolKind::Alias(ty) = &self.global_scope.types[type_idx].kind {
+            if *ty != Type::Unresolved {
+                return ty.clone();
+            }
+        }
+        let Some(type_alias) = self.type_aliases.get(&type_idx).cloned() else {
+            return Type::Error;
+        };
+        if !self.resolving_aliases.insert(type_idx) {
+            self.diagnostics
+                .borrow_mut()
+                .report_recursive_type_alias(
+                    type_alias.identifier.span.literal(self.source_map),
+                    &type_alias.identifier.span,
+                );
+            return Type::Error;
+        }
+        let ty = self.resolve_type(&type_alias.type_name, &type_alias.question_mark);
+        self.resolving_aliases.remove(&type_idx);
+        self.global_scope.types[type_idx].kind = TypeSymbolKind::Alias(ty.clone());
+        ty
+    }
+
+    fn resolve_type(&mut self, type_name: &Token, question_mark: &Option<Token>) -> Type {
+        let name = type_name.span.literal(self.source_map);
+        let ty = match self.global_scope.lookup_type(name) {
+            Some(type_idx) => self.type_of_symbol(type_idx),
+            None => {
+                let candidates = closest_matches(
+                    name,
+                    self.global_scope
+                        .types
+                        .iter()
+                        .map(|symbol| symbol.name.as_str()),
+                    3,
+                );
+                self.diagnostics
+                    .borrow_mut()
+                    .report_undeclared_type(name, &type_name.span, &candidates);
+                Type::Error
+            }
+        };
+        match question_mark {
+            Some(_) => ty.into_nullable(),
+            None => ty,
+        }
+    }
+
+    fn type_of_symbol(&mut self, type_idx: TypeIdx) -> Type {
+        let symbol = &self.global_scope.types[type_idx];
+        match &symbol.kind {
+            TypeSymbolKind::Builtin(ty) => ty.clone(),
+            TypeSymbolKind::Struct(_) => Type::Struct(type_idx, symbol.name.clone()),
+            TypeSymbolKind::Enum(_) => Type::Enum(type_idx, symbol.name.clone()),
+            TypeSymbolKind::Alias(_) => self.resolve_alias(type_idx),
+        }
+    }
+
+    fn check_expression(&mut self, ast: &mut Ast, expr_id: ExprId) -> Type {
+        self.visit_expression(ast, expr_id);
+        self.report_mismatched_branches(ast, expr_id);
+        match &ast.query_expr(expr_id).ty {
+            Type::Unresolved => Type::Error,
+            ty => ty.clone(),
+        }
+    }
+
+    fn check_body(&mut self, ast: &mut Ast, body: &Body) -> Type {
+        self.visit_body(ast, body);
+        match Self::body_value(ast, body) {
+            Some(expr_id) => ast.query_expr(expr_id).ty.clone(),
+            None => Type::Void,
+        }
+    }
+
+    /// The expression whose value a body evaluates to, if any.
+    fn body_value(ast: &Ast, body: &Body) -> Option<ExprId> {
+        match body.last().map(|stmt| &ast.query_stmt(*stmt).kind) {
+            Some(StmtKind::Expr(expr_id)) => Some(*expr_id),
+            _ => None,
+        }
+    }
+
+    /// Reports the `if` expressions with mismatched branches that a used
+    /// value comes from, and types the value as an error if there were any.
+    /// Only the innermost mismatch is reported. An `if` used as a statement
+    /// never gets here, so its branches may have any types.
+    fn report_mismatched_branches(&mut self, ast: &mut Ast, expr_id: ExprId) -> bool {
+        if self.mismatched_branches.is_empty() {
+            return false;
+        }
+        let values: Vec<ExprId> = match &ast.query_expr(expr_id).kind {
+            ExprKind::If(if_expr) => Self::body_value(ast, &if_expr.then_branch)
+                .into_iter()
+                .chain(
+                    if_expr
+                        .else_branch
+                        .as_ref()
+                        .and_then(|else_branch| Self::body_value(ast, &else_branch.body)),
+                )
+                .collect(),
+            ExprKind::Block(block_expr) => block_expr.returning_statements(ast).into_iter().collect(),
+            _ => Vec::new(),
+        };
+        let mut reported = false;
+        for value in values {
+            reported |= self.report_mismatched_branches(ast, value);
+        }
+        if let Some((expected, actual, span)) = self.mismatched_branches.remove(&expr_id) {
+            if !reported {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_type_mismatch(&expected, &actual, &span);
+                reported = true;
+            }
+        }
+        if reported {
+            ast.set_type(expr_id, Type::Error);
+        }
+        reported
+    }
+
+    fn expect_assignable(&mut self, ast: &Ast, expr_id: ExprId, actual: &Type, expected: &Type) {
+        if actual.is_assignable_to(expected) {
+            return;
+        }
+        let span = ast.query_expr(expr_id).span(ast);
+        if actual.can_be_null() && actual.without_null().is_assignable_to(expected) {
+            self.diagnostics
+                .borrow_mut()
+                .report_possibly_null_value(actual, &span);
+        } else {
+            self.diagnostics
+                .borrow_mut()
+                .report_type_mismatch(expected, actual, &span);
+        }
+    }
+
+    /// Reports a use of a possibly-null value where a plain value is required
+    /// and continues checking with the non-nullable type.
+    fn expect_non_null(&mut self, ast: &Ast, expr_id: ExprId, ty: Type) -> Type {
+        if !ty.can_be_null() {
+            return ty;
+        }
+        let span = ast.query_expr(expr_id).span(ast);
+        self.diagnostics
+            .borrow_mut()
+            .report_possibly_null_value(&ty, &span);
+        ty.without_null()
+    }
+
+    fn variable_type(&self, variable_idx: VariableIdx) -> Type {
+        if variable_idx == VariableIdx::unreachable() {
+            return Type::Error;
+        }
+        let ty = &self.global_scope.variables[variable_idx].ty;
+        if self.non_null.contains(&variable_idx) {
+            ty.without_null()
+        } else {
+            ty.clone()
+        }
+    }
+
+    /// The type a `null` is compared against: a variable's declared type,
+    /// so that comparing a variable narrowed to non-null stays allowed.
+    fn compared_with_null_type(&self, ast: &Ast, expr_id: ExprId, ty: Type) -> Type {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Variable(var_expr) if var_expr.variable_idx != VariableIdx::unreachable() => {
+                self.global_scope.variables[var_expr.variable_idx].ty.clone()
+            }
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                self.compared_with_null_type(ast, parenthesized_expr.inner, ty)
+            }
+            _ => ty,
+        }
+    }
+
+    fn record_assignment(&mut self, variable_idx: VariableIdx, value: &Type) {
+        if value.can_be_null() {
+            self.non_null.remove(&variable_idx);
+        } else {
+            self.non_null.insert(variable_idx);
+        }
+    }
+
+    /// The variables a condition proves non-null when it evaluates to
+    /// `true` and when it evaluates to `false`, respectively.
+    fn null_checks(ast: &Ast, condition: ExprId) -> (Vec<VariableIdx>, Vec<VariableIdx>) {
+        match &ast.query_expr(condition).kind {
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                Self::null_checks(ast, parenthesized_expr.inner)
+            }
+            ExprKind::Binary(binary_expr) => {
+                let left = &ast.query_expr(binary_expr.left).kind;
+                let right = &ast.query_expr(binary_expr.right).kind;
+                let checked = match (left, right) {
+                    (ExprKind::Variable(var_expr), ExprKind::Null(_))
+                    | (ExprKind::Null(_), ExprKind::Variable(var_expr)) => var_expr.variable_idx,
+                    _ => return (Vec::new(), Vec::new()),
+                };
+                match binary_expr.operator.kind {
+                    BinOpKind::NotEqualsto => (vec![checked], Vec::new()),
+                    BinOpKind::Equalsto => (Vec::new(), vec![checked]),
+                    _ => (Vec::new(), Vec::new()),
+                }
+            }
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn body_diverges(ast: &Ast, body: &Body) -> bool {
+        body.iter()
+            .any(|stmt| matches!(ast.query_stmt(*stmt).kind, StmtKind::Return(_)))
+    }
+
+    fn check_condition(&mut self, ast: &mut Ast, condition: ExprId) {
+        let ty = self.check_expression(ast, condition);
+        let ty = self.expect_non_null(ast, condition, ty);
+        self.expect_assignable(ast, condition, &ty, &Type::Bool);
+    }
+}
+
+impl<'a> ASTVisitor for TypeChecker<'a> {
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        let return_type = self.global_scope.functions[func_decl.idx].return_type.clone();
+        let previous_return_type = self.function_return_type.replace(return_type);
+        let previous_non_null = std::mem::take(&mut self.non_null);
+        self.visit_body(ast, &func_decl.body);
+        self.non_null = previous_non_null;
+        self.function_return_type = previous_return_type;
+    }
+
+    fn visit_const_decl(&mut self, ast: &mut Ast, const_decl: &ConstDeclaration, _item_id: ItemId) {
+        let previous_non_null = std::mem::take(&mut self.non_null);
+        let actual = self.check_expression(ast, const_decl.initializer);
+        if const_decl.variable_idx != VariableIdx::unreachable() {
+            let expected = self.global_scope.variables[const_decl.variable_idx].ty.clone();
+            self.expect_assignable(ast, const_decl.initializer, &actual, &expected);
+        }
+        self.non_null = previous_non_null;
+    }
+
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_stmt: &ReturnStmt) {
+        let Some(expected) = self.function_return_type.clone() else {
+            self.diagnostics
+                .borrow_mut()
+                .report_cannot_return_outside_function(&return_stmt.return_keyword);
+            return;
+        };
+        match return_stmt.return_value {
+            Some(return_value) => {
+                let actual = self.check_expression(ast, return_value);
+                self.expect_assignable(ast, return_value, &actual, &expected);
+            }
+            None if expected != Type::Void => {
+                self.diagnostics.borrow_mut().report_type_mismatch(
+                    &expected,
+                    &Type::Void,
+                    &return_stmt.return_keyword.span,
+                );
+            }
+            None => {}
+        }
+    }
+
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_stmt: &WhileStmt) {
+        // Facts established before the loop don't survive an assignment in a
+        // previous iteration, so forget everything the body may overwrite.
+        // A call may assign any global, so one anywhere in the loop forgets
+        // them all.
+        let mut assigned = AssignedVariables::default();
+        assigned.visit_expression(ast, while_stmt.condition);
+        assigned.visit_body(ast, &while_stmt.body);
+        for variable_idx in &assigned.variables {
+            self.non_null.remove(variable_idx);
+        }
+        if assigned.has_call {
+            let variables = &self.global_scope.variables;
+            self.non_null
+                .retain(|variable_idx| !variables[*variable_idx].is_global);
+        }
+
+        self.check_condition(ast, while_stmt.condition);
+        let (when_true, when_false) = Self::null_checks(ast, while_stmt.condition);
+        let before = self.non_null.clone();
+        self.non_null.extend(when_true);
+        self.visit_body(ast, &while_stmt.body);
+        // The loop may run any number of times, so only what holds both
+        // before it and after its body holds after it.
+        let after_body = std::mem::take(&mut self.non_null);
+        self.non_null = before.intersection(&after_body).copied().collect();
+        self.non_null.extend(when_false);
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, expr: &Expr) {
+        for stmt in &block_expr.stmts {
+            self.visit_statement(ast, *stmt);
+        }
+        let ty = match block_expr.returning_statements(ast) {
+            Some(expr_id) => ast.query_expr(expr_id).ty.clone(),
+            None => Type::Void,
+        };
+        ast.set_type(expr.id, ty);
+    }
+
+    fn visit_if_expression(&mut self, ast: &mut Ast, if_expr: &IfExpr, expr: &Expr) {
+        self.check_condition(ast, if_expr.condition);
+        let (when_true, when_false) = Self::null_checks(ast, if_expr.condition);
+        let before = self.non_null.clone();
+
+        self.non_null.extend(when_true);
+        let then_type = self.check_body(ast, &if_expr.then_branch);
+        let then_diverges = Self::body_diverges(ast, &if_expr.then_branch);
+        let after_then = std::mem::replace(&mut self.non_null, before);
+
+        self.non_null.extend(when_false);
+        let (else_type, else_diverges) = match &if_expr.else_branch {
+            Some(else_branch) => (
+                Some(self.check_body(ast, &else_branch.body)),
+                Self::body_diverges(ast, &else_branch.body),
+            ),
+            None => (None, false),
+        };
+        let after_else = std::mem::take(&mut self.non_null);
+
+        self.non_null = match (then_diverges, else_diverges) {
+            (true, _) => after_else,
+            (false, true) => after_then,
+            (false, false) => after_then.intersection(&after_else).copied().collect(),
+        };
+
+        // A branch that returns has no value, so the other decides the type.
+        let ty = match (&if_expr.else_branch, else_type) {
+            (Some(_), Some(else_type)) if then_diverges => else_type,
+            (Some(_), Some(_)) if else_diverges => then_type,
+            (Some(else_branch), Some(else_type)) => match then_type.join(&else_type) {
+                Some(ty) => ty,
+                None => {
+                    let span = match Self::body_value(ast, &else_branch.body) {
+                        Some(value) => ast.query_expr(value).span(ast),
+                        None => else_branch.body.span(),
+                    };
+                    self.mismatched_branches
+                        .insert(expr.id, (then_type, else_type, span));
+                    Type::Void
+                }
+            },
+            _ => Type::Void,
+        };
+        ast.set_type(expr.id, ty);
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        let initializer_type = let_stmt
+            .initializer
+            .map(|initializer| self.check_expression(ast, initializer));
+        let ty = match (&let_stmt.type_annotation, &initializer_type) {
+            (Some(annotation), _) => {
+                let declared = self.resolve_type(&annotation.type_name, &annotation.question_mark);
+                if let (Some(initializer), Some(initializer_type)) =
+                    (let_stmt.initializer, &initializer_type)
+                {
+                    self.expect_assignable(ast, initializer, initializer_type, &declared);
+                }
+                declared
+            }
+            (None, Some(Type::Null)) => {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_cannot_infer_null_type(
+                        let_stmt.identifier.span.literal(self.source_map),
+                        &let_stmt.identifier.span,
+                    );
+                Type::Error
+            }
+            (None, Some(initializer_type)) => initializer_type.clone(),
+            (None, None) => {
+                self.diagnostics
+                    .borrow_mut()
+                    .report_missing_type_annotation(
+                        let_stmt.identifier.span.literal(self.source_map),
+                        &let_stmt.identifier.span,
+                    );
+                Type::Error
+            }
+        };
+        if let_stmt.variable_idx == VariableIdx::unreachable() {
+            return;
+        }
+        self.global_scope.variables[let_stmt.variable_idx].ty = ty;
+        match &initializer_type {
+            Some(initializer_type) => self.record_assignment(let_stmt.variable_idx, initializer_type),
+            None => {
+                self.non_null.remove(&let_stmt.variable_idx);
+            }
+        }
+    }
+
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expr: &CallExpr, expr: &Expr) {
+        let argument_types: Vec<Type> = call_expr
+            .arguments
+            .iter()
+            .map(|argument| self.check_expression(ast, *argument))
+            .collect();
+
+        // The callee may assign any global, so facts about globals are lost.
+        let variables = &self.global_scope.variables;
+        self.non_null
+            .retain(|variable_idx| !variables[*variable_idx].is_global);
+
+        if call_expr.function_idx == FunctionIdx::unreachable() {
+            ast.set_type(expr.id, Type::Error);
+            return;
+        }
+        let function = &self.global_scope.functions[call_expr.function_idx];
+        let return_type = function.return_type.clone();
+        let parameter_types: Vec<Type> = function
+            .parameters
+            .iter()
+            .map(|parameter| self.global_scope.variables[*parameter].ty.clone())
+            .collect();
+
+        if parameter_types.len() != argument_types.len() {
+            self.diagnostics.borrow_mut().report_invalid_argument_count(
+                call_expr.function_name(self.source_map),
+                &call_expr.callee.span,
+                parameter_types.len(),
+                argument_types.len(),
+            );
+        }
+        for ((argument, actual), expected) in call_expr
+            .arguments
+            .iter()
+            .zip(argument_types.iter())
+            .zip(parameter_types.iter())
+        {
+            self.expect_assignable(ast, *argument, actual, expected);
+        }
+        ast.set_type(expr.id, return_type);
+    }
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, expr: &Expr) {
+        let value = self.check_expression(ast, assign_expr.expression);
+        if assign_expr.variable_idx == VariableIdx::unreachable() {
+            ast.set_type(expr.id, Type::Error);
+            return;
+        }
+        let declared = self.global_scope.variables[assign_expr.variable_idx].ty.clone();
+        self.expect_assignable(ast, assign_expr.expression, &value, &declared);
+        self.record_assignment(assign_expr.variable_idx, &value);
+        ast.set_type(expr.id, self.variable_type(assign_expr.variable_idx));
+    }
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, var_expr: &VarExpr, expr: &Expr) {
+        ast.set_type(expr.id, self.variable_type(var_expr.variable_idx));
+    }
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, _number_expr: &NumberExpr, expr: &Expr) {
+        ast.set_type(expr.id, Type::Int);
+    }
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, _bool_expr: &BoolExpr, expr: &Expr) {
+        ast.set_type(expr.id, Type::Bool);
+    }
+
+    fn visit_null_expression(&mut self, ast: &mut Ast, _null_expr: &NullExpr, expr: &Expr) {
+        ast.set_type(expr.id, Type::Null);
+    }
+
+    fn visit_string_expression(&mut self, ast: &mut Ast, string_expr: &StringExpr, expr: &Expr) {
+        for part in &string_expr.parts {
+            let StringPart::Interpolation(part) = part else {
+                continue;
+            };
+            let ty = self.check_expression(ast, *part);
+            let ty = self.expect_non_null(ast, *part, ty);
+            if !matches!(ty, Type::Int | Type::Bool | Type::String | Type::Error) {
+                let span = ast.query_expr(*part).span(ast);
+                self.diagnostics
+                    .borrow_mut()
+                    .report_cannot_interpolate(&ty, &span);
+            }
+        }
+        ast.set_type(expr.id, Type::String);
+    }
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, expr: &Expr) {
+        let operand = self.check_expression(ast, unary_expr.operand);
+        let operand = self.expect_non_null(ast, unary_expr.operand, operand);
+        self.expect_assignable(ast, unary_expr.operand, &operand, &Type::Int);
+        ast.set_type(expr.id, Type::Int);
+    }
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expr: &BinaryExpr, expr: &Expr) {
+        let left = self.check_expression(ast, binary_expr.left);
+        let right = self.check_expression(ast, binary_expr.right);
+        let ty = match binary_expr.operator.kind {
+            BinOpKind::Equalsto | BinOpKind::NotEqualsto => {
+                let (left, right) = match (&left, &right) {
+                    (Type::Null, _) => {
+                        let right = self.compared_with_null_type(ast, binary_expr.right, right);
+                        (left, right)
+                    }
+                    (_, Type::Null) => {
+                        (self.compared_with_null_type(ast, binary_expr.left, left), right)
+                    }
+                    _ => (left, right),
+                };
+                if !left.is_comparable_to(&right) {
+                    self.diagnostics.borrow_mut().report_invalid_binary_operands(
+                        binary_expr.operator.token.span.literal(self.source_map),
+                        &binary_expr.operator.token.span,
+                        &left,
+                        &right,
+                    );
+                }
+                Type::Bool
+            }
+            _ => {
+                let left = self.expect_non_null(ast, binary_expr.left, left);
+                let right = self.expect_non_null(ast, binary_expr.right, right);
+                let is_comparison = matches!(
+                    binary_expr.operator.kind,
+                    BinOpKind::LessThan
+                        | BinOpKind::LessThanEqualTo
+                        | BinOpKind::GreaterThan
+                        | BinOpKind::GreaterThanEqualTo
+                );
+                // Strings support concatenation and ordering, everything
+                // else is integer arithmetic.
+                let operand_type = if left == Type::String || right == Type::String {
+                    Type::String
+                } else {
+                    Type::Int
+                };
+                let is_supported = operand_type == Type::Int
+                    || is_comparison
+                    || binary_expr.operator.kind == BinOpKind::Plus;
+                if !is_supported
+                    || !left.is_assignable_to(&operand_type)
+                    || !right.is_assignable_to(&operand_type)
+                {
+                    self.diagnostics.borrow_mut().report_invalid_binary_operands(
+                        binary_expr.operator.token.span.literal(self.source_map),
+                        &binary_expr.operator.token.span,
+                        &left,
+                        &right,
+                    );
+                }
+                if is_comparison {
+                    Type::Bool
+                } else {
+                    operand_type
+                }
+            }
+        };
+        ast.set_type(expr.id, ty);
+    }
+
+    fn visit_parenthesized_expression(
+        &mut self,
+        ast: &mut Ast,
+        parenthesized_expr: &ParenthesizedExpr,
+        expr: &Expr,
+    ) {
+        let ty = self.check_expression(ast, parenthesized_expr.inner);
+        ast.set_type(expr.id, ty);
+    }
+}
+
+/// Collects every variable assigned anywhere inside a loop, and whether it
+/// calls any function.
+#[derive(Default)]
+struct AssignedVariables {
+    variables: HashSet<VariableIdx>,
+    has_call: bool,
+}
+
+impl ASTVisitor for AssignedVariables {
+    fn visit_func_decl(&mut self, _ast: &mut Ast, _func_decl: &FuncDeclaration, _item_id: ItemId) {}
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        if let Some(initializer) = let_stmt.initializer {
+            self.visit_expression(ast, initializer);
+        }
+    }
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, _expr: &Expr) {
+        self.variables.insert(assign_expr.variable_idx);
+        self.visit_expression(ast, assign_expr.expression);
+    }
+
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expr: &CallExpr, _expr: &Expr) {
+        self.has_call = true;
+        for argument in &call_expr.arguments {
+            self.visit_expression(ast, *argument);
+        }
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, _var_expr: &VarExpr, _expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    compilation_unit::{GlobalScope, VariableIdx},
+    definitions::{
+        lexer::Token, visitor::ASTVisitor, AssignExpr, Ast, BlockExpr, Body, BoolExpr, Expr,
+        ExprId, ExprKind, FuncDeclaration, IfExpr, ItemId, LetStmt, NullExpr, NumberExpr,
+        ReturnStmt, Stmt, StmtId, UnaryExpr, VarExpr, WhileStmt,
+    },
+    diagnostics::{DiagnosticsBagCell, ErrorCode},
+    lint::{Lint, LintDiagnostic},
+    text::span::TextSpan,
+    typecheck::Type,
+};
+
+/// What is known at a point in the program: whether control can reach it
+/// at all, and which variables may not have been assigned yet.
+#[derive(Debug, Clone, Default)]
+struct FlowState {
+    reachable: bool,
+    maybe_unassigned: HashSet<VariableIdx>,
+}
+
+impl FlowState {
+    fn reachable() -> Self {
+        Self {
+            reachable: true,
+            maybe_unassigned: HashSet::new(),
+        }
+    }
+
+    /// The state where two branches meet again. A branch that never falls
+    /// through doesn't contribute anything to the code after it.
+    fn join(self, other: FlowState) -> FlowState {
+        match (self.reachable, other.reachable) {
+            (true, false) => self,
+            (false, true) => other,
+            _ => FlowState {
+                reachable: self.reachable,
+                maybe_unassigned: self
+                    .maybe_unassigned
+                    .union(&other.maybe_unassigned)
+                    .copied()
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Checks how control moves through function bodies: every path of a
+/// function returning a value must end in `return`, statements that can
+/// never run are flagged, and a variable declared without an initializer
+/// must be assigned on every path before it is read.
+///
+/// Runs after type checking, since it needs the resolved return types.
+/// Unreachable statements are diagnostics of the `unreachable_code` lint,
+/// which are handed to the lint driver rather than reported directly.
+pub struct FlowAnalyzer<'a> {
+    global_scope: &'a GlobalScope,
+    diagnostics: DiagnosticsBagCell,
+    state: FlowState,
+    declarations: HashMap<VariableIdx, Token>,
+    item: Option<ItemId>,
+    unreachable: Vec<LintDiagnostic>,
+}
+
+impl<'a> FlowAnalyzer<'a> {
+    pub fn new(global_scope: &'a GlobalScope, diagnostics: DiagnosticsBagCell) -> Self {
+        Self {
+            global_scope,
+            diagnostics,
+            state: FlowState::reachable(),
+            declarations: HashMap::new(),
+            item: None,
+            unreachable: Vec::new(),
+        }
+    }
+
+    /// Analyzes every item, returning the diagnostics of the
+    /// `unreachable_code` lint.
+    pub fn analyze(mut self, ast: &mut Ast) -> Vec<LintDiagnostic> {
+        ast.visit(&mut self);
+        self.unreachable
+    }
+
+    fn visit_statements(&mut self, ast: &mut Ast, stmts: &[StmtId]) {
+        let mut reported = !self.state.reachable;
+        for stmt in stmts {
+            if !self.state.reachable && !reported {
+                let span = ast.query_stmt(*stmt).span(ast);
+                let mut diagnostic = LintDiagnostic::new(
+                    Lint::UnreachableCode,
+                    "Unreachable statement".to_string(),
+                    span,
+                )
+                .with_code(ErrorCode::UnreachableStatement);
+                diagnostic.item = self.item;
+                self.unreachable.push(diagnostic);
+                reported = true;
+            }
+            self.visit_statement(ast, *stmt);
+        }
+    }
+
+    /// Whether the condition is the literal `true`, i.e. the loop can only
+    /// be left through `return`.
+    fn is_always_true(ast: &Ast, condition: ExprId) -> bool {
+        match &ast.query_expr(condition).kind {
+            ExprKind::Boolean(bool_expr) => bool_expr.value,
+            ExprKind::Parenthesized(parenthesized_expr) => {
+                Self::is_always_true(ast, parenthesized_expr.inner)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ASTVisitor for FlowAnalyzer<'_> {
+    fn visit_item(&mut self, ast: &mut Ast, item: ItemId) {
+        self.item = Some(item);
+        self.visit_item_default(ast, item);
+    }
+
+    fn visit_func_decl(&mut self, ast: &mut Ast, func_decl: &FuncDeclaration, _item_id: ItemId) {
+        let previous_state = std::mem::replace(&mut self.state, FlowState::reachable());
+        self.visit_body(ast, &func_decl.body);
+        let function = &self.global_scope.functions[func_decl.idx];
+        if self.state.reachable && !matches!(function.return_type, Type::Void | Type::Error) {
+            self.diagnostics.borrow_mut().report_not_all_paths_return(
+                &function.name,
+                &func_decl.identifier.span,
+                &func_decl.body.close_brace.span,
+            );
+        }
+        self.state = previous_state;
+    }
+
+    fn visit_body(&mut self, ast: &mut Ast, body: &Body) {
+        self.visit_statements(ast, body);
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, _expr: &Expr) {
+        self.visit_statements(ast, &block_expr.stmts);
+    }
+
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_stmt: &ReturnStmt) {
+        if let Some(return_value) = return_stmt.return_value {
+            self.visit_expression(ast, return_value);
+        }
+        self.state.reachable = false;
+    }
+
+    fn visit_if_expression(&mut self, ast: &mut Ast, if_expr: &IfExpr, _expr: &Expr) {
+        self.visit_expression(ast, if_expr.condition);
+        let before = self.state.clone();
+        self.visit_body(ast, &if_expr.then_branch);
+        let after_then = std::mem::replace(&mut self.state, before);
+        if let Some(else_branch) = &if_expr.else_branch {
+            self.visit_body(ast, &else_branch.body);
+        }
+        let after_else = std::mem::take(&mut self.state);
+        self.state = after_then.join(after_else);
+    }
+
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_stmt: &WhileStmt) {
+        self.visit_expression(ast, while_stmt.condition);
+        // The body may run zero times, so nothing it assigns counts after the
+        // loop; it's still checked for unreachable code and unassigned reads.
+        let before = self.state.clone();
+        self.visit_body(ast, &while_stmt.body);
+        self.state = before;
+        if Self::is_always_true(ast, while_stmt.condition) {
+            self.state.reachable = false;
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_stmt: &LetStmt, _stmt: &Stmt) {
+        match let_stmt.initializer {
+            Some(initializer) => {
+                self.visit_expression(ast, initializer);
+                self.state.maybe_unassigned.remove(&let_stmt.variable_idx);
+            }
+            None => {
+                self.declarations
+                    .insert(let_stmt.variable_idx, let_stmt.identifier.clone());
+                self.state.maybe_unassigned.insert(let_stmt.variable_idx);
+            }
+        }
+    }
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, _expr: &Expr) {
+        self.visit_expression(ast, assign_expr.expression);
+        self.state.maybe_unassigned.remove(&assign_expr.variable_idx);
+    }
+
+    fn visit_variable_expression(&mut self, _ast: &mut Ast, var_expr: &VarExpr, _expr: &Expr) {
+        if !self.state.reachable || !self.state.maybe_unassigned.contains(&var_expr.variable_idx) {
+            return;
+        }
+        if let Some(declaration) = self.declarations.get(&var_expr.variable_idx) {
+            self.diagnostics
+                .borrow_mut()
+                .report_possibly_uninitialized_variable(
+                    &self.global_scope.variables[var_expr.variable_idx].name,
+                    &var_expr.identifier.span,
+                    &declaration.span,
+                );
+        }
+        // Report each variable once per path rather than on every read.
+        self.state.maybe_unassigned.remove(&var_expr.variable_idx);
+    }
+
+    fn visit_number_expression(&mut self, _ast: &mut Ast, _number_expr: &NumberExpr, _expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, _ast: &mut Ast, _bool_expr: &BoolExpr, _expr: &Expr) {}
+
+    fn visit_null_expression(&mut self, _ast: &mut Ast, _null_expr: &NullExpr, _expr: &Expr) {}
+
+    fn visit_error(&mut self, _ast: &mut Ast, _span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expr: &UnaryExpr, _expr: &Expr) {
+        self.visit_expression(ast, unary_expr.operand);
+    }
+}
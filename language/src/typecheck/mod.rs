@@ -1,25 +1,37 @@
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone)]
+use crate::compilation_unit::TypeIdx;
+
+pub mod checker;
+pub mod flow;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     Int,
     Bool,
+    String,
     Void,
+    Null,
+    Nullable(Box<Type>),
+    Struct(TypeIdx, String),
+    Enum(TypeIdx, String),
     Unresolved,
     Error,
 }
 
 impl Display for Type {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let type_name = match self {
-            Type::Int => "int",
-            Type::Bool => "bool",
-            Type::Unresolved => "unresolved",
-            Type::Void => "void",
-            Type::Error => "?",
-        };
-
-        write!(f, "{}", type_name)
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Unresolved => write!(f, "unresolved"),
+            Type::Void => write!(f, "void"),
+            Type::Null => write!(f, "null"),
+            Type::Nullable(inner) => write!(f, "{}?", inner),
+            Type::Struct(_, name) | Type::Enum(_, name) => write!(f, "{}", name),
+            Type::Error => write!(f, "?"),
+        }
     }
 }
 
@@ -28,18 +40,76 @@ impl Type {
         match (self, other) {
             (Type::Int, Type::Int) => true,
             (Type::Bool, Type::Bool) => true,
+            (Type::String, Type::String) => true,
+            (Type::Struct(idx, _), Type::Struct(other_idx, _)) => idx == other_idx,
+            (Type::Enum(idx, _), Type::Enum(other_idx, _)) => idx == other_idx,
             (Type::Error, _) => true,
             (_, Type::Error) => true,
+            (Type::Null, Type::Nullable(_)) => true,
+            (Type::Nullable(inner), Type::Nullable(other_inner)) => {
+                inner.is_assignable_to(other_inner)
+            }
+            (inner, Type::Nullable(other_inner)) => inner.is_assignable_to(other_inner),
             _ => false,
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Type> {
-        match s {
-            "int" => Some(Type::Int),
-            "bool" => Some(Type::Bool),
-            "void" => Some(Type::Void),
+    /// Whether a value of this type may hold `null` at runtime.
+    pub fn can_be_null(&self) -> bool {
+        matches!(self, Type::Null | Type::Nullable(_))
+    }
+
+    /// The type with `null` removed, i.e. what a value narrows to after a
+    /// successful `!= null` check.
+    pub fn without_null(&self) -> Type {
+        match self {
+            Type::Nullable(inner) => (**inner).clone(),
+            Type::Null => Type::Error,
+            ty => ty.clone(),
+        }
+    }
+
+    pub fn into_nullable(self) -> Type {
+        match self {
+            Type::Nullable(_) | Type::Null | Type::Error => self,
+            ty => Type::Nullable(Box::new(ty)),
+        }
+    }
+
+    /// Whether `==` and `!=` may compare values of the two types. `null`
+    /// only compares with types that can hold it.
+    pub fn is_comparable_to(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Void, _) | (_, Type::Void) => false,
+            (Type::Null, ty) | (ty, Type::Null) => ty.can_be_null() || *ty == Type::Error,
+            _ => {
+                let left = self.without_null();
+                let right = other.without_null();
+                left.is_assignable_to(&right) || right.is_assignable_to(&left)
+            }
+        }
+    }
+
+    /// The narrowest type both branches of an `if`/`else` fit into, if any.
+    pub fn join(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Error, _) | (_, Type::Error) => Some(Type::Error),
+            _ if self == other => Some(self.clone()),
+            (Type::Null, ty) | (ty, Type::Null) => Some(ty.clone().into_nullable()),
+            _ if self.is_assignable_to(other) => Some(other.clone()),
+            _ if other.is_assignable_to(self) => Some(self.clone()),
             _ => None,
         }
     }
-}
\ No newline at end of file
+
+    /// The type names every program can use without declaring them. They
+    /// live in the global scope next to user-defined types.
+    pub fn builtins() -> Vec<(&'static str, Type)> {
+        vec![
+            ("int", Type::Int),
+            ("bool", Type::Bool),
+            ("string", Type::String),
+            ("void", Type::Void),
+        ]
+    }
+}
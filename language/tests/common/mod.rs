@@ -0,0 +1,11 @@
+use nsharp_compiler::{compilation_unit::CompilationUnit, text::source_map::SourceMap};
+
+/// Compiles `source` as the only file of a program, named `name`, and
+/// panics if it doesn't compile.
+pub fn compile(name: &str, source: &str) -> (SourceMap, CompilationUnit) {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file(name.to_string(), source.to_string());
+    let compilation_unit = CompilationUnit::compile(&source_map, file_id)
+        .unwrap_or_else(|_| panic!("failed to compile:\n{}", source));
+    (source_map, compilation_unit)
+}
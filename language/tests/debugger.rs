@@ -0,0 +1,154 @@
+use nsharp_compiler::definitions::{
+    debugger::{Breakpoint, DebugCommand, DebugFrontend, Debugger, Pause, PauseReason},
+    runtime_error::RuntimeErrorKind,
+};
+
+mod common;
+
+/// A front end answering each pause with the next of its commands and
+/// recording the pauses.
+struct Script<'a> {
+    commands: Vec<DebugCommand>,
+    pauses: &'a mut Vec<Pause>,
+}
+
+impl<'a> DebugFrontend for Script<'a> {
+    fn paused(&mut self, pause: &Pause, _breakpoints: &mut Vec<Breakpoint>) -> DebugCommand {
+        self.pauses.push(pause.clone());
+        if self.commands.is_empty() {
+            DebugCommand::Continue
+        } else {
+            self.commands.remove(0)
+        }
+    }
+}
+
+/// Runs the source as `scripts/main.ns` under a debugger, returning the
+/// pauses and the line each paused at.
+fn debug(
+    source: &str,
+    breakpoints: &[&str],
+    stop_at_entry: bool,
+    commands: Vec<DebugCommand>,
+) -> (Vec<Pause>, Vec<usize>, Option<RuntimeErrorKind>) {
+    let (source_map, mut compilation_unit) = common::compile("scripts/main.ns", source);
+    let mut pauses = Vec::new();
+    let mut debugger = Debugger::new(
+        &source_map,
+        Script {
+            commands,
+            pauses: &mut pauses,
+        },
+    );
+    for breakpoint in breakpoints {
+        debugger = debugger.with_breakpoint(Breakpoint::parse(breakpoint).unwrap());
+    }
+    if stop_at_entry {
+        debugger = debugger.stop_at_entry();
+    }
+    let error = compilation_unit
+        .run_with_debugger(debugger)
+        .err()
+        .map(|error| error.kind);
+    let lines = pauses
+        .iter()
+        .map(|pause| {
+            let text = &source_map.get(pause.span.file_id).text;
+            text.line_index(pause.span.start) + 1
+        })
+        .collect();
+    (pauses, lines, error)
+}
+
+const PROGRAM: &str = "\
+func add(a: int, b: int) -> int {
+    let sum = a + b;
+    return sum;
+}
+
+let total = 0;
+let i = 0;
+while i < 3 {
+    total = add(total, i);
+    i = i + 1;
+}
+let done = true;
+";
+
+#[test]
+fn line_breakpoints_pause_every_time_they_are_reached() {
+    let (pauses, lines, error) = debug(PROGRAM, &["main.ns:10"], false, vec![]);
+    assert_eq!(error, None);
+    assert_eq!(lines, [10, 10, 10]);
+    assert!(pauses
+        .iter()
+        .all(|pause| pause.reason == PauseReason::Breakpoint(0)));
+    assert_eq!(pauses[2].variable(0, "total").unwrap().value, "3");
+}
+
+#[test]
+fn function_breakpoints_pause_at_the_first_statement_with_a_backtrace() {
+    let (pauses, lines, _) = debug(PROGRAM, &["add"], false, vec![DebugCommand::Quit]);
+    assert_eq!(lines, [2]);
+    let frames: Vec<String> = pauses[0]
+        .frames
+        .iter()
+        .map(|frame| frame.stack_frame.to_string())
+        .collect();
+    assert_eq!(frames, ["add", "<top level>"]);
+}
+
+#[test]
+fn steps_enter_skip_and_leave_calls() {
+    use DebugCommand::*;
+    let (_, lines, error) = debug(
+        PROGRAM,
+        &[],
+        true,
+        vec![
+            StepOver, StepOver, StepOver, StepIn, StepIn, StepOut, StepOver, Quit,
+        ],
+    );
+    assert_eq!(lines, [6, 7, 8, 9, 2, 3, 10, 9]);
+    assert_eq!(error, Some(RuntimeErrorKind::Interrupted));
+}
+
+#[test]
+fn every_frame_shows_its_variables_by_name() {
+    let (pauses, _, _) = debug(PROGRAM, &["main.ns:3"], false, vec![DebugCommand::Quit]);
+    let variables: Vec<Vec<String>> = pauses[0]
+        .frames
+        .iter()
+        .map(|frame| {
+            frame
+                .variables
+                .iter()
+                .map(|variable| format!("{}: {} = {}", variable.name, variable.ty, variable.value))
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        variables,
+        [
+            vec!["a: int = 0", "b: int = 0", "sum: int = 0"],
+            vec!["total: int = 0", "i: int = 0"],
+        ]
+    );
+}
+
+#[test]
+fn breakpoints_parse_from_text() {
+    assert_eq!(
+        Breakpoint::parse("src/main.ns:12"),
+        Some(Breakpoint::Line {
+            file: "src/main.ns".to_string(),
+            line: 12
+        })
+    );
+    assert_eq!(
+        Breakpoint::parse("add"),
+        Some(Breakpoint::Function("add".to_string()))
+    );
+    assert_eq!(Breakpoint::parse("main.ns:0"), None);
+    assert_eq!(Breakpoint::parse(""), None);
+}
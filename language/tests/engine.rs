@@ -0,0 +1,160 @@
+use nsharp_compiler::{
+    definitions::{
+        eval::Value,
+        limits::Limits,
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+    },
+    engine::{Engine, EngineError, ScriptValue},
+    typecheck::Type,
+};
+
+const PROGRAM: &str = "\
+let calls = 0;
+
+func add(a: int, b: int) -> int {
+    calls = calls + 1;
+    return a + b;
+}
+
+func greet(name: string?) -> string {
+    calls = calls + 1;
+    if name != null {
+        return \"hello, \" + name;
+    }
+    return \"hello, stranger\";
+}
+
+func divide(a: int, b: int) -> int {
+    return a / b;
+}
+";
+
+#[test]
+fn calls_convert_values_both_ways() {
+    let mut program = Engine::new().compile(PROGRAM).unwrap();
+    let sum: i64 = program.call("add", &[1.into(), 2.into()]).unwrap();
+    assert_eq!(sum, 3);
+    let greeting: String = program.call("greet", &["ada".into()]).unwrap();
+    assert_eq!(greeting, "hello, ada");
+    let greeting: String = program
+        .call("greet", &[Option::<&str>::None.into()])
+        .unwrap();
+    assert_eq!(greeting, "hello, stranger");
+    let value: ScriptValue = program.call("add", &[4.into(), 5.into()]).unwrap();
+    assert_eq!(value, ScriptValue::Int(9));
+}
+
+#[test]
+fn globals_persist_between_calls() {
+    let mut program = Engine::new().compile(PROGRAM).unwrap();
+    assert_eq!(program.global("calls"), None);
+    for _ in 0..3 {
+        program.call::<i64>("add", &[0.into(), 0.into()]).unwrap();
+    }
+    assert_eq!(program.global("calls"), Some(ScriptValue::Int(3)));
+
+    program.run().unwrap();
+    assert_eq!(program.global("calls"), Some(ScriptValue::Int(0)));
+}
+
+#[test]
+fn mismatched_calls_are_typed_errors() {
+    let mut program = Engine::new().compile(PROGRAM).unwrap();
+    match program.call::<i64>("sub", &[]) {
+        Err(EngineError::UnknownFunction(name)) => assert_eq!(name, "sub"),
+        other => panic!("unexpected {:?}", other),
+    }
+    let error = program
+        .call::<i64>("add", &["1".into(), 2.into()])
+        .unwrap_err();
+    assert!(matches!(error, EngineError::Arguments { .. }));
+    assert_eq!(
+        error.to_string(),
+        "'add' takes (int, int), but was given (string, int)"
+    );
+    let error = program
+        .call::<bool>("add", &[1.into(), 2.into()])
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        EngineError::ReturnValue {
+            expected: "bool",
+            found: ScriptValue::Int(3),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn errors_carry_diagnostics() {
+    let error = Engine::new()
+        .compile_named("broken.ns", "let x: int = \"one\";")
+        .unwrap_err();
+    let EngineError::Compile(error) = error else {
+        panic!("expected a compile error");
+    };
+    assert_eq!(error.diagnostics.len(), 1);
+    assert!(error.render().contains("broken.ns:1"), "{}", error.render());
+
+    // Warnings are reported alongside the errors but not counted as ones.
+    let error = Engine::new()
+        .compile("#[deny(unused_variables)]\nfunc f() {\n    let x = 1;\n}\nfunc g() {}\nf();")
+        .unwrap_err();
+    let message = error.to_string();
+    let EngineError::Compile(error) = error else {
+        panic!("expected a compile error");
+    };
+    assert_eq!(error.diagnostics.len(), 2);
+    assert_eq!(message, "could not compile the program due to 1 errors");
+
+    let mut program = Engine::new().compile(PROGRAM).unwrap();
+    let error = program
+        .call::<i64>("divide", &[1.into(), 0.into()])
+        .unwrap_err();
+    let EngineError::Runtime(error) = error else {
+        panic!("expected a runtime error");
+    };
+    assert_eq!(error.kind, RuntimeErrorKind::DivisionByZero);
+    assert!(program.render_error(&error).contains("a / b"));
+}
+
+#[test]
+fn hosts_configure_natives_and_limits() {
+    let mut engine = Engine::new().with_limits(Limits::new().with_max_steps(1_000));
+    engine.register("twice", &[("n", Type::Int)], Type::Int, |_, arguments| {
+        let n = arguments[0].expect_number()?;
+        n.checked_mul(2)
+            .map(Value::Number)
+            .ok_or(RuntimeErrorKind::Overflow)
+    });
+    let mut program = engine
+        .compile("func run(n: int) -> int { while n > 0 { n = n + 1; } return twice(n); }")
+        .unwrap();
+    assert!(matches!(
+        program.call::<i64>("run", &[1.into()]),
+        Err(EngineError::Runtime(RuntimeError {
+            kind: RuntimeErrorKind::StepLimitExceeded { .. },
+            ..
+        }))
+    ));
+    let doubled: i64 = program.call("twice", &[21.into()]).unwrap();
+    assert_eq!(doubled, 42);
+}
+
+#[test]
+fn strings_from_earlier_calls_are_freed() {
+    let mut program = Engine::new()
+        .with_limits(Limits::new().with_max_heap_size(64 * 1024))
+        .compile(
+            "let last = \"\";\nfunc label(n: int) -> string { last = \"item {n}\"; return last; }",
+        )
+        .unwrap();
+    for n in 0..50_000 {
+        let label: String = program.call("label", &[n.into()]).unwrap();
+        assert_eq!(label, format!("item {}", n));
+    }
+    assert_eq!(
+        program.global("last"),
+        Some(ScriptValue::String("item 49999".to_string()))
+    );
+}
@@ -0,0 +1,109 @@
+use std::{env, fs, process::Command};
+
+use nsharp_compiler::{
+    diagnostics::{json::JsonEmitter, sarif::SarifEmitter, Diagnostic},
+    text::source_map::SourceMap,
+};
+
+mod common;
+
+/// A warning, then a runtime error on a line with multi-byte characters in
+/// front of it, so byte and character columns disagree.
+const PROGRAM: &str = "\
+func unused() {}
+func zero() -> int { return 0; }
+let _ = \"é😀 {10 / zero()}\";
+";
+
+/// The warnings of compiling `PROGRAM` followed by the error running it.
+fn diagnostics() -> (SourceMap, Vec<Diagnostic>) {
+    let (source_map, mut compilation_unit) = common::compile("formats.ns", PROGRAM);
+    let mut diagnostics = compilation_unit
+        .diagnostics_bag
+        .borrow()
+        .diagnostics
+        .clone();
+    let error = compilation_unit.run().unwrap_err();
+    diagnostics.push(error.to_diagnostic());
+    (source_map, diagnostics)
+}
+
+#[test]
+fn json_has_one_based_lines_and_character_columns() {
+    let (source_map, diagnostics) = diagnostics();
+    let emitter = JsonEmitter::new(&source_map);
+    let lines: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| emitter.emit(diagnostic).to_string())
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            concat!(
+                r#"{"code":null,"lint":"unused_functions","severity":"warning","#,
+                r#""message":"Unused function 'unused'","#,
+                r#""span":{"file":"formats.ns","byte_start":5,"byte_end":11,"#,
+                r#""line_start":1,"column_start":6,"line_end":1,"column_end":12},"#,
+                r#""labels":[],"#,
+                r#""help":"if this is intentional, prefix it with an underscore: '_unused'","#,
+                r#""suggestions":[{"message":"rename to '_unused'","replacement":"_unused","#,
+                r#""span":{"file":"formats.ns","byte_start":5,"byte_end":11,"#,
+                r#""line_start":1,"column_start":6,"line_end":1,"column_end":12}}]}"#,
+            ),
+            concat!(
+                r#"{"code":null,"lint":null,"severity":"error","#,
+                r#""message":"division by zero","#,
+                r#""span":{"file":"formats.ns","byte_start":67,"byte_end":78,"#,
+                r#""line_start":3,"column_start":14,"line_end":3,"column_end":25},"#,
+                r#""labels":[],"help":null,"suggestions":[]}"#,
+            ),
+        ]
+    );
+}
+
+#[test]
+fn sarif_has_one_based_lines_and_character_columns() {
+    let (source_map, diagnostics) = diagnostics();
+    let log = SarifEmitter::new(&source_map)
+        .emit(&diagnostics)
+        .to_string();
+    assert_eq!(
+        log,
+        concat!(
+            r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","#,
+            r#""runs":[{"tool":{"driver":{"name":"nsharp","version":""#,
+            env!("CARGO_PKG_VERSION"),
+            r#"","rules":[{"id":"unused_functions","name":"unused_functions"}]}},"#,
+            r#""columnKind":"unicodeCodePoints","results":["#,
+            r#"{"ruleId":"unused_functions","level":"warning","#,
+            r#""message":{"text":"Unused function 'unused'\nhelp: if this is intentional, prefix it with an underscore: '_unused'"},"#,
+            r#""locations":[{"physicalLocation":{"artifactLocation":{"uri":"formats.ns"},"#,
+            r#""region":{"startLine":1,"startColumn":6,"endLine":1,"endColumn":12,"byteOffset":5,"byteLength":6}}}],"#,
+            r#""fixes":[{"description":{"text":"rename to '_unused'"},"#,
+            r#""artifactChanges":[{"artifactLocation":{"uri":"formats.ns"},"#,
+            r#""replacements":[{"deletedRegion":{"startLine":1,"startColumn":6,"endLine":1,"endColumn":12,"byteOffset":5,"byteLength":6},"#,
+            r#""insertedContent":{"text":"_unused"}}]}]}]},"#,
+            r#"{"level":"error","message":{"text":"division by zero"},"#,
+            r#""locations":[{"physicalLocation":{"artifactLocation":{"uri":"formats.ns"},"#,
+            r#""region":{"startLine":3,"startColumn":14,"endLine":3,"endColumn":25,"byteOffset":67,"byteLength":11}}}]}"#,
+            r#"]}]}"#,
+        )
+    );
+}
+
+#[test]
+fn warnings_and_runtime_errors_share_one_sarif_log() {
+    let path = env::temp_dir().join(format!("nsharp-formats-{}.ns", std::process::id()));
+    fs::write(&path, PROGRAM).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nsharp-compiler"))
+        .arg("--error-format=sarif")
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1, "{}", stderr);
+    assert_eq!(stderr.matches(r#""level":"warning""#).count(), 1);
+    assert_eq!(stderr.matches(r#""level":"error""#).count(), 1);
+}
@@ -0,0 +1,87 @@
+use nsharp_compiler::{
+    compilation_unit::CompilationUnit,
+    diagnostics::{
+        explain::{examples, explanation},
+        ErrorCode,
+    },
+    text::source_map::SourceMap,
+};
+
+/// The codes of the errors `source` fails to compile with, or `None` if it
+/// compiles.
+fn compile_errors(code: ErrorCode, index: usize, source: &str) -> Option<Vec<ErrorCode>> {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file(format!("{}-example-{}.ns", code, index), source.to_string());
+    match CompilationUnit::compile(&source_map, file_id) {
+        Ok(_) => None,
+        Err(diagnostics_bag) => Some(
+            diagnostics_bag
+                .borrow()
+                .diagnostics
+                .iter()
+                .filter_map(|diagnostic| diagnostic.code)
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn every_code_has_failing_and_fixed_examples() {
+    for code in ErrorCode::all() {
+        let examples = examples(explanation(*code));
+        assert!(
+            examples
+                .iter()
+                .any(|example| example.compile_fail && example.expected_error == Some(*code)),
+            "{} has no example failing with it",
+            code
+        );
+        assert!(
+            examples.iter().any(|example| !example.compile_fail),
+            "{} has no example that compiles",
+            code
+        );
+    }
+}
+
+#[test]
+fn examples_behave_as_documented() {
+    for code in ErrorCode::all() {
+        for (index, example) in examples(explanation(*code)).iter().enumerate() {
+            let errors = compile_errors(*code, index, example.source);
+            match (example.compile_fail, errors) {
+                (true, Some(errors)) => {
+                    if let Some(expected) = example.expected_error {
+                        assert!(
+                            errors.contains(&expected),
+                            "example {} of {} failed with {:?} instead of {}:\n{}",
+                            index,
+                            code,
+                            errors,
+                            expected,
+                            example.source
+                        );
+                    }
+                }
+                (true, None) => panic!(
+                    "example {} of {} should fail to compile:\n{}",
+                    index, code, example.source
+                ),
+                (false, Some(errors)) => panic!(
+                    "example {} of {} should compile, failed with {:?}:\n{}",
+                    index, code, errors, example.source
+                ),
+                (false, None) => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn codes_round_trip_through_parse() {
+    for code in ErrorCode::all() {
+        assert_eq!(ErrorCode::parse(&code.to_string()), Some(*code));
+    }
+    assert_eq!(ErrorCode::parse("E0099"), None);
+    assert_eq!(ErrorCode::parse("0010"), None);
+}
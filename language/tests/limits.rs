@@ -0,0 +1,202 @@
+use std::{thread, time::Duration};
+
+use nsharp_compiler::{
+    compilation_unit::CompilationUnit,
+    definitions::{
+        eval::ASTEval,
+        limits::{InterruptHandle, Limits, MAX_NESTING_DEPTH},
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+    },
+    diagnostics::ErrorCode,
+    text::source_map::SourceMap,
+};
+
+mod common;
+
+fn run(
+    source: &str,
+    limits: Limits,
+    interrupt_handle: InterruptHandle,
+) -> Result<(), RuntimeError> {
+    let (_, mut compilation_unit) = common::compile("test.ns", source);
+    compilation_unit.run_with_limits(limits, interrupt_handle)
+}
+
+fn run_error(source: &str, limits: Limits) -> RuntimeErrorKind {
+    run(source, limits, InterruptHandle::new())
+        .expect_err("expected a runtime error")
+        .kind
+}
+
+const COUNT_TO_100: &str = "\
+let i = 0;
+while i < 100 {
+    i = i + 1;
+}
+";
+
+#[test]
+fn step_limit_stops_infinite_loops() {
+    let error = run_error("while true {}", Limits::new().with_max_steps(1000));
+    assert_eq!(error, RuntimeErrorKind::StepLimitExceeded { limit: 1000 });
+}
+
+#[test]
+fn programs_within_the_step_limit_complete() {
+    assert!(run(
+        COUNT_TO_100,
+        Limits::new().with_max_steps(10_000),
+        InterruptHandle::new()
+    )
+    .is_ok());
+    assert_eq!(
+        run_error(COUNT_TO_100, Limits::new().with_max_steps(100)),
+        RuntimeErrorKind::StepLimitExceeded { limit: 100 }
+    );
+}
+
+#[test]
+fn call_depth_is_configurable() {
+    let source = "\
+func depth(n: int) -> int {
+    if n == 0 {
+        return 0;
+    }
+    return 1 + depth(n - 1);
+}
+
+let _x = depth(50);
+";
+    assert!(run(source, Limits::new(), InterruptHandle::new()).is_ok());
+    assert_eq!(
+        run_error(source, Limits::new().with_max_call_depth(10)),
+        RuntimeErrorKind::StackOverflow { depth: 10 }
+    );
+}
+
+#[test]
+fn heap_limit_stops_growing_strings() {
+    let source = "\
+let text = \"data\";
+while true {
+    text = text + text;
+}
+";
+    let error = run_error(source, Limits::new().with_max_heap_size(1 << 20));
+    assert_eq!(
+        error,
+        RuntimeErrorKind::HeapLimitExceeded { limit: 1 << 20 }
+    );
+}
+
+#[test]
+fn strings_nothing_refers_to_are_freed() {
+    // About 400 KB of strings, none of which outlives its iteration.
+    let source = "\
+let i = 0;
+while i < 20000 {
+    let line = \"line {i} of many\";
+    i = i + 1;
+}
+";
+    assert_eq!(
+        run(
+            source,
+            Limits::new().with_max_heap_size(64 * 1024),
+            InterruptHandle::new()
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn interrupt_handle_cancels_from_another_thread() {
+    let interrupt_handle = InterruptHandle::new();
+    let interrupter = {
+        let interrupt_handle = interrupt_handle.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupt_handle.interrupt();
+        })
+    };
+    let error = run("while true {}", Limits::new(), interrupt_handle.clone())
+        .expect_err("expected an interruption");
+    interrupter.join().unwrap();
+    assert_eq!(error.kind, RuntimeErrorKind::Interrupted);
+
+    interrupt_handle.reset();
+    assert!(run(COUNT_TO_100, Limits::new(), interrupt_handle).is_ok());
+}
+
+/// `let x = ((..(1)..));` with `depth` pairs of parentheses.
+fn nested_parentheses(depth: usize) -> String {
+    format!("let x = {}1{};", "(".repeat(depth), ")".repeat(depth))
+}
+
+fn compile_errors(source: &str) -> Vec<Option<ErrorCode>> {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file("test.ns".to_string(), source.to_string());
+    match CompilationUnit::compile(&source_map, file_id) {
+        Ok(_) => Vec::new(),
+        Err(diagnostics_bag) => diagnostics_bag
+            .borrow()
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect(),
+    }
+}
+
+#[test]
+fn deeply_nested_code_fails_to_compile() {
+    // The statement and its initializer are the first two levels.
+    assert_eq!(
+        compile_errors(&nested_parentheses(MAX_NESTING_DEPTH - 1)),
+        [Some(ErrorCode::NestingTooDeep)]
+    );
+    assert_eq!(
+        compile_errors(&nested_parentheses(20000)),
+        [Some(ErrorCode::NestingTooDeep)]
+    );
+    let blocks = format!("{}{}", "while true {".repeat(20000), "}".repeat(20000));
+    assert_eq!(compile_errors(&blocks), [Some(ErrorCode::NestingTooDeep)]);
+    let negations = format!("let x = {}1;", "-".repeat(20000));
+    assert_eq!(
+        compile_errors(&negations),
+        [Some(ErrorCode::NestingTooDeep)]
+    );
+    let interpolations = format!("let x = {}1{};", "\"{".repeat(2000), "}\"".repeat(2000));
+    assert_eq!(
+        compile_errors(&interpolations),
+        [Some(ErrorCode::NestingTooDeep)]
+    );
+}
+
+#[test]
+fn code_nested_up_to_the_limit_runs() {
+    for source in [
+        format!(
+            "{}\nprintln(\"{{x}}\");",
+            nested_parentheses(MAX_NESTING_DEPTH - 2)
+        ),
+        format!(
+            "let x = 0;\n{}x = 1;{}\nprintln(\"{{x}}\");",
+            "if x == 0 {".repeat(MAX_NESTING_DEPTH / 2 - 2),
+            "}".repeat(MAX_NESTING_DEPTH / 2 - 2)
+        ),
+        format!(
+            "let x = 1{};\nprintln(\"{{x}}\");",
+            " ** 1".repeat(MAX_NESTING_DEPTH - 2)
+        ),
+    ] {
+        let (_, mut compilation_unit) = common::compile("test.ns", &source);
+        let mut output = Vec::new();
+        {
+            let mut eval = ASTEval::new(&compilation_unit.global_scope).with_output(&mut output);
+            eval.strings = compilation_unit.strings().clone();
+            eval.run(&mut compilation_unit.ast).unwrap();
+        }
+        assert_eq!(output, b"1\n");
+        assert!(run(&source, Limits::new(), InterruptHandle::new()).is_ok());
+    }
+}
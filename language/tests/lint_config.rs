@@ -0,0 +1,122 @@
+use std::{env, fs};
+
+use nsharp_compiler::lint::{config::LintConfig, Lint, LintLevel};
+
+fn errors(text: &str) -> Vec<(usize, String)> {
+    LintConfig::parse(text).expect_err("expected the config to be rejected")
+}
+
+#[test]
+fn levels_are_read_from_the_lints_table() {
+    let config = LintConfig::parse(
+        "\
+# A project.
+[package]
+name = \"demo\"  # not a lint
+
+  [ lints ]
+unused_variables = \"allow\"
+	shadowing=\"deny\"   # trailing comment
+
+[other]
+self_assignment = 3
+",
+    )
+    .unwrap();
+    assert_eq!(config.level(Lint::UnusedVariables), LintLevel::Allow);
+    assert_eq!(config.level(Lint::Shadowing), LintLevel::Deny);
+    assert_eq!(config.level(Lint::SelfAssignment), LintLevel::Warn);
+}
+
+#[test]
+fn empty_and_comment_only_files_keep_the_defaults() {
+    for text in [
+        "",
+        "\n\n",
+        "# nothing\n[lints]\n# unused_variables = \"deny\"\n",
+    ] {
+        let config = LintConfig::parse(text).unwrap();
+        for lint in Lint::all() {
+            assert_eq!(config.level(*lint), lint.default_level());
+        }
+    }
+}
+
+#[test]
+fn malformed_lines_are_errors() {
+    assert_eq!(
+        errors("[lints]\nunused_variables \"deny\"\n"),
+        [(
+            2,
+            "expected 'lint = \"level\"', found 'unused_variables \"deny\"'".to_string()
+        )]
+    );
+    assert_eq!(
+        errors("[lints\nunused_variables = \"deny\"\n"),
+        [(1, "unclosed table header '[lints'".to_string())]
+    );
+}
+
+#[test]
+fn unknown_lints_are_errors() {
+    assert_eq!(
+        errors("[lints]\nunused_variable = \"deny\"\n"),
+        [(2, "unknown lint 'unused_variable'".to_string())]
+    );
+}
+
+#[test]
+fn unknown_or_unquoted_levels_are_errors() {
+    for value in ["\"forbid\"", "deny", "\"deny", "'deny'", ""] {
+        assert_eq!(
+            errors(&format!("[lints]\nshadowing = {}\n", value)),
+            [(
+                2,
+                format!(
+                    "invalid level {} for lint 'shadowing', expected \"allow\", \"warn\" or \"deny\"",
+                    value
+                )
+            )],
+        );
+    }
+}
+
+#[test]
+fn lints_configured_twice_are_errors() {
+    assert_eq!(
+        errors("[lints]\nshadowing = \"deny\"\n\nshadowing = \"allow\"\nshadowing = \"warn\"\n"),
+        [
+            (
+                4,
+                "lint 'shadowing' is already configured on line 2".to_string()
+            ),
+            (
+                5,
+                "lint 'shadowing' is already configured on line 2".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn every_error_is_reported() {
+    let path = env::temp_dir().join(format!("nsharp-lint-config-{}", std::process::id()));
+    fs::create_dir_all(&path).unwrap();
+    let config_path = path.join(LintConfig::FILE_NAME);
+    fs::write(
+        &config_path,
+        "[lints]\nunknown = \"deny\"\nshadowing = \"loud\"\nunused_functions = \"allow\"\n",
+    )
+    .unwrap();
+    let error = LintConfig::for_source_file(&path.join("main.ns")).unwrap_err();
+    let config_path = config_path.canonicalize().unwrap();
+    fs::remove_dir_all(&path).unwrap();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "{0}:2: unknown lint 'unknown'\n\
+             {0}:3: invalid level \"loud\" for lint 'shadowing', expected \"allow\", \"warn\" or \"deny\"",
+            config_path.display()
+        )
+    );
+}
@@ -0,0 +1,159 @@
+use std::{cell::RefCell, rc::Rc};
+
+use nsharp_compiler::{
+    compilation_unit::CompilationUnit,
+    definitions::{
+        builtins::NativeRegistry,
+        eval::{ASTEval, Value},
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+    },
+    lint::config::LintConfig,
+    text::source_map::SourceMap,
+    typecheck::Type,
+};
+
+/// Compiles `source` with `natives` and runs it on `input`, returning what
+/// it printed and how it ended.
+fn run(
+    source: &str,
+    natives: &NativeRegistry,
+    input: &str,
+) -> Result<(String, Result<(), RuntimeError>), String> {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file("test.ns".to_string(), source.to_string());
+    let mut compilation_unit = CompilationUnit::compile_files_with_natives(
+        &source_map,
+        &[file_id],
+        &LintConfig::new(),
+        natives,
+    )
+    .map_err(|diagnostics_bag| {
+        diagnostics_bag
+            .borrow()
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
+    let mut output = Vec::new();
+    let result = {
+        let mut eval = ASTEval::new(&compilation_unit.global_scope)
+            .with_output(&mut output)
+            .with_input(input.as_bytes());
+        eval.strings = compilation_unit.strings().clone();
+        eval.run(&mut compilation_unit.ast)
+    };
+    Ok((String::from_utf8(output).unwrap(), result))
+}
+
+#[test]
+fn builtins_print_and_read_lines() {
+    let (output, result) = run(
+        "\
+let done = false;
+while done == false {
+    let name = read_line();
+    if name != null {
+        print(\"hello, \");
+        println(name);
+    } else {
+        done = true;
+    }
+}
+",
+        &NativeRegistry::with_builtins(),
+        "ada\ngrace\n",
+    )
+    .unwrap();
+    assert!(result.is_ok());
+    assert_eq!(output, "hello, ada\nhello, grace\n");
+}
+
+#[test]
+fn assert_and_exit_stop_the_program() {
+    let natives = NativeRegistry::with_builtins();
+    let (_, result) = run("assert(len(\"abc\") == 2);", &natives, "").unwrap();
+    assert_eq!(result.unwrap_err().kind, RuntimeErrorKind::AssertionFailed);
+    let (output, result) = run("println(\"a\");\nexit(2);\nprintln(\"b\");", &natives, "").unwrap();
+    assert_eq!(output, "a\n");
+    assert_eq!(result.unwrap_err().kind, RuntimeErrorKind::Exit { code: 2 });
+}
+
+#[test]
+fn hosts_register_typed_natives() {
+    let mut natives = NativeRegistry::with_builtins();
+    natives.register("double", &[("n", Type::Int)], Type::Int, |_, arguments| {
+        let n = arguments[0].expect_number()?;
+        n.checked_mul(2)
+            .map(Value::Number)
+            .ok_or(RuntimeErrorKind::Overflow)
+    });
+    let greeted = Rc::new(RefCell::new(Vec::new()));
+    natives.register("greet", &[("name", Type::String)], Type::String, {
+        let greeted = greeted.clone();
+        move |context, arguments| {
+            let name = context.string(arguments[0].expect_string()?).to_string();
+            let greeting = format!("hi {}", name);
+            greeted.borrow_mut().push(name);
+            Ok(context.intern(&greeting))
+        }
+    });
+
+    let (output, result) =
+        run("println(greet(\"n#\") + \" {double(21)}\");", &natives, "").unwrap();
+    assert!(result.is_ok());
+    assert_eq!(output, "hi n# 42\n");
+    assert_eq!(*greeted.borrow(), ["n#"]);
+
+    let errors = run("let _x = double(\"21\");", &natives, "").unwrap_err();
+    assert!(errors.contains("string"), "{}", errors);
+}
+
+#[test]
+fn natives_can_be_left_out_or_replaced() {
+    let errors = run("println(\"hi\");", &NativeRegistry::new(), "").unwrap_err();
+    assert!(errors.contains("println"), "{}", errors);
+
+    let mut natives = NativeRegistry::with_builtins();
+    natives.register(
+        "println",
+        &[("text", Type::String)],
+        Type::Void,
+        |context, arguments| {
+            let text = context.string(arguments[0].expect_string()?).to_uppercase();
+            context.write(&text)?;
+            Ok(Value::Void)
+        },
+    );
+    let (output, _) = run("println(\"quiet\");", &natives, "").unwrap();
+    assert_eq!(output, "QUIET");
+}
+
+#[test]
+fn natives_returning_the_wrong_type_fail() {
+    let mut natives = NativeRegistry::with_builtins();
+    natives.register("answer", &[], Type::Int, |_, _| Ok(Value::Boolean(true)));
+    natives.register("maybe", &[], Type::Nullable(Box::new(Type::Int)), |_, _| {
+        Ok(Value::Null)
+    });
+    let (_, result) = run("let _x = maybe();\nlet _y = answer();", &natives, "").unwrap();
+    let error = result.unwrap_err();
+    assert_eq!(
+        error.kind,
+        RuntimeErrorKind::NativeReturnType {
+            name: "answer".to_string(),
+            expected: "int".to_string(),
+            found: "bool",
+        }
+    );
+    assert_eq!(
+        error.kind.to_string(),
+        "native function 'answer' returned bool, but is declared to return int"
+    );
+}
+
+#[test]
+fn the_default_registry_is_empty() {
+    assert!(NativeRegistry::default().functions().is_empty());
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use nsharp_compiler::definitions::profiler::{Profile, Profiler};
+
+mod common;
+
+fn profile(source: &str) -> Profile {
+    let (source_map, mut compilation_unit) = common::compile("main.ns", source);
+    let (result, profile) = compilation_unit.run_with_profiler(Profiler::new(&source_map));
+    result.expect("expected the program to run");
+    profile
+}
+
+const PROGRAM: &str = "\
+func square(n: int) -> int {
+    return n * n;
+}
+
+func fib(n: int) -> int {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+let total = 0;
+let i = 0;
+while i < 10 {
+    total = total + square(i);
+    i = i + 1;
+}
+let _f = fib(10);
+";
+
+#[test]
+fn functions_record_calls_and_times() {
+    let profile = profile(PROGRAM);
+    let calls = |name: &str| {
+        profile
+            .functions
+            .iter()
+            .find(|function| function.name == name)
+            .unwrap_or_else(|| panic!("no profile for {}", name))
+            .calls
+    };
+    assert_eq!(calls("<top level>"), 1);
+    assert_eq!(calls("square"), 10);
+    assert_eq!(calls("fib"), 177);
+
+    let top_level = &profile
+        .functions
+        .iter()
+        .find(|f| f.name == "<top level>")
+        .unwrap();
+    for function in &profile.functions {
+        assert!(function.exclusive <= function.inclusive);
+        assert!(function.inclusive <= top_level.inclusive);
+    }
+    // Every moment is spent in exactly one function.
+    let exclusive: Duration = profile.functions.iter().map(|f| f.exclusive).sum();
+    assert_eq!(exclusive, top_level.inclusive);
+    assert!(profile
+        .functions
+        .windows(2)
+        .all(|pair| pair[0].exclusive >= pair[1].exclusive));
+}
+
+#[test]
+fn lines_record_hits_most_executed_first() {
+    let profile = profile(PROGRAM);
+    let hits = |line: usize| {
+        profile
+            .lines
+            .iter()
+            .find(|profile| profile.line == line)
+            .map_or(0, |profile| profile.hits)
+    };
+    assert_eq!(hits(2), 10);
+    assert_eq!(hits(14), 1);
+    assert_eq!(hits(15), 10);
+    assert_eq!(hits(16), 10);
+    assert_eq!(hits(6), 177);
+    assert_eq!(profile.lines[0].line, 6);
+    assert_eq!(profile.lines[0].source, "if n < 2 {");
+}
+
+#[test]
+fn folded_stacks_nest_calls_under_their_callers() {
+    let profile = profile(PROGRAM);
+    let folded = profile.folded_stacks();
+    let stacks: Vec<&str> = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert!(stacks.contains(&"<top level>"));
+    assert!(stacks.contains(&"<top level>;square"));
+    assert!(stacks.contains(&"<top level>;fib;fib;fib"));
+    assert!(!stacks.iter().any(|stack| stack.contains("square;")));
+    assert!(folded
+        .lines()
+        .all(|line| line.rsplit_once(' ').unwrap().1.parse::<u128>().is_ok()));
+}
+
+#[test]
+fn report_lists_functions_then_lines() {
+    let report = profile(PROGRAM).report();
+    let function_header = report.find("function").unwrap();
+    let line_header = report.find("line").unwrap();
+    assert!(function_header < line_header);
+    assert!(report.contains("main.ns:15  total = total + square(i);"));
+}
@@ -0,0 +1,109 @@
+use nsharp_compiler::{
+    definitions::{lexer::TokenKind, runtime_error::RuntimeErrorKind},
+    repl::{Session, SessionError},
+    typecheck::Type,
+};
+
+/// Evaluates the input, returning its value and type as the REPL shows
+/// them.
+fn evaluate(session: &mut Session, input: &str) -> Option<String> {
+    let evaluation = session
+        .evaluate(input)
+        .unwrap_or_else(|error| panic!("'{}' failed: {:?}", input, error));
+    evaluation
+        .value
+        .map(|(value, ty)| format!("{}: {}", value, ty))
+}
+
+#[test]
+fn functions_and_globals_persist_across_inputs() {
+    let mut session = Session::new();
+    evaluate(
+        &mut session,
+        "func square(n: int) -> int {\n    return n * n;\n}",
+    );
+    assert_eq!(evaluate(&mut session, "let x = square(4)"), None);
+    assert_eq!(evaluate(&mut session, "x + 1"), Some("17: int".to_string()));
+    evaluate(&mut session, "x = x * 2");
+    assert_eq!(evaluate(&mut session, "x"), Some("32: int".to_string()));
+    assert_eq!(
+        evaluate(&mut session, "let name = \"n#\"\nname + \"!\""),
+        Some("\"n#!\": string".to_string())
+    );
+    assert_eq!(
+        evaluate(&mut session, "len(name)"),
+        Some("2: int".to_string())
+    );
+}
+
+#[test]
+fn failed_inputs_leave_the_state_unchanged() {
+    let mut session = Session::new();
+    evaluate(&mut session, "let count = 1");
+    assert!(matches!(
+        session.evaluate("count = 2\nlet broken = count / 0"),
+        Err(SessionError::Runtime(error)) if error.kind == RuntimeErrorKind::DivisionByZero
+    ));
+    assert!(matches!(
+        session.evaluate("broken"),
+        Err(SessionError::Compile(_))
+    ));
+    assert_eq!(evaluate(&mut session, "count"), Some("1: int".to_string()));
+}
+
+#[test]
+fn type_of_does_not_evaluate() {
+    let mut session = Session::new();
+    evaluate(&mut session, "let total = 0");
+    assert_eq!(session.type_of("total = total + 1").unwrap(), Type::Int);
+    assert_eq!(session.type_of("total > 2").unwrap(), Type::Bool);
+    assert!(matches!(
+        session.type_of("let other = 1"),
+        Err(SessionError::NotAnExpression)
+    ));
+    assert_eq!(evaluate(&mut session, "total"), Some("0: int".to_string()));
+}
+
+#[test]
+fn unclosed_delimiters_are_incomplete() {
+    assert!(Session::is_incomplete("func f() -> int {"));
+    assert!(Session::is_incomplete("let x = (1 +"));
+    assert!(!Session::is_incomplete("while false {}"));
+    assert!(!Session::is_incomplete("let x = 1"));
+}
+
+#[test]
+fn reset_forgets_everything() {
+    let mut session = Session::new();
+    evaluate(&mut session, "let x = 1");
+    session.reset();
+    assert!(matches!(
+        session.evaluate("x"),
+        Err(SessionError::Compile(_))
+    ));
+}
+
+#[test]
+fn tokens_skip_whitespace() {
+    let mut session = Session::new();
+    let kinds: Vec<String> = session
+        .tokens("let a = 1;")
+        .iter()
+        .map(|token| token.kind.to_string())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "Let",
+            "Identifier",
+            "Equals",
+            "Number(1)",
+            "Semicolon",
+            "Eof"
+        ]
+    );
+    assert!(session
+        .tokens("a // note")
+        .iter()
+        .any(|token| token.kind == TokenKind::Comment));
+}
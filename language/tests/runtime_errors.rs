@@ -0,0 +1,163 @@
+use nsharp_compiler::definitions::{
+    limits::DEFAULT_MAX_CALL_DEPTH,
+    runtime_error::{RuntimeError, RuntimeErrorKind},
+};
+
+mod common;
+
+/// Compiles and runs `source`, returning its runtime error along with the
+/// text of the error's span and of each stack frame's span.
+fn run(source: &str) -> Option<(RuntimeError, String, Vec<String>)> {
+    let (source_map, mut compilation_unit) = common::compile("test.ns", source);
+    let error = compilation_unit.run().err()?;
+    let span = source_map.literal(&error.span).to_string();
+    let frames = error
+        .stack_trace
+        .iter()
+        .map(|frame| source_map.literal(&frame.span).to_string())
+        .collect();
+    Some((error, span, frames))
+}
+
+#[test]
+fn programs_without_errors_run_to_completion() {
+    assert!(run("let _x = 10 / 2;").is_none());
+}
+
+#[test]
+fn division_by_zero_has_a_stack_trace() {
+    let (error, span, frames) = run("\
+func divide(a: int, b: int) -> int {
+    return a / b;
+}
+
+func average(total: int, count: int) -> int {
+    return divide(total, count);
+}
+
+let _ok = average(10, 2);
+let _broken = average(10, 0);
+")
+    .unwrap();
+    assert_eq!(error.kind, RuntimeErrorKind::DivisionByZero);
+    assert_eq!(span, "a / b");
+    let functions: Vec<_> = error
+        .stack_trace
+        .iter()
+        .map(|frame| frame.function.as_deref())
+        .collect();
+    assert_eq!(functions, [Some("divide"), Some("average"), None]);
+    assert_eq!(frames, ["a / b", "divide(total, count)", "average(10, 0)"]);
+}
+
+#[test]
+fn overflow_is_reported() {
+    let (error, span, _) = run("let big = 9223372036854775807;\nlet _x = big + 1;").unwrap();
+    assert_eq!(error.kind, RuntimeErrorKind::Overflow);
+    assert_eq!(span, "big + 1");
+}
+
+#[test]
+fn runaway_recursion_overflows_the_stack() {
+    let (error, _, frames) = run("\
+func recurse(n: int) -> int {
+    return recurse(n + 1);
+}
+
+let _x = recurse(0);
+")
+    .unwrap();
+    assert_eq!(
+        error.kind,
+        RuntimeErrorKind::StackOverflow {
+            depth: DEFAULT_MAX_CALL_DEPTH
+        }
+    );
+    assert_eq!(frames.len(), DEFAULT_MAX_CALL_DEPTH + 1);
+    assert_eq!(error.calls().len(), 2);
+    assert_eq!(error.to_diagnostic().labels.len(), 2);
+}
+
+#[test]
+fn errors_stop_the_program() {
+    let (error, span, _) = run("\
+let zero = 0;
+let i = 0;
+while i < 10 {
+    i = i + 1;
+    if i == 5 {
+        let _x = i / zero;
+    }
+}
+let _unreached = 1 / 0 + 1;
+")
+    .unwrap();
+    assert_eq!(error.kind, RuntimeErrorKind::DivisionByZero);
+    assert_eq!(span, "i / zero");
+}
+
+#[test]
+fn globals_are_undeclared_until_their_let_runs() {
+    let (error, span, frames) = run("\
+let early = read();
+
+func read() -> int {
+    return early;
+}
+")
+    .unwrap();
+    assert_eq!(
+        error.kind,
+        RuntimeErrorKind::UndeclaredVariable {
+            name: "early".to_string()
+        }
+    );
+    assert_eq!(span, "early");
+    assert_eq!(frames, ["early", "read()"]);
+
+    let (error, span, _) = run("\
+let early = write();
+
+func write() -> int {
+    early = 1;
+    return 2;
+}
+")
+    .unwrap();
+    assert_eq!(
+        error.kind,
+        RuntimeErrorKind::UndeclaredVariable {
+            name: "early".to_string()
+        }
+    );
+    assert_eq!(span, "early = 1");
+}
+
+#[test]
+fn calls_only_see_their_own_locals_and_globals() {
+    assert!(run("\
+let total = 0;
+
+func count(n: int) -> int {
+    let seen = n;
+    if n > 0 {
+        let inner = count(n - 1);
+        total = total + 1;
+        if seen != n {
+            return 1 / 0;
+        }
+        return inner + 1;
+    }
+    return 0;
+}
+
+let result = count(20);
+if result != 20 {
+    let _ = 1 / 0;
+}
+if total != 20 {
+    let _ = 1 / 0;
+}
+")
+    .is_none());
+}
@@ -0,0 +1,90 @@
+use nsharp_compiler::text::{ColumnKind, LineColumn, SourceText};
+
+const KINDS: [ColumnKind; 3] = [ColumnKind::Byte, ColumnKind::Char, ColumnKind::Utf16];
+
+/// `é` is two bytes and one UTF-16 unit, `😀` four bytes and two UTF-16
+/// units, and the lines end in CRLF.
+const TEXT: &str = "let é = 1;\r\n\"😀é😀\"\r\n\r\nend😀";
+
+#[test]
+fn offsets_round_trip_through_every_column_kind() {
+    let text = SourceText::new(TEXT.to_string());
+    for kind in KINDS {
+        for position in 0..=TEXT.len() {
+            // Between a `\r` and its `\n` is not a position on any line.
+            if !TEXT.is_char_boundary(position) || TEXT[..position].ends_with('\r') {
+                continue;
+            }
+            let line_column = text.line_column(position, kind);
+            assert_eq!(
+                text.offset(line_column, kind),
+                position,
+                "{:?} at {:?}",
+                kind,
+                line_column
+            );
+        }
+    }
+}
+
+#[test]
+fn columns_count_in_their_unit() {
+    let text = SourceText::new(TEXT.to_string());
+    // Just past `"😀é`.
+    let position = TEXT.find("😀é").unwrap() + "😀é".len();
+    let columns = KINDS.map(|kind| text.line_column(position, kind));
+    assert_eq!(
+        columns,
+        [
+            LineColumn { line: 1, column: 7 },
+            LineColumn { line: 1, column: 3 },
+            LineColumn { line: 1, column: 4 },
+        ]
+    );
+    // The last line has no terminator.
+    assert_eq!(
+        text.line_column(TEXT.len(), ColumnKind::Utf16),
+        LineColumn { line: 3, column: 5 }
+    );
+}
+
+#[test]
+fn positions_inside_characters_resolve_to_their_start() {
+    let text = SourceText::new(TEXT.to_string());
+    let emoji = TEXT.find('😀').unwrap();
+    for position in emoji + 1..emoji + 4 {
+        assert_eq!(
+            text.line_column(position, ColumnKind::Char),
+            LineColumn { line: 1, column: 1 }
+        );
+    }
+    // Between the two halves of the surrogate pair.
+    assert_eq!(
+        text.offset(LineColumn { line: 1, column: 2 }, ColumnKind::Utf16),
+        emoji
+    );
+}
+
+#[test]
+fn offsets_past_the_end_clamp() {
+    let text = SourceText::new(TEXT.to_string());
+    // The end of `let é = 1;`, before its CRLF.
+    assert_eq!(
+        text.offset(
+            LineColumn {
+                line: 0,
+                column: 99
+            },
+            ColumnKind::Char
+        ),
+        "let é = 1;".len()
+    );
+    assert_eq!(
+        text.offset(LineColumn { line: 2, column: 5 }, ColumnKind::Byte),
+        TEXT.find("\r\n\r\n").unwrap() + 2
+    );
+    assert_eq!(
+        text.offset(LineColumn { line: 9, column: 0 }, ColumnKind::Utf16),
+        TEXT.len()
+    );
+}
@@ -0,0 +1,331 @@
+//! Compiles every `.ns` file under `tests/ui` and checks its diagnostics
+//! against the annotations in it, then runs it and compares its stdout and
+//! exit code with the `.stdout` file next to it.
+//!
+//! An annotation is a comment naming the severity and part of the message
+//! of a diagnostic on its line, or with one `^` per line, on a line above:
+//!
+//! ```text
+//! let total = count + 1; //~ ERROR Undeclared variable 'count'
+//! let unused = 1;
+//! //~^ WARN unused_variables
+//! ```
+//!
+//! The text after `ERROR`, `WARN` or `NOTE` matches a diagnostic whose
+//! message contains it, or whose error code or lint name it is. Every error
+//! and warning must be annotated. A `.stdout` file starts with an
+//! `exit code: N` line, followed by the program's output. A test may also
+//! have a `.stderr` file, holding its diagnostics the way they are printed
+//! to a terminal without colors.
+//!
+//! `cargo test --test ui -- --bless` regenerates the `.stdout` and existing
+//! `.stderr` files; create an empty `.stderr` file to start snapshotting one.
+//! Other arguments only run the tests whose path contains them.
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use nsharp_compiler::{
+    compilation_unit::CompilationUnit,
+    diagnostics::{printer::DiagnosticsPrinter, Diagnostic, Severity},
+    lint::config::LintConfig,
+    text::source_map::SourceMap,
+};
+
+/// Programs running longer than this are killed, and fail their test.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Annotation {
+    line: usize,
+    severity: Severity,
+    message: String,
+}
+
+impl Annotation {
+    fn parse_all(source: &str) -> Result<Vec<Annotation>, String> {
+        let mut annotations = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let Some(position) = line.find("//~") else {
+                continue;
+            };
+            let rest = &line[position + "//~".len()..];
+            let carets = rest.len() - rest.trim_start_matches('^').len();
+            let rest = rest[carets..].trim();
+            let (severity, message) = rest.split_once(' ').unwrap_or((rest, ""));
+            let severity = match severity {
+                "ERROR" => Severity::Error,
+                "WARN" => Severity::Warning,
+                "NOTE" => Severity::Note,
+                _ => {
+                    return Err(format!(
+                        "line {}: malformed annotation '{}'",
+                        index + 1,
+                        line
+                    ))
+                }
+            };
+            if carets > index {
+                return Err(format!(
+                    "line {}: annotation points above the file",
+                    index + 1
+                ));
+            }
+            annotations.push(Annotation {
+                line: index + 1 - carets,
+                severity,
+                message: message.trim().to_string(),
+            });
+        }
+        Ok(annotations)
+    }
+
+    fn matches(&self, line: usize, diagnostic: &Diagnostic) -> bool {
+        self.line == line
+            && self.severity == diagnostic.severity
+            && (diagnostic.message.contains(&self.message)
+                || diagnostic.code.map(|code| code.to_string()).as_deref() == Some(&self.message)
+                || diagnostic.lint == Some(self.message.as_str()))
+    }
+}
+
+/// Compiles the file and checks its diagnostics against its annotations, and
+/// how they render against its `.stderr` file if it has one.
+fn check_diagnostics(path: &Path, source: &str, bless: bool, failures: &mut Vec<String>) {
+    let mut annotations = match Annotation::parse_all(source) {
+        Ok(annotations) => annotations,
+        Err(error) => {
+            failures.push(error);
+            return;
+        }
+    };
+    let lint_config = match LintConfig::for_source_file(path) {
+        Ok(lint_config) => lint_config,
+        Err(error) => {
+            failures.push(error.to_string());
+            return;
+        }
+    };
+    // Relative, so that rendered diagnostics don't depend on the checkout.
+    let name = path
+        .strip_prefix(env!("CARGO_MANIFEST_DIR"))
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file(name, source.to_string());
+    let diagnostics_bag =
+        match CompilationUnit::compile_with_lints(&source_map, file_id, &lint_config) {
+            Ok(compilation_unit) => compilation_unit.diagnostics_bag,
+            Err(diagnostics_bag) => diagnostics_bag,
+        };
+    let text = &source_map.get(file_id).text;
+    for diagnostic in &diagnostics_bag.borrow().diagnostics {
+        let line = text.line_index(diagnostic.span.start) + 1;
+        match annotations
+            .iter()
+            .position(|annotation| annotation.matches(line, diagnostic))
+        {
+            Some(index) => {
+                annotations.remove(index);
+            }
+            None if diagnostic.severity == Severity::Note => {}
+            None => failures.push(format!(
+                "line {}: unexpected {}: {}",
+                line, diagnostic.severity, diagnostic.message
+            )),
+        }
+    }
+    for annotation in annotations {
+        failures.push(format!(
+            "line {}: expected {} not reported: {}",
+            annotation.line, annotation.severity, annotation.message
+        ));
+    }
+
+    let stderr_path = path.with_extension("stderr");
+    if stderr_path.exists() {
+        let mut diagnostics_bag = diagnostics_bag.borrow_mut();
+        diagnostics_bag.sort();
+        let printer =
+            DiagnosticsPrinter::new(&source_map, &diagnostics_bag.diagnostics).with_colors(false);
+        let rendered: String = diagnostics_bag
+            .diagnostics
+            .iter()
+            .map(|diagnostic| format!("{}\n", printer.stringify_diagnostic(diagnostic)))
+            .collect();
+        compare_snapshot(&stderr_path, &rendered, bless, failures);
+    }
+}
+
+/// Runs the file with the compiler's binary, returning the contents its
+/// `.stdout` file should have.
+fn run(path: &Path) -> Result<String, String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nsharp-compiler"))
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| format!("could not run the compiler: {}", error))?;
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+    let deadline = Instant::now() + TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|error| error.to_string())? {
+            break status;
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {}s", TIMEOUT.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let output = reader
+        .join()
+        .unwrap()
+        .map_err(|error| format!("could not read stdout: {}", error))?;
+    let exit_code = status
+        .code()
+        .map_or_else(|| "signal".to_string(), |code| code.to_string());
+    Ok(format!("exit code: {}\n{}", exit_code, output))
+}
+
+/// Runs the file and compares the result with its `.stdout` file, or
+/// overwrites that file when blessing.
+fn check_stdout(path: &Path, bless: bool, failures: &mut Vec<String>) {
+    let actual = match run(path) {
+        Ok(actual) => actual,
+        Err(error) => {
+            failures.push(error);
+            return;
+        }
+    };
+    compare_snapshot(&path.with_extension("stdout"), &actual, bless, failures);
+}
+
+/// Compares `actual` with the contents of `snapshot_path`, or overwrites
+/// that file when blessing.
+fn compare_snapshot(snapshot_path: &Path, actual: &str, bless: bool, failures: &mut Vec<String>) {
+    let expected = fs::read_to_string(snapshot_path).ok();
+    if expected.as_deref() == Some(actual) {
+        return;
+    }
+    if bless {
+        if let Err(error) = fs::write(snapshot_path, actual) {
+            failures.push(format!(
+                "could not write {}: {}",
+                snapshot_path.display(),
+                error
+            ));
+        }
+        return;
+    }
+    match expected {
+        Some(expected) => failures.push(format!(
+            "{} differs\n--- expected\n{}--- actual\n{}",
+            snapshot_path.display(),
+            expected,
+            actual
+        )),
+        None => failures.push(format!(
+            "missing {}, run with --bless to create it",
+            snapshot_path.display()
+        )),
+    }
+}
+
+fn collect_tests(directory: &Path, tests: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_tests(&path, tests);
+        } else if path.extension().is_some_and(|extension| extension == "ns") {
+            tests.push(path);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut bless = false;
+    let mut filters = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--bless" => bless = true,
+            // Flags cargo passes to every test binary.
+            _ if arg.starts_with('-') => {}
+            _ => filters.push(arg),
+        }
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("ui");
+    let mut tests = Vec::new();
+    collect_tests(&root, &mut tests);
+    tests.sort();
+    tests.retain(|path| {
+        filters.is_empty()
+            || filters
+                .iter()
+                .any(|filter| path.to_string_lossy().contains(filter.as_str()))
+    });
+
+    println!("\nrunning {} ui tests", tests.len());
+    let mut failed = Vec::new();
+    for path in &tests {
+        let name = path
+            .strip_prefix(&root)
+            .unwrap_or(path)
+            .display()
+            .to_string();
+        let mut failures = Vec::new();
+        match fs::read_to_string(path) {
+            Ok(source) => {
+                check_diagnostics(path, &source, bless, &mut failures);
+                check_stdout(path, bless, &mut failures);
+            }
+            Err(error) => failures.push(format!("could not read the test: {}", error)),
+        }
+        if failures.is_empty() {
+            println!("test {} ... ok", name);
+        } else {
+            println!("test {} ... FAILED", name);
+            failed.push((name, failures));
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for (name, failures) in &failed {
+            println!("\n---- {} ----", name);
+            for failure in failures {
+                println!("{}", failure);
+            }
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        tests.len() - failed.len(),
+        failed.len()
+    );
+    if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,347 @@
+use std::time::{Duration, Instant};
+
+use nsharp_compiler::{
+    compilation_unit::CompilationUnit,
+    definitions::{
+        bytecode::Bytecode,
+        eval::{ASTEval, Value},
+        limits::Limits,
+        runtime_error::{RuntimeError, RuntimeErrorKind},
+        vm::VirtualMachine,
+    },
+};
+
+mod common;
+
+/// What running a program printed, and how it ended.
+type Outcome = (String, Result<(), RuntimeError>);
+
+/// Runs the program with the tree walker and with the virtual machine.
+fn run_both(compilation_unit: &mut CompilationUnit, limits: Limits) -> (Outcome, Outcome) {
+    let mut output = Vec::new();
+    let result = {
+        let mut eval = ASTEval::new(&compilation_unit.global_scope)
+            .with_limits(limits)
+            .with_output(&mut output);
+        eval.strings = compilation_unit.strings().clone();
+        eval.run(&mut compilation_unit.ast)
+    };
+    let tree_walked = (String::from_utf8(output).unwrap(), result);
+
+    let bytecode = Bytecode::compile(
+        &compilation_unit.ast,
+        &compilation_unit.global_scope,
+        limits,
+    );
+    let mut output = Vec::new();
+    let result = {
+        let mut vm =
+            VirtualMachine::new(&bytecode, &compilation_unit.global_scope).with_output(&mut output);
+        vm.strings = compilation_unit.strings().clone();
+        vm.run()
+    };
+    (tree_walked, (String::from_utf8(output).unwrap(), result))
+}
+
+fn assert_same(source: &str, limits: Limits) -> Outcome {
+    let (_, mut compilation_unit) = common::compile("test.ns", source);
+    let (tree_walked, executed) = run_both(&mut compilation_unit, limits);
+    assert_eq!(tree_walked, executed, "with {:?}", limits);
+    executed
+}
+
+const PROGRAM: &str = "\
+const GREETING: string = \"hello\";
+let calls = 0;
+
+func fib(n: int) -> int {
+    calls = calls + 1;
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+func describe(n: int) -> string {
+    let parity = if n % 2 == 0 { \"even\" } else { \"odd\" };
+    let size = {
+        let big = n > 10;
+        if big { \"big\" } else { \"small\" }
+    };
+    return \"{n} is {parity} and {size}\";
+}
+
+func first_letters(text: string?) -> string {
+    if text == null {
+        return \"nothing\";
+    }
+    return text + \"!\";
+}
+
+let i = 0;
+let total = 0;
+while i < 12 {
+    let square = i * i;
+    if square % 3 == 0 {
+        total = total + square;
+    } else {
+        total = total - 1;
+    }
+    i = i + 1;
+}
+println(\"{GREETING}, total {total}, fib {fib(10)} in {calls} calls\");
+println(describe(7));
+println(describe(12));
+println(first_letters(null) + \" \" + first_letters(\"abc\"));
+let unset: int;
+unset = -(2 ** 10) | 3 ^ 5 & 6;
+println(\"{unset} {\"abc\" < \"abd\"} {total >= 100} {(1 + 2) * 3}\");
+";
+
+#[test]
+fn programs_behave_the_same() {
+    let (output, result) = assert_same(PROGRAM, Limits::new());
+    assert!(result.is_ok());
+    assert_eq!(
+        output,
+        "\
+hello, total 118, fib 55 in 177 calls
+7 is odd and small
+12 is even and big
+nothing abc!
+-1017 true true 9
+"
+    );
+}
+
+#[test]
+fn runtime_errors_are_the_same() {
+    let programs = [
+        "func f(n: int) -> int { return 10 / n; }\nlet _x = f(1) + f(0);",
+        "let g = f();\nfunc f() -> int { return g; }",
+        "let g = f();\nfunc f() -> int { g = 2; return 1; }",
+        "let x = 9223372036854775807;\nlet _y = { x + 1 };",
+        "let _x = 2 ** -1;",
+        "func f(n: int) -> int { return f(n + 1); }\nlet _x = f(0);",
+        "println(\"before\");\nassert(1 == 2);\nprintln(\"after\");",
+    ];
+    for program in programs {
+        let (_, result) = assert_same(program, Limits::new());
+        assert!(result.is_err(), "{}", program);
+    }
+}
+
+#[test]
+fn every_step_limit_stops_at_the_same_expression() {
+    let (_, mut compilation_unit) = common::compile("test.ns", PROGRAM);
+    let mut limit = 0;
+    loop {
+        let limits = Limits::new().with_max_steps(limit);
+        let (tree_walked, executed) = run_both(&mut compilation_unit, limits);
+        assert_eq!(tree_walked, executed, "with a limit of {} steps", limit);
+        if executed.1.is_ok() {
+            break;
+        }
+        limit += 1;
+    }
+    assert!(limit > 500);
+}
+
+#[test]
+fn heap_and_call_depth_limits_are_the_same() {
+    let source = "\
+func grow(text: string, n: int) -> string {
+    if n == 0 {
+        return text;
+    }
+    let longer = text + \"{n}\";
+    return grow(longer, n - 1);
+}
+let result = grow(\"x\", 20);
+println(result);
+";
+    let (_, mut compilation_unit) = common::compile("test.ns", source);
+    for max_heap_size in (0..2000).step_by(7) {
+        let limits = Limits::new().with_max_heap_size(max_heap_size);
+        let (tree_walked, executed) = run_both(&mut compilation_unit, limits);
+        assert_eq!(
+            tree_walked, executed,
+            "with a heap of {} bytes",
+            max_heap_size
+        );
+    }
+    for max_call_depth in 0..25 {
+        let limits = Limits::new().with_max_call_depth(max_call_depth);
+        let (tree_walked, executed) = run_both(&mut compilation_unit, limits);
+        assert_eq!(tree_walked, executed, "with a depth of {}", max_call_depth);
+        if max_call_depth < 21 {
+            assert_eq!(
+                executed.1.unwrap_err().kind,
+                RuntimeErrorKind::StackOverflow {
+                    depth: max_call_depth
+                }
+            );
+        }
+    }
+}
+
+#[test]
+fn strings_are_collected_at_the_same_points() {
+    let source = "\
+func pad(text: string, n: int) -> string {
+    while n > 0 {
+        text = text + \".\";
+        n = n - 1;
+    }
+    return text;
+}
+let kept = \"\";
+let i = 0;
+while i < 300 {
+    let line = pad(\"{i}\", i % 40);
+    if i % 10 == 0 {
+        kept = kept + line;
+    }
+    i = i + 1;
+}
+println(kept);
+";
+    let (_, mut compilation_unit) = common::compile("test.ns", source);
+    let mut completed = 0;
+    for max_heap_size in (0..80_000).step_by(1999) {
+        let limits = Limits::new().with_max_heap_size(max_heap_size);
+        let (tree_walked, executed) = run_both(&mut compilation_unit, limits);
+        assert_eq!(
+            tree_walked, executed,
+            "with a heap of {} bytes",
+            max_heap_size
+        );
+        completed += executed.1.is_ok() as usize;
+    }
+    // Far more strings are made than fit, so most runs only complete
+    // because strings get freed.
+    assert!(completed > 25, "{} runs completed", completed);
+}
+
+#[test]
+fn the_virtual_machine_runs_loops_without_lookups() {
+    let (_, compilation_unit) = common::compile("test.ns", 
+        "func sum(n: int) -> int {\n    let total = 0;\n    while n > 0 {\n        total = total + n;\n        n = n - 1;\n    }\n    return total;\n}\n",
+    );
+    let bytecode = Bytecode::compile(
+        &compilation_unit.ast,
+        &compilation_unit.global_scope,
+        Limits::new(),
+    );
+    let function_idx = compilation_unit
+        .global_scope
+        .lookup_function("sum")
+        .unwrap();
+    let chunk = bytecode.functions[function_idx].as_ref().unwrap();
+    // The parameter and the local.
+    assert_eq!(chunk.locals, 2);
+
+    let mut vm = VirtualMachine::new(&bytecode, &compilation_unit.global_scope);
+    let span = chunk.spans[0];
+    let result = vm.call_function(function_idx, &[Value::Number(100_000)], span);
+    assert_eq!(result, Ok(Value::Number(5_000_050_000)));
+}
+
+#[test]
+fn loops_leave_nothing_on_the_stack() {
+    let (_, compilation_unit) = common::compile(
+        "test.ns",
+        "\
+func count(n: int) -> int {
+    let total = 0;
+    while n > 0 {
+        if n % 2 == 0 {
+            total = total + 1;
+        }
+        n = n - 1;
+    }
+    return total;
+}
+let i = 0;
+let evens = 0;
+while i < 100000 {
+    let j = 0;
+    while j < 2 {
+        j = j + 1;
+    }
+    { evens = evens + count(3); }
+    i = i + 1;
+}
+",
+    );
+    let bytecode = Bytecode::compile(
+        &compilation_unit.ast,
+        &compilation_unit.global_scope,
+        Limits::new(),
+    );
+    let mut vm = VirtualMachine::new(&bytecode, &compilation_unit.global_scope);
+    assert_eq!(vm.run(), Ok(()));
+    let evens = compilation_unit
+        .global_scope
+        .lookup_global_variable("evens")
+        .unwrap();
+    assert_eq!(vm.variable(evens), Some(Value::Number(100_000)));
+    assert!(vm.peak_stack_len() < 10, "{}", vm.peak_stack_len());
+}
+
+/// How long running the program once takes with each engine.
+fn time_both(source: &str) -> (Duration, Duration) {
+    let (_, mut compilation_unit) = common::compile("test.ns", source);
+    let start = Instant::now();
+    let mut eval = ASTEval::new(&compilation_unit.global_scope);
+    eval.run(&mut compilation_unit.ast).unwrap();
+    let tree_walked = start.elapsed();
+
+    let bytecode = Bytecode::compile(
+        &compilation_unit.ast,
+        &compilation_unit.global_scope,
+        Limits::new(),
+    );
+    let start = Instant::now();
+    let mut vm = VirtualMachine::new(&bytecode, &compilation_unit.global_scope);
+    vm.run().unwrap();
+    (tree_walked, start.elapsed())
+}
+
+/// Timing depends on the machine and the build, so this only runs when
+/// asked for: `cargo test --release --test vm -- --ignored`.
+#[test]
+#[ignore]
+fn the_virtual_machine_is_ten_times_faster_on_loops() {
+    let (tree_walked, executed) = time_both(
+        "\
+func collatz(n: int) -> int {
+    let steps = 0;
+    while n != 1 {
+        if n % 2 == 0 {
+            n = n / 2;
+        } else {
+            n = 3 * n + 1;
+        }
+        steps = steps + 1;
+    }
+    return steps;
+}
+let i = 1;
+let longest = 0;
+while i < 10000 {
+    let steps = collatz(i);
+    if steps > longest {
+        longest = steps;
+    }
+    i = i + 1;
+}
+",
+    );
+    let speedup = tree_walked.as_secs_f64() / executed.as_secs_f64();
+    println!(
+        "tree walker {:?}, virtual machine {:?}, {:.1}x",
+        tree_walked, executed, speedup
+    );
+    assert!(speedup >= 10.0, "only {:.1}x faster", speedup);
+}